@@ -0,0 +1,138 @@
+-- The same schema as the sqlite migrations up to 20261018170546_add-dry-run, with integers as
+-- BIGINT so they decode as i64 and flags as BOOLEAN.
+CREATE TABLE message_responses (
+  guild_id                    BIGINT NOT NULL,
+  channel_id                  BIGINT NOT NULL,
+  response                    BIGINT NOT NULL,
+  timeout_duration            BIGINT,
+  quarantine_role_id          BIGINT,
+  delete_message              BOOLEAN NOT NULL DEFAULT FALSE,
+  purge_window                BIGINT,
+  delete_message_days         BIGINT NOT NULL DEFAULT 7,
+  reason                      TEXT,
+  new_account_age             BIGINT,
+  new_account_response        BIGINT,
+  established_member_age      BIGINT,
+  established_member_response BIGINT,
+  -- Bitmask of the trigger kinds that count in the channel, messages only by default
+  trigger_kinds               BIGINT NOT NULL DEFAULT 1,
+  dry_run                     BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY(guild_id, channel_id)
+);
+
+CREATE TABLE logging_channels (
+  guild_id   BIGINT NOT NULL,
+  channel_id BIGINT NOT NULL,
+  PRIMARY KEY(guild_id)
+);
+
+CREATE TABLE quarantined_members (
+  guild_id           BIGINT NOT NULL,
+  user_id            BIGINT NOT NULL,
+  quarantine_role_id BIGINT NOT NULL,
+  -- Comma separated list of the role IDs removed from the member
+  removed_role_ids   TEXT NOT NULL,
+  PRIMARY KEY(guild_id, user_id)
+);
+
+CREATE TABLE message_templates (
+  guild_id   BIGINT NOT NULL,
+  -- 0 for templates that apply to the whole guild
+  channel_id BIGINT NOT NULL,
+  kind       BIGINT NOT NULL,
+  template   TEXT NOT NULL,
+  PRIMARY KEY(guild_id, channel_id, kind)
+);
+
+CREATE TABLE escalation_policies (
+  guild_id     BIGINT NOT NULL,
+  -- Comma separated list of responses, one for each offense
+  ladder       TEXT NOT NULL,
+  -- Seconds before an offense stops counting towards the ladder
+  decay_window BIGINT NOT NULL,
+  PRIMARY KEY(guild_id)
+);
+
+CREATE TABLE offenses (
+  guild_id   BIGINT NOT NULL,
+  user_id    BIGINT NOT NULL,
+  -- Unix timestamp of the offense
+  created_at BIGINT NOT NULL
+);
+
+CREATE INDEX offenses_guild_id_user_id ON offenses(guild_id, user_id);
+
+CREATE TABLE incidents (
+  id             BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  guild_id       BIGINT NOT NULL,
+  channel_id     BIGINT NOT NULL,
+  user_id        BIGINT NOT NULL,
+  action         BIGINT NOT NULL,
+  -- Start of the message that triggered the honeypot
+  content        TEXT NOT NULL,
+  -- Unix timestamp of the incident
+  created_at     BIGINT NOT NULL,
+  outcome        TEXT,
+  error          TEXT,
+  false_positive BOOLEAN NOT NULL DEFAULT FALSE,
+  trigger_kind   BIGINT NOT NULL DEFAULT 0,
+  dry_run        BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX incidents_guild_id_created_at ON incidents(guild_id, created_at);
+
+CREATE TABLE exemptions (
+  guild_id  BIGINT NOT NULL,
+  -- 0 for users, 1 for roles
+  kind      BIGINT NOT NULL,
+  target_id BIGINT NOT NULL,
+  PRIMARY KEY(guild_id, kind, target_id)
+);
+
+CREATE TABLE guild_settings (
+  guild_id          BIGINT NOT NULL,
+  exemption_mode    BIGINT NOT NULL DEFAULT 0,
+  exempt_moderators BOOLEAN NOT NULL DEFAULT FALSE,
+  dry_run           BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY(guild_id)
+);
+
+CREATE TABLE role_responses (
+  guild_id            BIGINT NOT NULL,
+  role_id             BIGINT NOT NULL,
+  response            BIGINT NOT NULL,
+  timeout_duration    BIGINT,
+  quarantine_role_id  BIGINT,
+  purge_window        BIGINT,
+  delete_message_days BIGINT NOT NULL DEFAULT 7,
+  reason              TEXT,
+  PRIMARY KEY(guild_id, role_id)
+);
+
+CREATE TABLE burst_detection (
+  guild_id           BIGINT NOT NULL,
+  -- How many distinct channels the same content has to be posted in
+  channel_count      BIGINT NOT NULL,
+  -- Seconds, quoted since WINDOW is a keyword
+  "window"           BIGINT NOT NULL,
+  response           BIGINT NOT NULL,
+  timeout_duration   BIGINT,
+  quarantine_role_id BIGINT,
+  delete_messages    BOOLEAN NOT NULL DEFAULT FALSE,
+  PRIMARY KEY(guild_id)
+);
+
+CREATE TABLE content_rules (
+  id                 BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+  guild_id           BIGINT NOT NULL,
+  kind               BIGINT NOT NULL,
+  -- Regex, comma separated keywords or domains, or the mention count for mass mentions
+  pattern            TEXT NOT NULL,
+  response           BIGINT NOT NULL,
+  timeout_duration   BIGINT,
+  quarantine_role_id BIGINT,
+  delete_message     BOOLEAN NOT NULL DEFAULT FALSE,
+  reason             TEXT
+);
+
+CREATE INDEX content_rules_guild_id ON content_rules(guild_id);
@@ -0,0 +1 @@
+ALTER TABLE message_responses ADD COLUMN timeout_duration INTEGER;
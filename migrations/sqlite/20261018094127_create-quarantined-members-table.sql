@@ -0,0 +1,10 @@
+ALTER TABLE message_responses ADD COLUMN quarantine_role_id INTEGER;
+
+CREATE TABLE quarantined_members (
+  guild_id           INTEGER NOT NULL,
+  user_id            INTEGER NOT NULL,
+  quarantine_role_id INTEGER NOT NULL,
+  -- Comma separated list of the role IDs removed from the member
+  removed_role_ids   TEXT NOT NULL,
+  PRIMARY KEY(guild_id, user_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE message_responses ADD COLUMN delete_message INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE message_responses ADD COLUMN purge_window INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE message_responses ADD COLUMN delete_message_days INTEGER NOT NULL DEFAULT 7;
+ALTER TABLE message_responses ADD COLUMN reason TEXT;
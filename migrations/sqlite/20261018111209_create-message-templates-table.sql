@@ -0,0 +1,8 @@
+CREATE TABLE message_templates (
+  guild_id   INTEGER NOT NULL,
+  -- 0 for templates that apply to the whole guild
+  channel_id INTEGER NOT NULL,
+  kind       INTEGER NOT NULL,
+  template   TEXT NOT NULL,
+  PRIMARY KEY(guild_id, channel_id, kind)
+);
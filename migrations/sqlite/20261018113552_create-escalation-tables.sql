@@ -0,0 +1,17 @@
+CREATE TABLE escalation_policies (
+  guild_id     INTEGER NOT NULL,
+  -- Comma separated list of responses, one for each offense
+  ladder       TEXT NOT NULL,
+  -- Seconds before an offense stops counting towards the ladder
+  decay_window INTEGER NOT NULL,
+  PRIMARY KEY(guild_id)
+);
+
+CREATE TABLE offenses (
+  guild_id   INTEGER NOT NULL,
+  user_id    INTEGER NOT NULL,
+  -- Unix timestamp of the offense
+  created_at INTEGER NOT NULL
+);
+
+CREATE INDEX offenses_guild_id_user_id ON offenses(guild_id, user_id);
@@ -0,0 +1,15 @@
+CREATE TABLE incidents (
+  id         INTEGER PRIMARY KEY AUTOINCREMENT,
+  guild_id   INTEGER NOT NULL,
+  channel_id INTEGER NOT NULL,
+  user_id    INTEGER NOT NULL,
+  action     INTEGER NOT NULL,
+  -- Start of the message that triggered the honeypot
+  content    TEXT NOT NULL,
+  -- Unix timestamp of the incident
+  created_at INTEGER NOT NULL,
+  outcome    TEXT,
+  error      TEXT
+);
+
+CREATE INDEX incidents_guild_id_created_at ON incidents(guild_id, created_at);
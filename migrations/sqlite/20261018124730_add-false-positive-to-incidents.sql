@@ -0,0 +1 @@
+ALTER TABLE incidents ADD COLUMN false_positive INTEGER NOT NULL DEFAULT 0;
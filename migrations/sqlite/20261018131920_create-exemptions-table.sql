@@ -0,0 +1,7 @@
+CREATE TABLE exemptions (
+  guild_id  INTEGER NOT NULL,
+  -- 0 for users, 1 for roles
+  kind      INTEGER NOT NULL,
+  target_id INTEGER NOT NULL,
+  PRIMARY KEY(guild_id, kind, target_id)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE guild_settings (
+  guild_id          INTEGER NOT NULL,
+  exemption_mode    INTEGER NOT NULL DEFAULT 0,
+  exempt_moderators INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY(guild_id)
+);
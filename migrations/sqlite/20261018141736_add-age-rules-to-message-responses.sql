@@ -0,0 +1,4 @@
+ALTER TABLE message_responses ADD COLUMN new_account_age INTEGER;
+ALTER TABLE message_responses ADD COLUMN new_account_response INTEGER;
+ALTER TABLE message_responses ADD COLUMN established_member_age INTEGER;
+ALTER TABLE message_responses ADD COLUMN established_member_response INTEGER;
@@ -0,0 +1,3 @@
+-- Bitmask of the trigger kinds that count in the channel, messages only by default
+ALTER TABLE message_responses ADD COLUMN trigger_kinds INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE incidents ADD COLUMN trigger_kind INTEGER NOT NULL DEFAULT 0;
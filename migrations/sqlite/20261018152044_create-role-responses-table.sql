@@ -0,0 +1,11 @@
+CREATE TABLE role_responses (
+  guild_id            INTEGER NOT NULL,
+  role_id             INTEGER NOT NULL,
+  response            INTEGER NOT NULL,
+  timeout_duration    INTEGER,
+  quarantine_role_id  INTEGER,
+  purge_window        INTEGER,
+  delete_message_days INTEGER NOT NULL DEFAULT 7,
+  reason              TEXT,
+  PRIMARY KEY(guild_id, role_id)
+);
@@ -0,0 +1,12 @@
+CREATE TABLE burst_detection (
+  guild_id           INTEGER NOT NULL,
+  -- How many distinct channels the same content has to be posted in
+  channel_count      INTEGER NOT NULL,
+  -- Seconds
+  window             INTEGER NOT NULL,
+  response           INTEGER NOT NULL,
+  timeout_duration   INTEGER,
+  quarantine_role_id INTEGER,
+  delete_messages    INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY(guild_id)
+);
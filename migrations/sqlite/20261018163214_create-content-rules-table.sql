@@ -0,0 +1,14 @@
+CREATE TABLE content_rules (
+  id                 INTEGER PRIMARY KEY AUTOINCREMENT,
+  guild_id           INTEGER NOT NULL,
+  kind               INTEGER NOT NULL,
+  -- Regex, comma separated keywords or domains, or the mention count for mass mentions
+  pattern            TEXT NOT NULL,
+  response           INTEGER NOT NULL,
+  timeout_duration   INTEGER,
+  quarantine_role_id INTEGER,
+  delete_message     INTEGER NOT NULL DEFAULT 0,
+  reason             TEXT
+);
+
+CREATE INDEX content_rules_guild_id ON content_rules(guild_id);
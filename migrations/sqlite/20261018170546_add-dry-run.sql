@@ -0,0 +1,3 @@
+ALTER TABLE guild_settings ADD COLUMN dry_run INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE message_responses ADD COLUMN dry_run INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE incidents ADD COLUMN dry_run INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,520 @@
+use std::sync::Arc;
+
+use poise::{
+    ChoiceParameter,
+    serenity_prelude::{self as serenity},
+};
+
+use crate::{
+    datastore::{
+        Datastore,
+        errors::Error,
+        models::{
+            AgeRule, DEFAULT_TIMEOUT_DURATION, HoneypotResponse, MessageResponse, TriggerKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    templates::render_at,
+    trigger::Trigger,
+    utils::format_duration,
+};
+
+/// Prefix of the custom IDs of the buttons on logged actions, so they can be told apart from
+/// other components.
+const BUTTON_ID_PREFIX: &str = "honeybot";
+
+/// The buttons on logged actions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ActionButton {
+    Unban,
+    Reinvite,
+    FalsePositive,
+}
+
+impl ActionButton {
+    fn name(self) -> &'static str {
+        match self {
+            ActionButton::Unban => "unban",
+            ActionButton::Reinvite => "reinvite",
+            ActionButton::FalsePositive => "false_positive",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            ActionButton::Unban,
+            ActionButton::Reinvite,
+            ActionButton::FalsePositive,
+        ]
+        .into_iter()
+        .find(|button| button.name() == name)
+    }
+}
+
+/// How long re-invites sent from the logging channel are valid for, in seconds.
+const REINVITE_MAX_AGE: u32 = 7 * 24 * 60 * 60;
+
+/// Embed field values are limited to 1024 characters by Discord.
+const MAX_FIELD_LENGTH: usize = 1024;
+
+/// What happened while taking action on a user, beyond the configured response.
+pub struct ActionDetails {
+    pub deleted_message_count: usize,
+    /// Extra detail about how the action went
+    pub outcome: Option<String>,
+    /// Why the action failed, if it did
+    pub error: Option<String>,
+    /// How many times the user has triggered a honeypot, if the guild escalates responses
+    pub offense_count: Option<u32>,
+    /// The channel's account or join age rule that picked the response, if one matched
+    pub age_rule: Option<AgeRule>,
+    /// ID of the incident recorded for the action, if it was saved
+    pub incident_id: Option<i64>,
+}
+
+/// Posts an embed describing the action taken on the user behind `trigger`, with buttons for
+/// moderators to reverse it.
+pub async fn log_action_in_channel(
+    ctx: &serenity::Context,
+    log_template: &str,
+    config: &HoneypotResponse,
+    trigger: &Trigger,
+    details: ActionDetails,
+    logging_channel: &serenity::GuildChannel,
+) {
+    let user = &trigger.user;
+    let mut embed = serenity::CreateEmbed::new()
+        .author(serenity::CreateEmbedAuthor::new(user.tag()).icon_url(user.face()))
+        .thumbnail(user.face())
+        .description(render_at(
+            log_template,
+            user.id,
+            &trigger.location(),
+            config.response,
+        ))
+        .colour(if config.dry_run {
+            serenity::Colour::BLUE
+        } else if details.error.is_some() {
+            serenity::Colour::RED
+        } else {
+            serenity::Colour::ORANGE
+        })
+        .field("User", format!("<@{}> (`{}`)", user.id, user.id), true)
+        .field(
+            if trigger.channel_id.is_some() {
+                "Channel"
+            } else {
+                "Role"
+            },
+            trigger.location(),
+            true,
+        )
+        .field("Action", config.response.name(), true)
+        .field("Trigger", trigger.kind.name(), true)
+        .field(
+            "Account created",
+            format!("<t:{}:R>", user.id.created_at().unix_timestamp()),
+            true,
+        )
+        .timestamp(trigger.timestamp);
+    if config.dry_run {
+        embed = embed.title("DRY RUN: no action was taken");
+    }
+    if let Some(joined_at) = trigger.joined_at {
+        embed = embed.field(
+            "Joined server",
+            format!("<t:{}:R>", joined_at.unix_timestamp()),
+            true,
+        );
+    }
+    if let Some(count) = details.offense_count {
+        embed = embed.field("Offense", format!("#{count}"), true);
+    }
+    let rule = match details.age_rule {
+        Some(AgeRule::NewAccount(threshold)) => {
+            format!("Account younger than {}", format_duration(threshold))
+        }
+        Some(AgeRule::EstablishedMember(threshold)) => {
+            format!("Member for over {}", format_duration(threshold))
+        }
+        None if details.offense_count.is_some() => "Escalation policy".to_string(),
+        None => "Channel response".to_string(),
+    };
+    embed = embed.field("Rule", rule, true);
+    match (config.response, config.quarantine_role_id) {
+        (MessageResponse::Timeout, _) => {
+            embed = embed.field(
+                "Timeout",
+                format_duration(config.timeout_duration.unwrap_or(DEFAULT_TIMEOUT_DURATION)),
+                true,
+            );
+        }
+        (MessageResponse::Quarantine, Some(role_id)) => {
+            embed = embed.field("Quarantine role", format!("<@&{role_id}>"), true);
+        }
+        _ => (),
+    }
+    if !trigger.content.is_empty() {
+        let name = match trigger.kind {
+            TriggerKind::Message => "Message",
+            TriggerKind::Reaction => "Reaction",
+            TriggerKind::Thread => "Thread",
+            TriggerKind::Role => "Role name",
+            TriggerKind::Voice => "Voice channel",
+            TriggerKind::Burst => "Repeated message",
+            TriggerKind::ContentRule => "Message",
+        };
+        embed = embed.field(name, truncate(&trigger.content), false);
+    }
+    if !trigger.attachments.is_empty() {
+        let attachments = trigger
+            .attachments
+            .iter()
+            .map(|attachment| format!("[{}]({})", attachment.filename, attachment.url))
+            .collect::<Vec<_>>()
+            .join("\n");
+        embed = embed.field("Attachments", truncate(&attachments), false);
+    }
+    if details.deleted_message_count > 0 {
+        embed = embed.field(
+            "Deleted messages",
+            details.deleted_message_count.to_string(),
+            true,
+        );
+    }
+    if let Some(outcome) = &details.outcome {
+        embed = embed.field("Outcome", truncate(outcome), false);
+    }
+    if let Some(error) = &details.error {
+        embed = embed.field("Error", truncate(error), false);
+    }
+    if let Some(incident_id) = details.incident_id {
+        embed = embed.footer(serenity::CreateEmbedFooter::new(format!(
+            "Incident #{incident_id}"
+        )));
+    }
+
+    let result = logging_channel
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .embed(embed)
+                // Nothing happened to the user in a dry run, so there's nothing to reverse.
+                .components(action_buttons(
+                    if config.dry_run {
+                        MessageResponse::Nothing
+                    } else {
+                        config.response
+                    },
+                    user.id,
+                    details.incident_id,
+                )),
+        )
+        .await;
+    match result {
+        Ok(_) => (),
+        Err(why) => {
+            tracing::warn!(
+                "Error logging action in channel `{}`: {why:?}",
+                logging_channel.id
+            );
+        }
+    }
+}
+
+/// Posts a message in the guild's logging channel, if it has one.
+pub async fn post_in_logging_channel(
+    ctx: impl serenity::CacheHttp,
+    datastore: &Datastore,
+    guild_id: serenity::GuildId,
+    message: serenity::CreateMessage,
+) {
+    let logging_channel_id = match datastore.get_logging_channel(guild_id).await {
+        Ok(logging_channel_id) => logging_channel_id,
+        Err(_) => {
+            tracing::warn!("Logging channel not found for guild `{guild_id}`");
+            return;
+        }
+    };
+    if let Err(why) = logging_channel_id.send_message(ctx, message).await {
+        tracing::warn!("Error posting in logging channel `{logging_channel_id}`: {why:?}");
+    }
+}
+
+fn action_buttons(
+    response: MessageResponse,
+    user_id: serenity::UserId,
+    incident_id: Option<i64>,
+) -> Vec<serenity::CreateActionRow> {
+    let button_id = |button| button_id(button, user_id, incident_id);
+    let mut buttons = vec![];
+    if matches!(response, MessageResponse::Ban | MessageResponse::SoftBan) {
+        buttons.push(
+            serenity::CreateButton::new(button_id(ActionButton::Unban))
+                .label("Unban")
+                .style(serenity::ButtonStyle::Success),
+        );
+    }
+    if matches!(
+        response,
+        MessageResponse::Ban | MessageResponse::SoftBan | MessageResponse::Kick
+    ) {
+        buttons.push(
+            serenity::CreateButton::new(button_id(ActionButton::Reinvite))
+                .label("Re-invite note")
+                .style(serenity::ButtonStyle::Secondary),
+        );
+    }
+    if incident_id.is_some() {
+        buttons.push(
+            serenity::CreateButton::new(button_id(ActionButton::FalsePositive))
+                .label("Mark false positive")
+                .style(serenity::ButtonStyle::Danger),
+        );
+    }
+    if buttons.is_empty() {
+        vec![]
+    } else {
+        vec![serenity::CreateActionRow::Buttons(buttons)]
+    }
+}
+
+/// Builds the custom ID of a button on a logged action.
+fn button_id(button: ActionButton, user_id: serenity::UserId, incident_id: Option<i64>) -> String {
+    // The incident ID is 0 if the incident wasn't saved, which no incident has.
+    format!(
+        "{BUTTON_ID_PREFIX}:{}:{user_id}:{}",
+        button.name(),
+        incident_id.unwrap_or(0)
+    )
+}
+
+/// Parses a custom ID built by `button_id`, returning `None` if it's malformed.
+fn parse_button_id(custom_id: &str) -> Option<(ActionButton, serenity::UserId, i64)> {
+    let mut parts = custom_id.split(':');
+    let (Some(BUTTON_ID_PREFIX), Some(button), Some(user_id), Some(incident_id), None) = (
+        parts.next(),
+        parts.next().and_then(ActionButton::from_name),
+        parts
+            .next()
+            .and_then(|id| id.parse::<u64>().ok())
+            .filter(|id| *id != 0),
+        parts
+            .next()
+            .and_then(|id| id.parse::<i64>().ok())
+            .filter(|id| *id >= 0),
+        parts.next(),
+    ) else {
+        return None;
+    };
+    Some((button, serenity::UserId::new(user_id), incident_id))
+}
+
+/// Handles clicks on the buttons of logged actions. Components that weren't created by
+/// `log_action_in_channel` are ignored, and malformed or stale buttons get an ephemeral error.
+pub async fn handle_action_button(
+    ctx: &serenity::Context,
+    datastore: &Arc<Datastore>,
+    component: &serenity::ComponentInteraction,
+) {
+    let custom_id = &component.data.custom_id;
+    if custom_id.split(':').next() != Some(BUTTON_ID_PREFIX) {
+        return;
+    }
+    let (Some((button, user_id, incident_id)), Some(guild_id)) =
+        (parse_button_id(custom_id), component.guild_id)
+    else {
+        tracing::warn!("Invalid button ID `{custom_id}`");
+        respond(
+            ctx,
+            component,
+            "This button is invalid or out of date".to_string(),
+            true,
+        )
+        .await;
+        return;
+    };
+    let moderator_id = component.user.id;
+
+    // Buttons in the logging channel can be seen by anyone that can read it, so check that the
+    // moderator would be able to take these actions themselves.
+    let permitted = component
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .is_some_and(|permissions| permissions.ban_members());
+    if !permitted {
+        respond(
+            ctx,
+            component,
+            "You need the Ban Members permission to use this button".to_string(),
+            true,
+        )
+        .await;
+        return;
+    }
+
+    let (content, ephemeral) = match button {
+        ActionButton::Unban => match guild_id.unban(ctx, user_id).await {
+            Ok(_) => (format!("<@{moderator_id}> unbanned <@{user_id}>"), false),
+            Err(why) => {
+                tracing::warn!("Error unbanning user from logging channel: {why:?}");
+                (format!("Error unbanning <@{user_id}>: {why}"), true)
+            }
+        },
+        ActionButton::Reinvite => (
+            send_reinvite_note(ctx, guild_id, component.channel_id, user_id).await,
+            true,
+        ),
+        ActionButton::FalsePositive => match datastore
+            .mark_incident_false_positive(guild_id, incident_id)
+            .await
+        {
+            Ok(_) => (
+                format!(
+                    "<@{moderator_id}> marked incident #{incident_id} for <@{user_id}> as a false positive"
+                ),
+                false,
+            ),
+            Err(Error::DatabaseEntryNotFound) => {
+                (format!("Incident #{incident_id} no longer exists"), true)
+            }
+            Err(why) => {
+                tracing::warn!("Error marking incident as a false positive: {why:?}");
+                (
+                    format!("Error marking incident #{incident_id} as a false positive"),
+                    true,
+                )
+            }
+        },
+    };
+    respond(ctx, component, content, ephemeral).await;
+}
+
+/// DMs the user a single use invite back to the guild, returning a description of how it went for
+/// the moderator.
+async fn send_reinvite_note(
+    ctx: &serenity::Context,
+    guild_id: serenity::GuildId,
+    fallback_channel_id: serenity::ChannelId,
+    user_id: serenity::UserId,
+) -> String {
+    let (guild_name, invite_channel_id) = match ctx.cache.guild(guild_id) {
+        Some(guild) => (
+            guild.name.clone(),
+            guild
+                .system_channel_id
+                .or(guild.rules_channel_id)
+                .unwrap_or(fallback_channel_id),
+        ),
+        None => ("the server".to_string(), fallback_channel_id),
+    };
+    let invite = match invite_channel_id
+        .create_invite(
+            ctx,
+            serenity::CreateInvite::new()
+                .max_age(REINVITE_MAX_AGE)
+                .max_uses(1)
+                .unique(true)
+                .audit_log_reason("re-invite after a honeypot false positive"),
+        )
+        .await
+    {
+        Ok(invite) => invite,
+        Err(why) => {
+            tracing::warn!("Error creating re-invite: {why:?}");
+            return format!("Error creating an invite for <@{user_id}>: {why}");
+        }
+    };
+
+    let note = format!(
+        "You were removed from **{guild_name}** by mistake, sorry about that! \
+        You're welcome to rejoin with this invite: {}",
+        invite.url()
+    );
+    let result = match user_id.create_dm_channel(ctx).await {
+        Ok(dm_channel) => dm_channel.say(ctx, note).await.map(|_| ()),
+        Err(why) => Err(why),
+    };
+    match result {
+        Ok(_) => format!("Sent <@{user_id}> a re-invite note with {}", invite.url()),
+        Err(why) => {
+            tracing::warn!("Error sending re-invite note: {why:?}");
+            format!(
+                "Couldn't DM <@{user_id}>, share this invite with them instead: {}",
+                invite.url()
+            )
+        }
+    }
+}
+
+async fn respond(
+    ctx: &serenity::Context,
+    component: &serenity::ComponentInteraction,
+    content: String,
+    ephemeral: bool,
+) {
+    let result = component
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::Message(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(ephemeral),
+            ),
+        )
+        .await;
+    if let Err(why) = result {
+        tracing::warn!("Error responding to button: {why:?}");
+    }
+}
+
+fn truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_FIELD_LENGTH {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(MAX_FIELD_LENGTH - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn button_id_round_trip() {
+        let user_id = serenity::UserId::new(123);
+        for button in [
+            ActionButton::Unban,
+            ActionButton::Reinvite,
+            ActionButton::FalsePositive,
+        ] {
+            assert_eq!(
+                parse_button_id(&button_id(button, user_id, Some(45))),
+                Some((button, user_id, 45))
+            );
+            assert_eq!(
+                parse_button_id(&button_id(button, user_id, None)),
+                Some((button, user_id, 0))
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_button_ids() {
+        for custom_id in [
+            "",
+            "honeybot",
+            "honeybot:unban:123",
+            "honeybot:unban:123:45:6",
+            "honeybot:explode:123:45",
+            "honeybot:unban:0:45",
+            "honeybot:unban:abc:45",
+            "honeybot:false_positive:123:-1",
+            "other:unban:123:45",
+        ] {
+            assert_eq!(parse_button_id(custom_id), None, "{custom_id}");
+        }
+    }
+}
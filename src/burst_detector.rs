@@ -0,0 +1,212 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use moka::future::Cache;
+use poise::serenity_prelude::{self as serenity};
+
+/// Longest window `/burst_detection` accepts. Users that haven't posted for this long are
+/// dropped from the detector.
+pub const MAX_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// Most channels `/burst_detection` accepts a burst needing to be posted in.
+pub const MAX_CHANNEL_COUNT: u32 = 50;
+
+/// How many users' recent messages are remembered at once.
+const MAX_TRACKED_USERS: u64 = 100_000;
+
+/// A message the detector has seen, without its content.
+struct Fingerprint {
+    hash: u64,
+    channel_id: serenity::ChannelId,
+    timestamp: i64,
+}
+
+/// Remembers fingerprints of each user's recent messages to notice the same message being posted
+/// in several channels.
+pub struct BurstDetector {
+    recent_messages: Cache<(serenity::GuildId, serenity::UserId), Arc<Mutex<Vec<Fingerprint>>>>,
+}
+
+impl Default for BurstDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BurstDetector {
+    pub fn new() -> Self {
+        Self {
+            recent_messages: Cache::builder()
+                .max_capacity(MAX_TRACKED_USERS)
+                .time_to_idle(MAX_WINDOW)
+                .build(),
+        }
+    }
+
+    /// Records the message and returns whether the user has now posted it in at least
+    /// `channel_count` channels within `window`. The user's history is cleared after a burst so
+    /// the same burst isn't acted on once per extra channel.
+    pub async fn record(
+        &self,
+        guild_id: serenity::GuildId,
+        message: &serenity::Message,
+        channel_count: u32,
+        window: Duration,
+    ) -> bool {
+        let Some(hash) = fingerprint(message) else {
+            return false;
+        };
+        let timestamp = message.timestamp.unix_timestamp();
+        let recent_messages = self
+            .recent_messages
+            .get_with((guild_id, message.author.id), async {
+                Arc::new(Mutex::new(vec![]))
+            })
+            .await;
+        let mut recent_messages = recent_messages
+            .lock()
+            .expect("burst detector lock shouldn't be poisoned");
+
+        let cutoff = timestamp - window.min(MAX_WINDOW).as_secs() as i64;
+        recent_messages.retain(|fingerprint| fingerprint.timestamp >= cutoff);
+        recent_messages.push(Fingerprint {
+            hash,
+            channel_id: message.channel_id,
+            timestamp,
+        });
+
+        let mut channel_ids: Vec<serenity::ChannelId> = recent_messages
+            .iter()
+            .filter(|fingerprint| fingerprint.hash == hash)
+            .map(|fingerprint| fingerprint.channel_id)
+            .collect();
+        channel_ids.sort_unstable();
+        channel_ids.dedup();
+        if channel_ids.len() >= channel_count as usize {
+            recent_messages.clear();
+            return true;
+        }
+        false
+    }
+}
+
+/// Hashes the message's content and attachments, ignoring case, whitespace and punctuation so
+/// near-identical copies of a message match. Returns `None` for messages with nothing to compare.
+fn fingerprint(message: &serenity::Message) -> Option<u64> {
+    let content: String = message
+        .content
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if content.is_empty() && message.attachments.is_empty() {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    for attachment in &message.attachments {
+        attachment.filename.hash(&mut hasher);
+        attachment.size.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUILD_ID: serenity::GuildId = serenity::GuildId::new(1);
+
+    fn message(content: &str, channel_id: u64, timestamp: i64) -> serenity::Message {
+        let mut message = serenity::Message::default();
+        message.content = content.to_owned();
+        message.author.id = serenity::UserId::new(2);
+        message.channel_id = serenity::ChannelId::new(channel_id);
+        message.timestamp = serenity::Timestamp::from_unix_timestamp(timestamp).unwrap();
+        message
+    }
+
+    #[tokio::test]
+    async fn detects_message_in_enough_channels() {
+        let detector = BurstDetector::new();
+        let window = Duration::from_secs(60);
+
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("Free nitro!", 10, 1000), 3, window)
+                .await
+        );
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("free nitro", 11, 1001), 3, window)
+                .await
+        );
+        // Posting again in a channel that already has the message doesn't count:
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("free nitro", 11, 1002), 3, window)
+                .await
+        );
+        assert!(
+            detector
+                .record(GUILD_ID, &message("FREE NITRO", 12, 1003), 3, window)
+                .await
+        );
+        // The history is cleared after a burst:
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("free nitro", 13, 1004), 3, window)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_messages_outside_window() {
+        let detector = BurstDetector::new();
+        let window = Duration::from_secs(60);
+
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("free nitro", 10, 1000), 2, window)
+                .await
+        );
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("free nitro", 11, 1061), 2, window)
+                .await
+        );
+        // The window is inclusive:
+        assert!(
+            detector
+                .record(GUILD_ID, &message("free nitro", 12, 1121), 2, window)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_different_messages() {
+        let detector = BurstDetector::new();
+        let window = Duration::from_secs(60);
+
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("hello", 10, 1000), 2, window)
+                .await
+        );
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("goodbye", 11, 1001), 2, window)
+                .await
+        );
+        // Messages with nothing to compare are never a burst:
+        assert!(
+            !detector
+                .record(GUILD_ID, &message("", 12, 1002), 1, window)
+                .await
+        );
+    }
+}
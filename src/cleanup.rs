@@ -0,0 +1,121 @@
+use poise::serenity_prelude::{self as serenity};
+
+use crate::datastore::{
+    Datastore,
+    traits::{DatastoreReader, DatastoreWriter},
+};
+
+/// Removes the configs of a channel that was deleted, and lets the guild know if it was a
+/// honeypot or the logging channel. `name` is the deleted channel's name, if it's known.
+pub async fn remove_deleted_channel(
+    ctx: &serenity::Context,
+    datastore: &Datastore,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    name: Option<&str>,
+) {
+    // The cache has a placeholder config for every channel, so only the database can say whether
+    // the channel was a honeypot.
+    let was_honeypot = match datastore.list_message_response_configs(guild_id).await {
+        Ok(configs) => configs.iter().any(|config| config.channel_id == channel_id),
+        Err(why) => {
+            tracing::error!("Error retrieving honeypot channels from database: {why:?}");
+            false
+        }
+    };
+    let logging_channel_id = datastore.get_logging_channel(guild_id).await.ok();
+    if let Err(why) = datastore.delete_channel_configs(guild_id, channel_id).await {
+        tracing::error!("Error deleting configs of deleted channel `{channel_id}`: {why:?}");
+        return;
+    }
+
+    let channel = match name {
+        Some(name) => format!("`#{name}`"),
+        None => format!("`{channel_id}`"),
+    };
+    if logging_channel_id == Some(channel_id) {
+        tracing::info!("Logging channel `{channel_id}` of guild `{guild_id}` was deleted");
+        notify_owner(
+            ctx,
+            guild_id,
+            format!(
+                "The logging channel {channel} was deleted, so the bot's actions are no longer \
+                 logged. Set a new one with `/logging_channel`."
+            ),
+        )
+        .await;
+    }
+    if was_honeypot {
+        tracing::info!("Honeypot channel `{channel_id}` of guild `{guild_id}` was deleted");
+        let content = format!(
+            "Honeypot channel {channel} was deleted, so the bot stopped listening to it. Use \
+             `/listen` to set up a new one."
+        );
+        match logging_channel_id.filter(|logging_channel_id| *logging_channel_id != channel_id) {
+            Some(logging_channel_id) => {
+                if let Err(why) = logging_channel_id.say(ctx, &content).await {
+                    tracing::error!("Error posting deleted honeypot notice: {why:?}");
+                    notify_owner(ctx, guild_id, content).await;
+                }
+            }
+            None => notify_owner(ctx, guild_id, content).await,
+        }
+    }
+}
+
+/// Removes the configs of honeypot and logging channels that were deleted while the bot was
+/// offline, since their delete events were missed.
+pub async fn remove_channels_deleted_while_offline(
+    ctx: &serenity::Context,
+    datastore: &Datastore,
+    guild: &serenity::Guild,
+) {
+    let mut channel_ids = match datastore.list_message_response_configs(guild.id).await {
+        Ok(configs) => configs
+            .iter()
+            .map(|config| config.channel_id)
+            .collect::<Vec<_>>(),
+        Err(why) => {
+            tracing::error!("Error retrieving honeypot channels from database: {why:?}");
+            return;
+        }
+    };
+    if let Ok(logging_channel_id) = datastore.get_logging_channel(guild.id).await
+        && !channel_ids.contains(&logging_channel_id)
+    {
+        channel_ids.push(logging_channel_id);
+    }
+    for channel_id in channel_ids {
+        if guild.channels.contains_key(&channel_id)
+            || guild.threads.iter().any(|thread| thread.id == channel_id)
+        {
+            continue;
+        }
+        // Archived threads aren't in the cache, so Discord is asked to be sure.
+        match channel_id.to_channel(ctx).await {
+            Err(serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response)))
+                if response.status_code.as_u16() == 404 =>
+            {
+                remove_deleted_channel(ctx, datastore, guild.id, channel_id, None).await;
+            }
+            Ok(_) => (),
+            Err(why) => tracing::warn!("Error checking whether `{channel_id}` exists: {why:?}"),
+        }
+    }
+}
+
+/// DMs the guild's owner, for when there's no logging channel to post in.
+async fn notify_owner(ctx: &serenity::Context, guild_id: serenity::GuildId, content: String) {
+    let Some((owner_id, guild_name)) = ctx
+        .cache
+        .guild(guild_id)
+        .map(|guild| (guild.owner_id, guild.name.clone()))
+    else {
+        return;
+    };
+    let message =
+        serenity::CreateMessage::new().content(format!("**Honeybot in {guild_name}**: {content}"));
+    if let Err(why) = owner_id.direct_message(ctx, message).await {
+        tracing::warn!("Error notifying the owner of guild `{guild_id}`: {why:?}");
+    }
+}
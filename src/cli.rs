@@ -0,0 +1,607 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+use clap::{Subcommand, ValueEnum};
+use poise::serenity_prelude::{self as serenity};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    datastore::{
+        SHARED_CACHE_TIME_TO_LIVE,
+        backend::Backend,
+        errors::Error,
+        models::{GuildSettings, IncidentFilter, MessageResponse},
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config,
+};
+
+/// How many incidents `incidents export` reads from the database at a time.
+const INCIDENTS_PAGE_SIZE: u32 = 500;
+
+/// Columns of `incidents export`, in order.
+const INCIDENT_COLUMNS: [&str; 11] = [
+    "id",
+    "created_at",
+    "channel_id",
+    "user_id",
+    "action",
+    "trigger_kind",
+    "content",
+    "outcome",
+    "error",
+    "false_positive",
+    "dry_run",
+];
+
+/// Admin tasks run against the database instead of starting the bot.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Inspect, edit, export or import a guild's honeypots and settings
+    ///
+    /// A running bot caches settings, so restart it after changing them here, or wait 30 seconds
+    /// when it uses PostgreSQL.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+
+    /// Export a guild's incidents
+    #[command(subcommand)]
+    Incidents(IncidentsCommand),
+
+    /// Apply pending database migrations, then list every applied migration
+    Migrate,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print a guild's honeypots and settings, one per line
+    List {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+    },
+    /// Change one setting of a guild, or of one of its honeypot channels with `--channel`
+    Set {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+
+        /// Honeypot channel to change instead of the guild
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        channel: Option<u64>,
+
+        key: SettingKey,
+
+        /// New value, spelled the way exported JSON documents spell it, e.g. `soft_ban`
+        value: String,
+    },
+    /// Stop listening to a honeypot channel
+    Unlisten {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        channel: u64,
+    },
+    /// Print a guild's honeypots and settings as JSON
+    Export {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+
+        /// Write the JSON to this file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Replace a guild's honeypots and settings with a JSON file, after showing what would change
+    Import {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+
+        /// JSON file from `config export` or `/config export`
+        file: PathBuf,
+
+        /// Apply the changes without asking
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Check that the file's channels and roles exist in the guild, which needs
+        /// `DISCORD_TOKEN` and access to Discord
+        #[arg(long)]
+        check_ids: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum IncidentsCommand {
+    /// Print a guild's incidents, newest first
+    Export {
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        guild: u64,
+
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+
+        /// Include incidents that were only logged by dry runs
+        #[arg(long)]
+        include_dry_runs: bool,
+
+        /// Write the incidents to this file instead of printing them
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Settings that `config set` can change.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum SettingKey {
+    /// The guild's logging channel ID, or `none` to stop logging
+    LoggingChannel,
+    /// `true` or `false`, for the guild or one honeypot channel
+    DryRun,
+    /// What happens when an exempt user triggers a honeypot: `warn` or `ignore`
+    ExemptionMode,
+    /// `true` or `false`
+    ExemptModerators,
+    /// What a honeypot channel does to posters, e.g. `ban` or `nothing`
+    Response,
+    /// `true` or `false`, whether a honeypot channel deletes the message that triggered it
+    DeleteMessage,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Runs an admin command, returning a message for the user if it fails.
+pub async fn run(command: Command, database: &Backend) -> Result<(), String> {
+    match command {
+        Command::Config(ConfigCommand::List { guild }) => {
+            list_config(database, serenity::GuildId::new(guild)).await
+        }
+        Command::Config(ConfigCommand::Set {
+            guild,
+            channel,
+            key,
+            value,
+        }) => {
+            set_config(
+                database,
+                serenity::GuildId::new(guild),
+                channel.map(serenity::ChannelId::new),
+                key,
+                &value,
+            )
+            .await
+        }
+        Command::Config(ConfigCommand::Unlisten { guild, channel }) => {
+            unlisten(
+                database,
+                serenity::GuildId::new(guild),
+                serenity::ChannelId::new(channel),
+            )
+            .await
+        }
+        Command::Config(ConfigCommand::Export { guild, output }) => {
+            export_config(database, serenity::GuildId::new(guild), output).await
+        }
+        Command::Config(ConfigCommand::Import {
+            guild,
+            file,
+            yes,
+            check_ids,
+        }) => {
+            import_config(
+                database,
+                serenity::GuildId::new(guild),
+                file,
+                yes,
+                check_ids,
+            )
+            .await
+        }
+        Command::Incidents(IncidentsCommand::Export {
+            guild,
+            format,
+            include_dry_runs,
+            output,
+        }) => {
+            export_incidents(
+                database,
+                serenity::GuildId::new(guild),
+                format,
+                include_dry_runs,
+                output,
+            )
+            .await
+        }
+        Command::Migrate => list_migrations(database).await,
+    }
+}
+
+async fn list_config(database: &Backend, guild_id: serenity::GuildId) -> Result<(), String> {
+    let guild_config = read_config(database, guild_id).await?;
+    for line in guild_config.summary() {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+async fn set_config(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    channel_id: Option<serenity::ChannelId>,
+    key: SettingKey,
+    value: &str,
+) -> Result<(), String> {
+    let before = read_config(database, guild_id).await?;
+    match channel_id {
+        Some(channel_id) => set_channel_setting(database, guild_id, channel_id, key, value).await?,
+        None => set_guild_setting(database, guild_id, key, value).await?,
+    }
+
+    let diff = read_config(database, guild_id).await?.diff(&before);
+    if diff.is_empty() {
+        println!("The setting already had that value, nothing was changed");
+        return Ok(());
+    }
+    for line in diff {
+        println!("{line}");
+    }
+    print_running_bot_note(database);
+    Ok(())
+}
+
+async fn set_guild_setting(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    key: SettingKey,
+    value: &str,
+) -> Result<(), String> {
+    let result = match key {
+        SettingKey::LoggingChannel => match value {
+            "none" => database.delete_logging_channel(guild_id).await,
+            value => {
+                let channel_id = serenity::ChannelId::new(parse_id(value)?);
+                database.insert_logging_channel(guild_id, channel_id).await
+            }
+        },
+        SettingKey::DryRun | SettingKey::ExemptionMode | SettingKey::ExemptModerators => {
+            let mut guild_settings = match database.get_guild_settings(guild_id).await {
+                Ok(guild_settings) => guild_settings,
+                Err(Error::DatabaseEntryNotFound) => GuildSettings::new(guild_id),
+                Err(why) => return Err(format!("Error reading the guild's settings: {why:?}")),
+            };
+            match key {
+                SettingKey::DryRun => guild_settings.dry_run = parse_bool(value)?,
+                SettingKey::ExemptionMode => guild_settings.exemption_mode = parse_choice(value)?,
+                _ => guild_settings.exempt_moderators = parse_bool(value)?,
+            }
+            database.insert_guild_settings(&guild_settings).await
+        }
+        SettingKey::Response | SettingKey::DeleteMessage => {
+            return Err(format!(
+                "`{}` is a honeypot channel setting, choose the channel with `--channel`",
+                key_name(key)
+            ));
+        }
+    };
+    result.map_err(|why| format!("Error writing the setting: {why:?}"))
+}
+
+async fn set_channel_setting(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    key: SettingKey,
+    value: &str,
+) -> Result<(), String> {
+    let mut config = match database
+        .get_message_response_config(guild_id, channel_id)
+        .await
+    {
+        Ok(config) => config,
+        Err(Error::DatabaseEntryNotFound) => return Err(not_a_honeypot(guild_id, channel_id)),
+        Err(why) => return Err(format!("Error reading the guild's settings: {why:?}")),
+    };
+    match key {
+        SettingKey::Response => {
+            config.response = parse_choice(value)?;
+            if config.response == MessageResponse::Quarantine && config.quarantine_role_id.is_none()
+            {
+                return Err(
+                    "The channel has no quarantine role, set one up with `/listen`".to_string(),
+                );
+            }
+        }
+        SettingKey::DryRun => config.dry_run = parse_bool(value)?,
+        SettingKey::DeleteMessage => config.delete_message = parse_bool(value)?,
+        SettingKey::LoggingChannel | SettingKey::ExemptionMode | SettingKey::ExemptModerators => {
+            return Err(format!(
+                "`{}` is a guild setting, leave out `--channel`",
+                key_name(key)
+            ));
+        }
+    }
+    database
+        .insert_message_response_config(&config)
+        .await
+        .map_err(|why| format!("Error writing the setting: {why:?}"))
+}
+
+async fn unlisten(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Result<(), String> {
+    match database
+        .get_message_response_config(guild_id, channel_id)
+        .await
+    {
+        Ok(_) => (),
+        Err(Error::DatabaseEntryNotFound) => return Err(not_a_honeypot(guild_id, channel_id)),
+        Err(why) => return Err(format!("Error reading the guild's settings: {why:?}")),
+    }
+    database
+        .delete_message_response_config(guild_id, channel_id)
+        .await
+        .map_err(|why| format!("Error unlistening to channel `{channel_id}`: {why:?}"))?;
+    println!("Unlistened to channel `{channel_id}`");
+    print_running_bot_note(database);
+    Ok(())
+}
+
+async fn export_config(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let guild_config = read_config(database, guild_id).await?;
+    write_output(output, guild_config.to_json())
+}
+
+async fn import_config(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    file: PathBuf,
+    yes: bool,
+    check_ids: bool,
+) -> Result<(), String> {
+    let json = std::fs::read_to_string(&file)
+        .map_err(|why| format!("Error reading `{}`: {why}", file.display()))?;
+    let guild_config = guild_config::GuildConfig::parse(&json)?;
+
+    let mut problems = guild_config.validate();
+    // Discord is only contacted when asked to, so imports work while it can't be reached or the
+    // token is revoked.
+    if check_ids {
+        let token = std::env::var("DISCORD_TOKEN")
+            .map_err(|_| "DISCORD_TOKEN needs to be set to check IDs".to_string())?;
+        let (channel_ids, role_ids) = fetch_guild_ids(&token, guild_id).await?;
+        problems.extend(guild_config.missing_ids(&channel_ids, &role_ids));
+    } else {
+        eprintln!(
+            "Channel and role IDs weren't checked against the guild, use --check-ids to check them"
+        );
+    }
+    if !problems.is_empty() {
+        return Err(format!(
+            "Nothing was imported, the file has problems:\n{}",
+            problems
+                .iter()
+                .map(|problem| format!("- {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+
+    let current = read_config(database, guild_id).await?;
+    let diff = guild_config.diff(&current);
+    if diff.is_empty() {
+        println!("The file matches the guild's settings, nothing to import");
+        return Ok(());
+    }
+    println!("Importing will make these changes:");
+    for line in &diff {
+        println!("{line}");
+    }
+    if !yes && !confirm("Apply these changes?") {
+        println!("Import cancelled, nothing was changed");
+        return Ok(());
+    }
+    database
+        .replace_guild_configs(guild_id, &guild_config)
+        .await
+        .map_err(|why| format!("Error importing the settings, nothing was changed: {why:?}"))?;
+    println!("Imported {} changes", diff.len());
+    print_running_bot_note(database);
+    Ok(())
+}
+
+async fn export_incidents(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+    format: ExportFormat,
+    include_dry_runs: bool,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let mut filter = IncidentFilter {
+        guild_id,
+        user_id: None,
+        channel_id: None,
+        action: None,
+        include_dry_runs,
+        limit: INCIDENTS_PAGE_SIZE,
+        offset: 0,
+    };
+    let mut incidents = vec![];
+    loop {
+        let page = database
+            .list_incidents(&filter)
+            .await
+            .map_err(|why| format!("Error reading the guild's incidents: {why:?}"))?;
+        let done = page.len() < INCIDENTS_PAGE_SIZE as usize;
+        incidents.extend(page.iter().map(|incident| {
+            serde_json::json!({
+                "id": incident.id,
+                "created_at": incident.created_at.to_string(),
+                "channel_id": incident.channel_id,
+                "user_id": incident.user_id,
+                "action": incident.action,
+                "trigger_kind": incident.trigger_kind,
+                "content": incident.content,
+                "outcome": incident.outcome,
+                "error": incident.error,
+                "false_positive": incident.false_positive,
+                "dry_run": incident.dry_run,
+            })
+        }));
+        if done {
+            break;
+        }
+        filter.offset += INCIDENTS_PAGE_SIZE;
+    }
+
+    let text = match format {
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&incidents).expect("incidents should always serialize")
+        }
+        ExportFormat::Csv => {
+            let mut lines = vec![INCIDENT_COLUMNS.join(",")];
+            lines.extend(incidents.iter().map(|incident| {
+                INCIDENT_COLUMNS
+                    .iter()
+                    .map(|column| match &incident[column] {
+                        serde_json::Value::Null => String::new(),
+                        serde_json::Value::String(value) => csv_field(value),
+                        value => value.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            }));
+            lines.join("\n")
+        }
+    };
+    write_output(output, text)
+}
+
+async fn list_migrations(database: &Backend) -> Result<(), String> {
+    let migrations = database
+        .list_applied_migrations()
+        .await
+        .map_err(|why| format!("Error reading the applied migrations: {why:?}"))?;
+    for (version, description) in &migrations {
+        println!("{version} {description}");
+    }
+    println!(
+        "The database is up to date, {} migrations are applied",
+        migrations.len()
+    );
+    Ok(())
+}
+
+async fn read_config(
+    database: &Backend,
+    guild_id: serenity::GuildId,
+) -> Result<guild_config::GuildConfig, String> {
+    guild_config::export(database, guild_id)
+        .await
+        .map_err(|why| format!("Error reading the guild's settings: {why:?}"))
+}
+
+/// Prints `text`, or writes it to `output` if given.
+fn write_output(output: Option<PathBuf>, text: String) -> Result<(), String> {
+    match output {
+        Some(output) => std::fs::write(&output, text + "\n")
+            .map_err(|why| format!("Error writing `{}`: {why}", output.display())),
+        None => {
+            println!("{text}");
+            Ok(())
+        }
+    }
+}
+
+/// Looks up the guild's channels, including active threads, and roles with Discord's API.
+async fn fetch_guild_ids(
+    token: &str,
+    guild_id: serenity::GuildId,
+) -> Result<(HashSet<serenity::ChannelId>, HashSet<serenity::RoleId>), String> {
+    let http = serenity::Http::new(token);
+    let fetch_error = |why: serenity::Error| format!("Error fetching guild `{guild_id}`: {why}");
+    let channels = guild_id.channels(&http).await.map_err(fetch_error)?;
+    let threads = guild_id
+        .get_active_threads(&http)
+        .await
+        .map_err(fetch_error)?;
+    let roles = guild_id.roles(&http).await.map_err(fetch_error)?;
+    let channel_ids = channels
+        .into_keys()
+        .chain(threads.threads.iter().map(|thread| thread.id))
+        .collect();
+    Ok((channel_ids, roles.into_keys().collect()))
+}
+
+/// Running bots cache settings and only invalidate their own caches, so they don't see changes
+/// made from the command line right away.
+fn print_running_bot_note(database: &Backend) {
+    match database {
+        Backend::Sqlite(_) => {
+            eprintln!("Restart the bot if it's running, it won't see this change until then")
+        }
+        Backend::Postgres(_) => eprintln!(
+            "Running bots see this change within {} seconds",
+            SHARED_CACHE_TIME_TO_LIVE.as_secs()
+        ),
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{question} [y/N] ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+fn not_a_honeypot(guild_id: serenity::GuildId, channel_id: serenity::ChannelId) -> String {
+    format!("`{channel_id}` isn't a honeypot channel of guild `{guild_id}`")
+}
+
+fn key_name(key: SettingKey) -> String {
+    key.to_possible_value()
+        .expect("setting keys are never skipped")
+        .get_name()
+        .to_string()
+}
+
+fn parse_id(value: &str) -> Result<u64, String> {
+    match value.parse() {
+        Ok(id) if id > 0 => Ok(id),
+        _ => Err(format!("Expected an ID or `none`, got `{value}`")),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Expected `true` or `false`, got `{value}`"))
+}
+
+/// Parses a choice the way JSON documents spell it, e.g. `soft_ban` or `ignore`.
+fn parse_choice<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|why| format!("Invalid value `{value}`: {why}"))
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -1,5 +1,19 @@
+mod burst_detection;
+mod config;
+mod dry_run;
+mod escalation;
+mod exempt;
+mod honeybot;
+mod honeypots;
+mod incidents;
+mod message_template;
+mod pardon;
+mod rule;
+
+use std::time::Duration;
+
 use poise::{
-    Context,
+    ChoiceParameter, Context,
     serenity_prelude::{self as serenity, Error},
 };
 use tracing::{Level, event};
@@ -7,48 +21,224 @@ use tracing::{Level, event};
 use crate::{
     context_data,
     datastore::{
-        models::{MessageResponse, MessageResponseConfig},
+        Datastore, errors,
+        models::{
+            DEFAULT_TIMEOUT_DURATION, MessageResponse, MessageResponseConfig, RoleResponseConfig,
+            TemplateKind, TriggerKind,
+        },
         prelude::*,
     },
+    permissions,
+    templates::{render_warning, resolve_template},
 };
 
+pub use burst_detection::burst_detection;
+pub use config::config;
+pub use dry_run::dry_run;
+pub use escalation::escalation;
+pub use exempt::exempt;
+pub use honeybot::honeybot;
+pub use honeypots::honeypots;
+pub use incidents::incidents;
+pub use message_template::message_template;
+pub use pardon::pardon;
+pub use rule::rule;
+
+/// Converts a command's `timeout_minutes` option, falling back to `DEFAULT_TIMEOUT_DURATION`.
+fn timeout_duration(timeout_minutes: Option<u64>) -> Duration {
+    timeout_minutes.map_or(DEFAULT_TIMEOUT_DURATION, |minutes| {
+        Duration::from_secs(minutes * 60)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
 #[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
 pub async fn listen(
     ctx: Context<'_, context_data::ContextData, Error>,
     #[description = "Channel to listen to"] channel: serenity::Channel,
     #[description = "Action for each new message in channel"] response: MessageResponse,
+    #[description = "Comma separated triggers: message, reaction, thread, voice (default message)"]
+    triggers: Option<String>,
+    #[description = "How long to time out posters for, in minutes (timeout only)"]
+    #[min = 1]
+    #[max = 40320] // Discord caps timeouts at 28 days
+    timeout_minutes: Option<u64>,
+    #[description = "Role to replace the poster's roles with (quarantine only)"]
+    quarantine_role: Option<serenity::Role>,
+    #[description = "Delete the message that triggered the honeypot"] delete_message: Option<bool>,
+    #[description = "Delete the poster's messages from the last N minutes (checks 2000 messages per channel/thread)"]
+    #[min = 1]
+    #[max = 20160] // Discord can only bulk delete messages younger than 14 days
+    purge_minutes: Option<u64>,
+    #[description = "Days of the poster's messages to delete when (soft) banning (default 7)"]
+    #[min = 0]
+    #[max = 7]
+    delete_message_days: Option<u8>,
+    #[description = "Audit log reason, supports the {user} and {channel} placeholders"]
+    #[max_length = 400]
+    reason: Option<String>,
+    #[description = "Use `new_account_response` for accounts younger than this many days"]
+    #[min = 1]
+    new_account_days: Option<u64>,
+    #[description = "Action for new accounts"] new_account_response: Option<MessageResponse>,
+    #[description = "Use `established_member_response` for members that joined over N days ago"]
+    #[min = 1]
+    established_member_days: Option<u64>,
+    #[description = "Action for long-standing members"] established_member_response: Option<
+        MessageResponse,
+    >,
+    #[description = "Only log what would be done, see `/dry_run` (default keeps current setting)"]
+    dry_run: Option<bool>,
 ) -> Result<(), Error> {
     let channel_id = channel.id();
     let guild_channel = channel.guild().unwrap();
+    let mut config = MessageResponseConfig::new(ctx.guild_id().unwrap(), channel_id, response);
+    let datastore = ctx.data().datastore.as_ref();
+    config.dry_run = match listen_dry_run(datastore, config.guild_id, channel_id, dry_run).await {
+        Ok(dry_run) => dry_run,
+        Err(why) => {
+            event!(Level::WARN, "Error reading message response: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("Error reading honeypot channel <#{channel_id}>"))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    // Joining is the trigger for voice channels, since bots rarely post in their text chat.
+    if matches!(
+        guild_channel.kind,
+        serenity::ChannelType::Voice | serenity::ChannelType::Stage
+    ) {
+        config.trigger_kinds = vec![TriggerKind::Voice];
+    }
+    config.delete_message = delete_message.unwrap_or(false);
+    config.purge_window = purge_minutes.map(|minutes| Duration::from_secs(minutes * 60));
+    if let Some(delete_message_days) = delete_message_days {
+        config.delete_message_days = delete_message_days;
+    }
+    config.reason = reason;
+    if let Some(triggers) = triggers {
+        match parse_trigger_kinds(&triggers) {
+            Ok(trigger_kinds) => config.trigger_kinds = trigger_kinds,
+            Err(invalid) => {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content(format!(
+                            "`{invalid}` is not a valid trigger. Valid triggers are: {}",
+                            TriggerKind::CHANNEL
+                                .iter()
+                                .map(|trigger_kind| format!("`{}`", trigger_kind.name()))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
+    let age_rules = [
+        (new_account_days, new_account_response, "new_account"),
+        (
+            established_member_days,
+            established_member_response,
+            "established_member",
+        ),
+    ];
+    for (days, rule_response, name) in age_rules {
+        if days.is_some() != rule_response.is_some() {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!(
+                        "`{name}_days` and `{name}_response` must be given together"
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    }
+    config.new_account_age = new_account_days.map(days_to_duration);
+    config.new_account_response = new_account_response;
+    config.established_member_age = established_member_days.map(days_to_duration);
+    config.established_member_response = established_member_response;
+
+    // Age rules can pick a timeout or quarantine too, so their options apply to every response.
+    let responses = [
+        Some(response),
+        new_account_response,
+        established_member_response,
+    ];
+    if responses.contains(&Some(MessageResponse::Timeout)) {
+        config.timeout_duration = Some(timeout_duration(timeout_minutes));
+    }
+    if responses.contains(&Some(MessageResponse::Quarantine)) {
+        match quarantine_role {
+            Some(role) => config.quarantine_role_id = Some(role.id),
+            None => {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content("A `quarantine_role` is required for the `quarantine` action")
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            }
+        }
+    }
     match ctx
         .data()
         .datastore
-        .insert_message_response_config(&MessageResponseConfig {
-            guild_id: ctx.guild_id().unwrap(),
-            channel_id,
-            response,
-        })
+        .insert_message_response_config(&config)
         .await
     {
         Ok(_) => {
-            guild_channel
-                .say(
+            let template = resolve_template(
+                &ctx.data().datastore,
+                config.guild_id,
+                Some(channel_id),
+                TemplateKind::Warning,
+            )
+            .await;
+            // Forum and stage channels can't be posted in directly.
+            if matches!(
+                guild_channel.kind,
+                serenity::ChannelType::Text
+                    | serenity::ChannelType::News
+                    | serenity::ChannelType::Voice
+            ) && let Err(why) = guild_channel
+                .send_message(
                     ctx,
-                    format!(
-                        "**Do not** post in this channel unless you want to be {}",
-                        match response {
-                            MessageResponse::Ban => "banned",
-                            MessageResponse::Kick => "kicked",
-                            MessageResponse::Respond => "mocked",
-                            MessageResponse::Nothing => "ignored",
-                        },
-                    ),
+                    serenity::CreateMessage::new()
+                        .content(render_warning(&template, channel_id, response))
+                        // Templates may mention users or roles, which shouldn't be pinged by
+                        // every `/listen`.
+                        .allowed_mentions(serenity::CreateAllowedMentions::new()),
                 )
-                .await?;
+                .await
+            {
+                // Missing permissions are reported by the preflight below.
+                event!(Level::WARN, "Error posting honeypot warning: {why:?}");
+            }
+            let problems = preflight(ctx, |guild, bot_member| {
+                permissions::check_honeypot_channel(guild, bot_member, &config)
+            })
+            .await;
             ctx.send(
                 poise::CreateReply::default()
                     .content(format!(
-                        "Listening to channel <#{channel_id}>, prepared to take action `{response:?}`"
+                        "Listening to channel <#{channel_id}> for {}, prepared to take action `{response:?}`{}{problems}",
+                        config
+                            .trigger_kinds
+                            .iter()
+                            .map(|trigger_kind| format!("`{}`", trigger_kind.name()))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                        if config.dry_run { " (DRY RUN)" } else { "" }
                     ))
                     .ephemeral(true),
             )
@@ -100,6 +290,105 @@ pub async fn unlisten(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn listen_role(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Role that triggers the honeypot when a member gets it"] role: serenity::Role,
+    #[description = "Action for each member that gets the role"] response: MessageResponse,
+    #[description = "How long to time out members for, in minutes (timeout only)"]
+    #[min = 1]
+    #[max = 40320] // Discord caps timeouts at 28 days
+    timeout_minutes: Option<u64>,
+    #[description = "Role to replace the member's roles with (quarantine only)"]
+    quarantine_role: Option<serenity::Role>,
+    #[description = "Delete the member's messages from the last N minutes (checks 2000 messages per channel/thread)"]
+    #[min = 1]
+    #[max = 20160] // Discord can only bulk delete messages younger than 14 days
+    purge_minutes: Option<u64>,
+    #[description = "Days of the member's messages to delete when (soft) banning (default 7)"]
+    #[min = 0]
+    #[max = 7]
+    delete_message_days: Option<u8>,
+    #[description = "Audit log reason, supports the {user} and {channel} (role name) placeholders"]
+    #[max_length = 400]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let mut config = RoleResponseConfig::new(ctx.guild_id().unwrap(), role.id, response);
+    config.purge_window = purge_minutes.map(|minutes| Duration::from_secs(minutes * 60));
+    if let Some(delete_message_days) = delete_message_days {
+        config.delete_message_days = delete_message_days;
+    }
+    config.reason = reason;
+    match response {
+        MessageResponse::Timeout => {
+            config.timeout_duration = Some(timeout_duration(timeout_minutes));
+        }
+        MessageResponse::Quarantine => match quarantine_role {
+            Some(quarantine_role) => config.quarantine_role_id = Some(quarantine_role.id),
+            None => {
+                ctx.send(
+                    poise::CreateReply::default()
+                        .content("A `quarantine_role` is required for the `quarantine` action")
+                        .ephemeral(true),
+                )
+                .await?;
+                return Ok(());
+            }
+        },
+        _ => (),
+    }
+    let content = match ctx
+        .data()
+        .datastore
+        .insert_role_response_config(&config)
+        .await
+    {
+        Ok(_) => format!(
+            "Members that get role <@&{}> will be {}",
+            role.id,
+            response.past_tense()
+        ),
+        Err(why) => {
+            event!(Level::WARN, "Error listening to role: {why:?}");
+            format!("Error listening to role <@&{}>", role.id)
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn unlisten_role(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Role to unlisten to"] role: serenity::Role,
+) -> Result<(), Error> {
+    let result = ctx
+        .data()
+        .datastore
+        .delete_role_response_config(ctx.guild_id().unwrap(), role.id)
+        .await;
+    let content = match result {
+        Ok(_) => format!("Unlistening to role <@&{}>", role.id),
+        Err(why) => {
+            event!(Level::WARN, "Error unlistening to role: {why:?}");
+            format!("Error unlistening to role <@&{}>", role.id)
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
 #[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
 pub async fn logging_channel(
     ctx: Context<'_, context_data::ContextData, Error>,
@@ -116,10 +405,14 @@ pub async fn logging_channel(
         .await;
     match result {
         Ok(_) => {
+            let problems = preflight(ctx, |guild, bot_member| {
+                permissions::check_logging_channel(guild, bot_member, channel_id)
+            })
+            .await;
             ctx.send(
                 poise::CreateReply::default()
                     .content(format!(
-                        "Bans / kicks from this bot will be logged in channel <#{channel_id}>"
+                        "Bans / kicks from this bot will be logged in channel <#{channel_id}>{problems}"
                     ))
                     .ephemeral(true),
             )
@@ -142,3 +435,205 @@ pub async fn logging_channel(
     };
     Ok(())
 }
+
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn release(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Quarantined user to restore the roles of"] user: serenity::User,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content =
+        match release_quarantined_member(ctx, &ctx.data().datastore, guild_id, user.id).await {
+            Ok(restored_role_count) => format!(
+                "Released user <@{}> from quarantine and restored {restored_role_count} role(s)",
+                user.id
+            ),
+            Err(why) => why,
+        };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Runs preflight checks against the bot's current permissions, returning any problems found as
+/// lines to append to a command's reply.
+async fn preflight(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    check: impl FnOnce(&serenity::Guild, &serenity::Member) -> Vec<permissions::Problem>,
+) -> String {
+    let Some(guild) = ctx.guild().map(|guild| guild.clone()) else {
+        return String::new();
+    };
+    let Some(bot_member) = permissions::bot_member(ctx.serenity_context(), &guild).await else {
+        return String::new();
+    };
+    check(&guild, &bot_member)
+        .iter()
+        .map(|problem| format!("\n{problem}"))
+        .collect()
+}
+
+/// Reads a honeypot channel's config, or `None` if the channel isn't a honeypot. The datastore
+/// returns a placeholder config for channels that aren't honeypots, so the guild's honeypot
+/// channels are listed instead.
+async fn find_honeypot_channel(
+    datastore: &impl DatastoreReader,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> Result<Option<MessageResponseConfig>, errors::Error> {
+    Ok(datastore
+        .list_message_response_configs(guild_id)
+        .await?
+        .into_iter()
+        .find(|config| config.channel_id == channel_id))
+}
+
+/// The dry run setting `/listen` gives a channel: the one it was given, or else the channel's
+/// current one, so that changing a honeypot with `/listen` doesn't turn dry run off.
+async fn listen_dry_run(
+    datastore: &impl DatastoreReader,
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+    dry_run: Option<bool>,
+) -> Result<bool, errors::Error> {
+    match dry_run {
+        Some(dry_run) => Ok(dry_run),
+        None => Ok(find_honeypot_channel(datastore, guild_id, channel_id)
+            .await?
+            .is_some_and(|config| config.dry_run)),
+    }
+}
+
+/// Parses a comma separated list of trigger kinds, returning the first invalid name on error, or
+/// an empty string if there are no trigger kinds.
+fn parse_trigger_kinds(triggers: &str) -> Result<Vec<TriggerKind>, String> {
+    let mut trigger_kinds = vec![];
+    for name in triggers
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        let trigger_kind = TriggerKind::from_name(name)
+            .filter(|trigger_kind| TriggerKind::CHANNEL.contains(trigger_kind))
+            .ok_or(name.to_string())?;
+        if !trigger_kinds.contains(&trigger_kind) {
+            trigger_kinds.push(trigger_kind);
+        }
+    }
+    if trigger_kinds.is_empty() {
+        return Err(String::new());
+    }
+    Ok(trigger_kinds)
+}
+
+fn days_to_duration(days: u64) -> Duration {
+    Duration::from_secs(days * 24 * 60 * 60)
+}
+
+/// Restores the roles taken from a quarantined member and removes the quarantine role, returning
+/// how many roles were restored or a message describing why they couldn't be.
+async fn release_quarantined_member(
+    cache_http: impl serenity::CacheHttp,
+    datastore: &Datastore,
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+) -> Result<usize, String> {
+    let quarantined_member = match datastore.get_quarantined_member(guild_id, user_id).await {
+        Ok(quarantined_member) => quarantined_member,
+        Err(why) => {
+            event!(Level::WARN, "Error reading quarantined member: {why:?}");
+            return Err(format!("User <@{user_id}> is not quarantined"));
+        }
+    };
+
+    // Keep any roles the member was given while quarantined, except for the quarantine role.
+    let member = match guild_id.member(&cache_http, user_id).await {
+        Ok(member) => member,
+        Err(why) => {
+            event!(Level::WARN, "Error fetching quarantined member: {why:?}");
+            return Err(format!("User <@{user_id}> is no longer in the server"));
+        }
+    };
+    let role_ids: Vec<serenity::RoleId> = member
+        .roles
+        .iter()
+        .filter(|role_id| **role_id != quarantined_member.quarantine_role_id)
+        .chain(quarantined_member.removed_role_ids.iter())
+        .copied()
+        .collect();
+    let result = guild_id
+        .edit_member(
+            cache_http.http(),
+            user_id,
+            serenity::EditMember::new()
+                .roles(role_ids)
+                .audit_log_reason("released from honeypot quarantine"),
+        )
+        .await;
+    if let Err(why) = result {
+        event!(
+            Level::WARN,
+            "Error restoring roles of quarantined member: {why:?}"
+        );
+        return Err(format!("Error restoring the roles of user <@{user_id}>"));
+    }
+
+    if let Err(why) = datastore.delete_quarantined_member(guild_id, user_id).await {
+        event!(Level::WARN, "Error deleting quarantined member: {why:?}");
+    }
+    Ok(quarantined_member.removed_role_ids.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::datastore::test_utils::test_each_backend;
+
+    use super::*;
+
+    test_each_backend! {
+        async fn listen_keeps_dry_run(db, restarted_db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let channel_id = serenity::ChannelId::new(87654321);
+            let mut config = MessageResponseConfig::new(guild_id, channel_id, MessageResponse::Ban);
+            config.dry_run = true;
+            datastore
+                .insert_message_response_config(&config)
+                .await
+                .unwrap();
+
+            // After a restart, with the channel's placeholder or real config cached
+            let restarted_datastore =
+                Datastore::new(/* cache= */ Default::default(), /* database= */ restarted_db);
+            let other_channel_id = serenity::ChannelId::new(12345678);
+            for channel_id in [channel_id, other_channel_id] {
+                restarted_datastore
+                    .get_message_response_config(guild_id, channel_id)
+                    .await
+                    .unwrap();
+            }
+            let result = listen_dry_run(&restarted_datastore, guild_id, channel_id, None).await;
+            assert_eq!(result, Ok(true));
+            let result =
+                listen_dry_run(&restarted_datastore, guild_id, channel_id, Some(false)).await;
+            assert_eq!(result, Ok(false));
+
+            // New honeypot channels aren't dry run unless asked to be
+            let result =
+                listen_dry_run(&restarted_datastore, guild_id, other_channel_id, None).await;
+            assert_eq!(result, Ok(false));
+
+            // Clean up rows:
+            datastore.delete_guild_configs(guild_id).await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use super::timeout_duration;
+use crate::{
+    burst_detector::MAX_WINDOW,
+    context_data,
+    datastore::{
+        errors,
+        models::{BurstDetectionConfig, MessageResponse},
+        prelude::*,
+    },
+    utils::format_duration,
+};
+
+#[poise::command(
+    slash_command,
+    subcommands(
+        "burst_detection_enable",
+        "burst_detection_show",
+        "burst_detection_disable"
+    ),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn burst_detection(
+    _ctx: Context<'_, context_data::ContextData, Error>,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, rename = "enable")]
+async fn burst_detection_enable(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "How many different channels the same message has to be posted in"]
+    #[min = 2]
+    #[max = 50] // Keep in sync with `burst_detector::MAX_CHANNEL_COUNT`
+    channel_count: u32,
+    #[description = "Seconds the messages have to be posted within"]
+    #[min = 1]
+    #[max = 600] // Keep in sync with `burst_detector::MAX_WINDOW`
+    window_seconds: u64,
+    #[description = "Action for users posting a burst (bans delete 7 days of messages)"]
+    response: MessageResponse,
+    #[description = "How long to time out users for, in minutes (timeout only)"]
+    #[min = 1]
+    #[max = 40320] // Discord caps timeouts at 28 days
+    timeout_minutes: Option<u64>,
+    #[description = "Role to replace the user's roles with (quarantine only)"]
+    quarantine_role: Option<serenity::Role>,
+    #[description = "Delete the user's messages from the burst"] delete_messages: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let quarantine_role_id = match (response, quarantine_role) {
+        (MessageResponse::Quarantine, Some(role)) => Some(role.id),
+        (MessageResponse::Quarantine, None) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("A `quarantine_role` is required for the `quarantine` action")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+        _ => None,
+    };
+    let burst_detection_config = BurstDetectionConfig {
+        guild_id,
+        channel_count,
+        window: Duration::from_secs(window_seconds).min(MAX_WINDOW),
+        response,
+        timeout_duration: (response == MessageResponse::Timeout)
+            .then(|| timeout_duration(timeout_minutes)),
+        quarantine_role_id,
+        delete_messages: delete_messages.unwrap_or(false),
+    };
+    let content = match ctx
+        .data()
+        .datastore
+        .insert_burst_detection_config(&burst_detection_config)
+        .await
+    {
+        Ok(_) => format!(
+            "Burst detection is enabled: {}",
+            describe_config(&burst_detection_config)
+        ),
+        Err(why) => {
+            event!(
+                Level::WARN,
+                "Error inserting burst detection config: {why:?}"
+            );
+            "Error enabling burst detection".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "show")]
+async fn burst_detection_show(
+    ctx: Context<'_, context_data::ContextData, Error>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx
+        .data()
+        .datastore
+        .get_burst_detection_config(guild_id)
+        .await
+    {
+        Ok(burst_detection_config) => format!(
+            "Burst detection is enabled: {}",
+            describe_config(&burst_detection_config)
+        ),
+        Err(errors::Error::DatabaseEntryNotFound) => "Burst detection is disabled".to_string(),
+        Err(why) => {
+            event!(Level::WARN, "Error reading burst detection config: {why:?}");
+            "Error reading the burst detection config".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "disable")]
+async fn burst_detection_disable(
+    ctx: Context<'_, context_data::ContextData, Error>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx
+        .data()
+        .datastore
+        .delete_burst_detection_config(guild_id)
+        .await
+    {
+        Ok(_) => "Burst detection is disabled",
+        Err(why) => {
+            event!(
+                Level::WARN,
+                "Error deleting burst detection config: {why:?}"
+            );
+            "Error disabling burst detection"
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe_config(burst_detection_config: &BurstDetectionConfig) -> String {
+    let mut description = format!(
+        "users posting the same message in {} channels within {} are handled with `{}`",
+        burst_detection_config.channel_count,
+        format_duration(burst_detection_config.window),
+        burst_detection_config.response.name(),
+    );
+    if let Some(timeout_duration) = burst_detection_config.timeout_duration {
+        description += &format!(" for {}", format_duration(timeout_duration));
+    }
+    if let Some(role_id) = burst_detection_config.quarantine_role_id {
+        description += &format!(" using <@&{role_id}>");
+    }
+    if burst_detection_config.delete_messages {
+        description += ", and their messages are deleted";
+    }
+    description
+}
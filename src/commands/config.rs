@@ -0,0 +1,214 @@
+use std::{collections::HashSet, time::Duration};
+
+use poise::{
+    Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{context_data, datastore::prelude::*, guild_config};
+
+/// Config documents are small, anything bigger than this isn't one.
+const MAX_CONFIG_SIZE: u32 = 1 << 20;
+
+/// How long the Apply and Cancel buttons of `/config import` wait for a click.
+const IMPORT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Messages are limited to 2000 characters by Discord.
+const MAX_LINES_LENGTH: usize = 1700;
+
+#[poise::command(
+    slash_command,
+    subcommands("config_export", "config_import"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn config(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Exports the server's honeypots and settings as a JSON file
+#[poise::command(slash_command, rename = "export")]
+async fn config_export(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let reply = match guild_config::export(ctx.data().datastore.as_ref(), guild_id).await {
+        Ok(guild_config) => poise::CreateReply::default()
+            .content("Import this file with `/config import` to copy these settings")
+            .attachment(serenity::CreateAttachment::bytes(
+                guild_config.to_json().into_bytes(),
+                format!("honeybot-{guild_id}.json"),
+            )),
+        Err(why) => {
+            event!(Level::WARN, "Error exporting guild config: {why:?}");
+            poise::CreateReply::default().content("Error exporting the server's settings")
+        }
+    };
+    ctx.send(reply.ephemeral(true)).await?;
+    Ok(())
+}
+
+/// Replaces the server's honeypots and settings with an exported JSON file, after a preview
+#[poise::command(slash_command, rename = "import")]
+async fn config_import(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "JSON file from `/config export` or `honeybot config export`"]
+    file: serenity::Attachment,
+) -> Result<(), Error> {
+    let Some(guild) = ctx.guild().map(|guild| guild.clone()) else {
+        return Ok(());
+    };
+    let datastore = ctx.data().datastore.as_ref();
+    let error = |content: String| {
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+    };
+
+    if file.size > MAX_CONFIG_SIZE {
+        ctx.send(error("The file is too big to be a config".to_string()))
+            .await?;
+        return Ok(());
+    }
+    let guild_config = match file.download().await {
+        Ok(bytes) => match String::from_utf8(bytes) {
+            Ok(json) => guild_config::GuildConfig::parse(&json),
+            Err(_) => Err("The file isn't text".to_string()),
+        },
+        Err(why) => {
+            event!(Level::WARN, "Error downloading config attachment: {why:?}");
+            Err("Error downloading the file".to_string())
+        }
+    };
+    let guild_config = match guild_config {
+        Ok(guild_config) => guild_config,
+        Err(why) => {
+            ctx.send(error(why)).await?;
+            return Ok(());
+        }
+    };
+
+    let channel_ids: HashSet<serenity::ChannelId> = guild
+        .channels
+        .keys()
+        .copied()
+        .chain(guild.threads.iter().map(|thread| thread.id))
+        .collect();
+    let role_ids: HashSet<serenity::RoleId> = guild.roles.keys().copied().collect();
+    let mut problems = guild_config.validate();
+    problems.extend(guild_config.missing_ids(&channel_ids, &role_ids));
+    if !problems.is_empty() {
+        ctx.send(error(format!(
+            "Nothing was imported, the file has problems:\n{}",
+            fit_lines(
+                &problems
+                    .iter()
+                    .map(|problem| format!("- {problem}"))
+                    .collect::<Vec<_>>()
+            )
+        )))
+        .await?;
+        return Ok(());
+    }
+
+    let current = match guild_config::export(datastore, guild.id).await {
+        Ok(current) => current,
+        Err(why) => {
+            event!(Level::WARN, "Error exporting guild config: {why:?}");
+            ctx.send(error("Error reading the server's settings".to_string()))
+                .await?;
+            return Ok(());
+        }
+    };
+    let diff = guild_config.diff(&current);
+    if diff.is_empty() {
+        ctx.send(error(
+            "The file matches the server's settings, nothing to import".to_string(),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let apply_id = format!("{}:apply", ctx.id());
+    let cancel_id = format!("{}:cancel", ctx.id());
+    let preview = format!(
+        "Importing will make these changes:\n```diff\n{}\n```",
+        fit_lines(&diff)
+    );
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(&preview)
+                .components(vec![serenity::CreateActionRow::Buttons(vec![
+                    serenity::CreateButton::new(&apply_id)
+                        .label("Apply")
+                        .style(serenity::ButtonStyle::Danger),
+                    serenity::CreateButton::new(&cancel_id)
+                        .label("Cancel")
+                        .style(serenity::ButtonStyle::Secondary),
+                ])])
+                .ephemeral(true),
+        )
+        .await?;
+
+    let filter_ids = [apply_id.clone(), cancel_id];
+    let interaction = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .timeout(IMPORT_CONFIRMATION_TIMEOUT)
+        .filter(move |interaction| filter_ids.contains(&interaction.data.custom_id))
+        .await;
+    let Some(interaction) = interaction else {
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content(format!(
+                        "{preview}\nNothing was imported, the preview expired"
+                    ))
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(());
+    };
+    let content = if interaction.data.custom_id != apply_id {
+        "Import cancelled, nothing was changed".to_string()
+    } else {
+        match datastore
+            .replace_guild_configs(guild.id, &guild_config)
+            .await
+        {
+            Ok(_) => format!("Imported {} changes", diff.len()),
+            Err(why) => {
+                event!(Level::WARN, "Error importing guild config: {why:?}");
+                "Error importing the settings, nothing was changed".to_string()
+            }
+        }
+    };
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                serenity::CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Joins lines, leaving out the ones that don't fit in a message.
+fn fit_lines(lines: &[String]) -> String {
+    let mut formatted = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line = line.replace("```", "'''");
+        if formatted.len() + line.len() > MAX_LINES_LENGTH {
+            formatted += &format!("… and {} more", lines.len() - index);
+            break;
+        }
+        formatted += &line;
+        formatted.push('\n');
+    }
+    formatted.trim_end().to_string()
+}
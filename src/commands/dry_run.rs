@@ -0,0 +1,100 @@
+use poise::{
+    Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{context_data, datastore::prelude::*};
+
+#[poise::command(
+    slash_command,
+    subcommands("dry_run_on", "dry_run_off"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn dry_run(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "on")]
+async fn dry_run_on(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Honeypot channel to dry run, or leave empty for the whole server"]
+    channel: Option<serenity::Channel>,
+) -> Result<(), Error> {
+    set_dry_run(ctx, channel, true).await
+}
+
+#[poise::command(slash_command, rename = "off")]
+async fn dry_run_off(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Honeypot channel to stop dry running, or leave empty for the whole server"]
+    channel: Option<serenity::Channel>,
+) -> Result<(), Error> {
+    set_dry_run(ctx, channel, false).await
+}
+
+/// Sets the dry run flag of a honeypot channel, or of the guild if no channel is given.
+async fn set_dry_run(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    channel: Option<serenity::Channel>,
+    dry_run: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let datastore = &ctx.data().datastore;
+    let state = if dry_run {
+        "only log what they would do"
+    } else {
+        "take action"
+    };
+    let content = match channel {
+        Some(channel) => {
+            let channel_id = channel.id();
+            match super::find_honeypot_channel(datastore.as_ref(), guild_id, channel_id).await {
+                Ok(Some(mut config)) => {
+                    config.dry_run = dry_run;
+                    match datastore.insert_message_response_config(&config).await {
+                        Ok(_) => format!("Honeypot channel <#{channel_id}> will now {state}"),
+                        Err(why) => {
+                            event!(Level::WARN, "Error updating message response: {why:?}");
+                            format!("Error updating honeypot channel <#{channel_id}>")
+                        }
+                    }
+                }
+                Ok(None) => format!("<#{channel_id}> is not a honeypot channel"),
+                Err(why) => {
+                    event!(Level::WARN, "Error reading message response: {why:?}");
+                    format!("Error reading honeypot channel <#{channel_id}>")
+                }
+            }
+        }
+        None => match datastore.get_guild_settings(guild_id).await {
+            Ok(mut guild_settings) => {
+                guild_settings.dry_run = dry_run;
+                match datastore.insert_guild_settings(&guild_settings).await {
+                    Ok(_) if dry_run => format!("All honeypots will now {state}"),
+                    Ok(_) => format!(
+                        "Honeypots will now {state}, unless their channel is dry run with `/dry_run on`"
+                    ),
+                    Err(why) => {
+                        event!(Level::WARN, "Error inserting guild settings: {why:?}");
+                        "Error saving the dry run setting".to_string()
+                    }
+                }
+            }
+            Err(why) => {
+                event!(Level::WARN, "Error reading guild settings: {why:?}");
+                "Error reading the dry run setting".to_string()
+            }
+        },
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use std::time::Duration;
+
+use poise::{ChoiceParameter, Context, serenity_prelude::Error};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{
+        errors,
+        models::{EscalationPolicy, MessageResponse},
+        prelude::*,
+    },
+    utils::format_duration,
+};
+
+/// Used when `/escalation set` isn't given a decay window.
+const DEFAULT_DECAY_DAYS: u64 = 30;
+
+#[poise::command(
+    slash_command,
+    subcommands("escalation_set", "escalation_show", "escalation_clear"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn escalation(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "set")]
+async fn escalation_set(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Comma separated responses for each offense, e.g. \"respond, kick, ban\""]
+    ladder: String,
+    #[description = "Days before an offense stops counting (default 30)"]
+    #[min = 1]
+    decay_days: Option<u64>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let ladder = match parse_ladder(&ladder) {
+        Ok(ladder) => ladder,
+        Err(why) => {
+            let content = match why {
+                LadderError::Empty => "The ladder needs at least one response".to_string(),
+                LadderError::Quarantine => "`quarantine` can't be used in the ladder, since \
+                    escalation policies don't have a quarantine role"
+                    .to_string(),
+                LadderError::Invalid(invalid) => format!(
+                    "`{invalid}` is not a valid response. Valid responses are: {}",
+                    MessageResponse::list()
+                        .iter()
+                        .map(|choice| format!("`{}`", choice.name))
+                        .filter(|name| name != "`quarantine`")
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(content)
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let escalation_policy = EscalationPolicy {
+        guild_id,
+        ladder,
+        decay_window: Duration::from_secs(decay_days.unwrap_or(DEFAULT_DECAY_DAYS) * 24 * 60 * 60),
+    };
+    match ctx
+        .data()
+        .datastore
+        .insert_escalation_policy(&escalation_policy)
+        .await
+    {
+        Ok(_) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!(
+                        "Honeypot channels will now escalate responses:\n{}",
+                        describe_policy(&escalation_policy)
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error inserting escalation policy: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("Error setting the escalation policy")
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "show")]
+async fn escalation_show(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx.data().datastore.get_escalation_policy(guild_id).await {
+        Ok(escalation_policy) => describe_policy(&escalation_policy),
+        Err(errors::Error::DatabaseEntryNotFound) => {
+            "No escalation policy is set, each honeypot channel uses its own response".to_string()
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading escalation policy: {why:?}");
+            "Error reading the escalation policy".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "clear")]
+async fn escalation_clear(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx
+        .data()
+        .datastore
+        .delete_escalation_policy(guild_id)
+        .await
+    {
+        Ok(_) => "Removed the escalation policy, each honeypot channel uses its own response",
+        Err(why) => {
+            event!(Level::WARN, "Error deleting escalation policy: {why:?}");
+            "Error removing the escalation policy"
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Why `/escalation set` was given a ladder it can't use.
+#[derive(Debug, PartialEq)]
+enum LadderError {
+    Empty,
+    /// A response name that doesn't exist
+    Invalid(String),
+    /// Quarantining needs a role, which escalation policies don't have.
+    Quarantine,
+}
+
+/// Parses a comma separated list of response names.
+fn parse_ladder(ladder: &str) -> Result<Vec<MessageResponse>, LadderError> {
+    let ladder = ladder
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| match MessageResponse::from_name(name) {
+            Some(MessageResponse::Quarantine) => Err(LadderError::Quarantine),
+            Some(response) => Ok(response),
+            None => Err(LadderError::Invalid(name.to_string())),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if ladder.is_empty() {
+        return Err(LadderError::Empty);
+    }
+    Ok(ladder)
+}
+
+fn describe_policy(escalation_policy: &EscalationPolicy) -> String {
+    let steps = escalation_policy
+        .ladder
+        .iter()
+        .enumerate()
+        .map(|(index, response)| format!("{}. {}", index + 1, response.name()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{steps}\nOffenses stop counting after {}.",
+        format_duration(escalation_policy.decay_window)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_ladder() {
+        assert_eq!(
+            super::parse_ladder("respond, kick,ban"),
+            Ok(vec![
+                MessageResponse::Respond,
+                MessageResponse::Kick,
+                MessageResponse::Ban
+            ])
+        );
+        assert_eq!(super::parse_ladder(" , "), Err(LadderError::Empty));
+        assert_eq!(
+            super::parse_ladder("kick, explode"),
+            Err(LadderError::Invalid("explode".to_string()))
+        );
+        assert_eq!(
+            super::parse_ladder("timeout, quarantine, ban"),
+            Err(LadderError::Quarantine)
+        );
+    }
+}
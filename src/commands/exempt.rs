@@ -0,0 +1,226 @@
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{
+        errors,
+        models::{Exemption, ExemptionMode},
+        prelude::*,
+    },
+};
+
+#[poise::command(
+    slash_command,
+    subcommands("exempt_add", "exempt_remove", "exempt_list", "exempt_settings"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn exempt(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "add")]
+async fn exempt_add(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "User to exempt from honeypot actions"] user: Option<serenity::User>,
+    #[description = "Role to exempt from honeypot actions"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let Some(exemption) = exemption_from_args(ctx, user, role).await? else {
+        return Ok(());
+    };
+    let content = match ctx
+        .data()
+        .datastore
+        .insert_exemption(guild_id, exemption)
+        .await
+    {
+        Ok(_) => format!("{} is now exempt from honeypot actions", mention(exemption)),
+        Err(why) => {
+            event!(Level::WARN, "Error inserting exemption: {why:?}");
+            format!("Error exempting {}", mention(exemption))
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "remove")]
+async fn exempt_remove(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "User to stop exempting"] user: Option<serenity::User>,
+    #[description = "Role to stop exempting"] role: Option<serenity::Role>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let Some(exemption) = exemption_from_args(ctx, user, role).await? else {
+        return Ok(());
+    };
+    let content = match ctx
+        .data()
+        .datastore
+        .delete_exemption(guild_id, exemption)
+        .await
+    {
+        Ok(_) => format!(
+            "{} is no longer exempt from honeypot actions",
+            mention(exemption)
+        ),
+        Err(errors::Error::DatabaseEntryNotFound) => {
+            format!("{} is not exempt", mention(exemption))
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error deleting exemption: {why:?}");
+            format!("Error removing the exemption of {}", mention(exemption))
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "list")]
+async fn exempt_list(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let datastore = &ctx.data().datastore;
+    let content = match (
+        datastore.get_exemptions(guild_id).await,
+        datastore.get_guild_settings(guild_id).await,
+    ) {
+        (Ok(exemptions), Ok(guild_settings)) => {
+            let mut lines = vec![format!(
+                "Exempt posters are handled with mode `{}`, moderators are {}exempt.",
+                guild_settings.exemption_mode.name(),
+                if guild_settings.exempt_moderators {
+                    ""
+                } else {
+                    "not "
+                }
+            )];
+            if exemptions.is_empty() {
+                lines.push("No users or roles are exempt".to_string());
+            }
+            lines.extend(
+                exemptions
+                    .into_iter()
+                    .map(|exemption| format!("- {}", mention(exemption))),
+            );
+            lines.join("\n")
+        }
+        (Err(why), _) | (_, Err(why)) => {
+            event!(Level::WARN, "Error reading exemptions: {why:?}");
+            "Error reading the exemption list".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "settings")]
+async fn exempt_settings(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Whether exempt posters are ignored or only warned"] mode: Option<
+        ExemptionMode,
+    >,
+    #[description = "Exempt members with the Administrator or Manage Messages permission"]
+    exempt_moderators: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let datastore = &ctx.data().datastore;
+    let mut guild_settings = match datastore.get_guild_settings(guild_id).await {
+        Ok(guild_settings) => guild_settings,
+        Err(why) => {
+            event!(Level::WARN, "Error reading guild settings: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("Error reading the exemption settings")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    if let Some(mode) = mode {
+        guild_settings.exemption_mode = mode;
+    }
+    if let Some(exempt_moderators) = exempt_moderators {
+        guild_settings.exempt_moderators = exempt_moderators;
+    }
+    let content = match datastore.insert_guild_settings(&guild_settings).await {
+        Ok(_) => format!(
+            "Exempt posters are handled with mode `{}`, moderators are {}exempt",
+            guild_settings.exemption_mode.name(),
+            if guild_settings.exempt_moderators {
+                ""
+            } else {
+                "not "
+            }
+        ),
+        Err(why) => {
+            event!(Level::WARN, "Error inserting guild settings: {why:?}");
+            "Error saving the exemption settings".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Picks the exemption described by the command's arguments, telling the user and returning
+/// `None` unless exactly one of `user` and `role` was given.
+async fn exemption_from_args(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    user: Option<serenity::User>,
+    role: Option<serenity::Role>,
+) -> Result<Option<Exemption>, Error> {
+    match (user, role) {
+        (Some(user), None) => Ok(Some(Exemption::User(user.id))),
+        (None, Some(role)) => Ok(Some(Exemption::Role(role.id))),
+        _ => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("Give either a `user` or a `role`")
+                    .ephemeral(true),
+            )
+            .await?;
+            Ok(None)
+        }
+    }
+}
+
+fn mention(exemption: Exemption) -> String {
+    match exemption {
+        Exemption::User(user_id) => format!("<@{user_id}>"),
+        Exemption::Role(role_id) => format!("<@&{role_id}>"),
+    }
+}
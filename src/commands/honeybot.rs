@@ -0,0 +1,194 @@
+use poise::{
+    Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{errors, prelude::*},
+    permissions::{self, Problem},
+    utils::truncate_description,
+};
+
+#[poise::command(
+    slash_command,
+    subcommands("honeybot_doctor"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn honeybot(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Checks the bot's permissions and role position against everything it's set up to do
+#[poise::command(slash_command, rename = "doctor")]
+async fn honeybot_doctor(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild().map(|guild| guild.clone()) else {
+        return Ok(());
+    };
+    let Some(bot_member) = permissions::bot_member(ctx.serenity_context(), &guild).await else {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("Error looking up the bot's roles in this server")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    };
+    let datastore = &ctx.data().datastore;
+    let mut sections: Vec<(String, Vec<Problem>)> = vec![];
+
+    match datastore.list_message_response_configs(guild.id).await {
+        Ok(configs) => {
+            for config in &configs {
+                sections.push((
+                    format!("Honeypot channel <#{}>", config.channel_id),
+                    permissions::check_honeypot_channel(&guild, &bot_member, config),
+                ));
+            }
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading honeypot channels: {why:?}");
+            sections.push(("Honeypot channels".to_string(), vec![read_error("them")]));
+        }
+    }
+
+    match datastore.get_role_response_configs(guild.id).await {
+        Ok(configs) => {
+            for config in &configs {
+                let mut problems = permissions::check_responses(
+                    &guild,
+                    &bot_member,
+                    &[config.response],
+                    config.quarantine_role_id,
+                    config.purge_window.is_some(),
+                );
+                if !guild.roles.contains_key(&config.role_id) {
+                    problems.insert(
+                        0,
+                        Problem {
+                            description: "The role no longer exists".to_string(),
+                            fix: "Remove it with `/unlisten_role`".to_string(),
+                        },
+                    );
+                }
+                sections.push((format!("Honeypot role <@&{}>", config.role_id), problems));
+            }
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading honeypot roles: {why:?}");
+            sections.push(("Honeypot roles".to_string(), vec![read_error("them")]));
+        }
+    }
+
+    match datastore.get_content_rules(guild.id).await {
+        Ok(content_rules) => {
+            for content_rule in &content_rules {
+                sections.push((
+                    format!("Content rule `#{}`", content_rule.id),
+                    permissions::check_responses(
+                        &guild,
+                        &bot_member,
+                        &[content_rule.response],
+                        content_rule.quarantine_role_id,
+                        content_rule.delete_message,
+                    ),
+                ));
+            }
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading content rules: {why:?}");
+            sections.push(("Content rules".to_string(), vec![read_error("them")]));
+        }
+    }
+
+    match datastore.get_burst_detection_config(guild.id).await {
+        Ok(config) => sections.push((
+            "Burst detection".to_string(),
+            permissions::check_responses(
+                &guild,
+                &bot_member,
+                &[config.response],
+                config.quarantine_role_id,
+                config.delete_messages,
+            ),
+        )),
+        Err(errors::Error::DatabaseEntryNotFound) => (),
+        Err(why) => {
+            event!(Level::WARN, "Error reading burst detection config: {why:?}");
+            sections.push(("Burst detection".to_string(), vec![read_error("it")]));
+        }
+    }
+
+    // Escalation can pick any response on the ladder, wherever the honeypot is.
+    match datastore.get_escalation_policy(guild.id).await {
+        Ok(escalation_policy) => sections.push((
+            "Escalation policy".to_string(),
+            permissions::check_responses(
+                &guild,
+                &bot_member,
+                &escalation_policy.ladder,
+                None,
+                false,
+            ),
+        )),
+        Err(errors::Error::DatabaseEntryNotFound) => (),
+        Err(why) => {
+            event!(Level::WARN, "Error reading escalation policy: {why:?}");
+            sections.push(("Escalation policy".to_string(), vec![read_error("it")]));
+        }
+    }
+
+    match datastore.get_logging_channel(guild.id).await {
+        Ok(logging_channel_id) => sections.push((
+            "Logging channel".to_string(),
+            permissions::check_logging_channel(&guild, &bot_member, logging_channel_id),
+        )),
+        Err(errors::Error::DatabaseEntryNotFound) => sections.push((
+            "Logging channel".to_string(),
+            vec![Problem {
+                description: "No logging channel is set, so actions and failures aren't logged"
+                    .to_string(),
+                fix: "Set one with `/logging_channel`".to_string(),
+            }],
+        )),
+        Err(why) => {
+            event!(Level::WARN, "Error reading logging channel: {why:?}");
+            sections.push(("Logging channel".to_string(), vec![read_error("it")]));
+        }
+    }
+
+    let lines: Vec<String> = sections
+        .iter()
+        .filter(|(_, problems)| !problems.is_empty())
+        .map(|(name, problems)| {
+            let mut section = format!("**{name}**");
+            for problem in problems {
+                section += &format!("\n{problem}");
+            }
+            section
+        })
+        .collect();
+    let embed = if lines.is_empty() {
+        serenity::CreateEmbed::new()
+            .title("No problems found")
+            .description("The bot can do everything it's set up to do")
+            .colour(serenity::Colour::DARK_GREEN)
+    } else {
+        serenity::CreateEmbed::new()
+            .title("Problems found")
+            .description(truncate_description(lines.join("\n\n")))
+            .colour(serenity::Colour::ORANGE)
+    };
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+fn read_error(what: &str) -> Problem {
+    Problem {
+        description: format!("Error reading {what} from the database"),
+        fix: "Try again later, or check the bot's logs".to_string(),
+    }
+}
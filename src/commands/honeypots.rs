@@ -0,0 +1,159 @@
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{
+        errors,
+        models::{IncidentStats, MessageResponseConfig},
+        prelude::*,
+    },
+    permissions,
+    utils::truncate_description,
+};
+
+/// Shows the honeypot channels, their triggers, and problems with them or the logging channel
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn honeypots(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let Some(guild) = ctx.guild().map(|guild| guild.clone()) else {
+        return Ok(());
+    };
+    let datastore = &ctx.data().datastore;
+    let (configs, stats) = match (
+        datastore.list_message_response_configs(guild.id).await,
+        datastore.get_incident_stats(guild.id).await,
+    ) {
+        (Ok(configs), Ok(stats)) => (configs, stats),
+        (Err(why), _) | (_, Err(why)) => {
+            event!(Level::WARN, "Error reading honeypot channels: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content("Error reading the honeypot channels")
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+    let dry_run = match datastore.get_guild_settings(guild.id).await {
+        Ok(guild_settings) => guild_settings.dry_run,
+        Err(why) => {
+            event!(Level::WARN, "Error reading guild settings: {why:?}");
+            false
+        }
+    };
+    let bot_member = permissions::bot_member(ctx.serenity_context(), &guild).await;
+
+    let mut lines = vec![];
+    if dry_run {
+        lines.push("**Every honeypot is in a DRY RUN**".to_string());
+    }
+    if configs.is_empty() {
+        lines.push("No channels are honeypots, add one with `/listen`".to_string());
+    }
+    for config in &configs {
+        let channel_stats = stats
+            .iter()
+            .find(|channel_stats| channel_stats.channel_id == Some(config.channel_id));
+        lines.push(describe_channel(
+            &guild,
+            bot_member.as_ref(),
+            config,
+            channel_stats,
+        ));
+    }
+    let logging_channel = match datastore.get_logging_channel(guild.id).await {
+        Ok(logging_channel_id) => {
+            describe_logging_channel(&guild, bot_member.as_ref(), logging_channel_id)
+        }
+        Err(errors::Error::DatabaseEntryNotFound) => {
+            "Not set, actions aren't logged. Set one with `/logging_channel`".to_string()
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading logging channel: {why:?}");
+            "Error reading the logging channel".to_string()
+        }
+    };
+
+    let description = truncate_description(lines.join("\n"));
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(
+                serenity::CreateEmbed::new()
+                    .title("Honeypot channels")
+                    .description(description)
+                    .field("Logging channel", logging_channel, false),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe_channel(
+    guild: &serenity::Guild,
+    bot_member: Option<&serenity::Member>,
+    config: &MessageResponseConfig,
+    stats: Option<&IncidentStats>,
+) -> String {
+    let mut description = format!(
+        "- <#{}> **{}** on {}",
+        config.channel_id,
+        config.response.name(),
+        config
+            .trigger_kinds
+            .iter()
+            .map(|trigger_kind| trigger_kind.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if config.dry_run {
+        description += " (DRY RUN)";
+    }
+    match stats {
+        Some(stats) => {
+            description += &format!(
+                ", triggered {} times, last <t:{}:R>",
+                stats.count,
+                stats.last_created_at.unix_timestamp()
+            )
+        }
+        None => description += ", never triggered",
+    }
+    match bot_member {
+        Some(bot_member) => {
+            for problem in permissions::check_honeypot_channel(guild, bot_member, config) {
+                description += &format!("\n  {problem}");
+            }
+        }
+        None if !guild.channels.contains_key(&config.channel_id) => {
+            description += "\n  ⚠️ The channel no longer exists"
+        }
+        None => (),
+    }
+    description
+}
+
+fn describe_logging_channel(
+    guild: &serenity::Guild,
+    bot_member: Option<&serenity::Member>,
+    logging_channel_id: serenity::ChannelId,
+) -> String {
+    let problems = match bot_member {
+        Some(bot_member) => {
+            permissions::check_logging_channel(guild, bot_member, logging_channel_id)
+        }
+        None => vec![],
+    };
+    match problems.as_slice() {
+        [] => format!("<#{logging_channel_id}>"),
+        problems => problems
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
@@ -0,0 +1,110 @@
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{
+        models::{Incident, IncidentFilter, MessageResponse, TriggerKind},
+        prelude::*,
+    },
+};
+
+// Kept small so a page of incidents fits in a single Discord message
+const INCIDENTS_PER_PAGE: u32 = 5;
+const MAX_FIELD_LENGTH: usize = 80;
+
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn incidents(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Only show incidents for this user"] user: Option<serenity::User>,
+    #[description = "Only show incidents in this channel"] channel: Option<serenity::Channel>,
+    #[description = "Only show incidents with this action"] action: Option<MessageResponse>,
+    #[description = "Page of incidents to show, newest first (default 1)"]
+    #[min = 1]
+    page: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let page = page.unwrap_or(1);
+    let filter = IncidentFilter {
+        guild_id,
+        user_id: user.map(|user| user.id),
+        channel_id: channel.map(|channel| channel.id()),
+        action,
+        include_dry_runs: true,
+        limit: INCIDENTS_PER_PAGE,
+        offset: (page - 1) * INCIDENTS_PER_PAGE,
+    };
+    let content = match ctx.data().datastore.list_incidents(&filter).await {
+        Ok(incidents) if incidents.is_empty() => format!("No incidents found on page {page}"),
+        Ok(incidents) => format!(
+            "**Incidents (page {page})**\n{}",
+            incidents
+                .iter()
+                .map(describe_incident)
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+        Err(why) => {
+            event!(Level::WARN, "Error listing incidents: {why:?}");
+            "Error listing incidents".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe_incident(incident: &Incident) -> String {
+    let location = match incident.channel_id {
+        Some(channel_id) => format!("in <#{channel_id}>"),
+        None => "by taking a role".to_string(),
+    };
+    let mut description = format!(
+        "`#{}` <t:{}:f> <@{}> {location}: **{}**",
+        incident.id,
+        incident.created_at.unix_timestamp(),
+        incident.user_id,
+        incident.action.name(),
+    );
+    if incident.dry_run {
+        description += " (DRY RUN)";
+    }
+    if !matches!(
+        incident.trigger_kind,
+        TriggerKind::Message | TriggerKind::Role
+    ) {
+        description += &format!(" ({})", incident.trigger_kind.name());
+    }
+    if !incident.content.is_empty() {
+        description += &format!(" \"{}\"", shorten(&incident.content));
+    }
+    if let Some(outcome) = &incident.outcome {
+        description += &format!(" {}", shorten(outcome));
+    }
+    if let Some(error) = &incident.error {
+        description += &format!(" **Failed:** {}", shorten(error));
+    }
+    description
+}
+
+/// Truncates the text and keeps it on a single line.
+fn shorten(text: &str) -> String {
+    let mut shortened: String = text
+        .chars()
+        .take(MAX_FIELD_LENGTH)
+        .map(|c| if c == '\n' { ' ' } else { c })
+        .collect();
+    if text.chars().count() > MAX_FIELD_LENGTH {
+        shortened.push('…');
+    }
+    shortened
+}
@@ -0,0 +1,168 @@
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    context_data,
+    datastore::{
+        models::{MessageResponse, MessageTemplate, TemplateKind},
+        prelude::*,
+    },
+    templates::{PLACEHOLDERS_HELP, render, render_warning, resolve_template},
+};
+
+#[poise::command(
+    slash_command,
+    subcommands(
+        "message_template_set",
+        "message_template_preview",
+        "message_template_reset"
+    ),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn message_template(
+    _ctx: Context<'_, context_data::ContextData, Error>,
+) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "set")]
+async fn message_template_set(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Message to customize"] kind: TemplateKind,
+    #[description = "New template, supports the {user}, {channel} and {action} placeholders"]
+    #[max_length = 1500]
+    template: String,
+    #[description = "Only use this template in one channel (defaults to the whole server)"]
+    channel: Option<serenity::Channel>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let channel_id = channel.map(|channel| channel.id());
+    let result = ctx
+        .data()
+        .datastore
+        .insert_message_template(&MessageTemplate {
+            guild_id,
+            channel_id,
+            kind,
+            template,
+        })
+        .await;
+    match result {
+        Ok(_) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!(
+                        "Set the `{}` template for {}. Placeholders: {PLACEHOLDERS_HELP}",
+                        kind.name(),
+                        scope_str(channel_id)
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error inserting message template: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("Error setting the `{}` template", kind.name()))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "preview")]
+async fn message_template_preview(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Message to preview"] kind: TemplateKind,
+    #[description = "Preview the template used in this channel (defaults to this channel)"]
+    channel: Option<serenity::Channel>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let channel_id = channel.map_or(ctx.channel_id(), |channel| channel.id());
+    let template = resolve_template(&ctx.data().datastore, guild_id, Some(channel_id), kind).await;
+
+    // Preview with the channel's configured action, or a ban if it isn't a honeypot channel.
+    let response = match ctx
+        .data()
+        .datastore
+        .get_message_response_config(guild_id, channel_id)
+        .await
+    {
+        Ok(config) if config.response != MessageResponse::Nothing => config.response,
+        _ => MessageResponse::Ban,
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "`{}` template for <#{channel_id}>:\n{}",
+                kind.name(),
+                match kind {
+                    TemplateKind::Warning => render_warning(&template, channel_id, response),
+                    _ => render(&template, ctx.author().id, channel_id, response),
+                }
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "reset")]
+async fn message_template_reset(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Message to reset to the default"] kind: TemplateKind,
+    #[description = "Reset the template of one channel (defaults to the whole server)"]
+    channel: Option<serenity::Channel>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let channel_id = channel.map(|channel| channel.id());
+    let result = ctx
+        .data()
+        .datastore
+        .delete_message_template(guild_id, channel_id, kind)
+        .await;
+    match result {
+        Ok(_) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!(
+                        "Reset the `{}` template for {}",
+                        kind.name(),
+                        scope_str(channel_id)
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error deleting message template: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("Error resetting the `{}` template", kind.name()))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+fn scope_str(channel_id: Option<serenity::ChannelId>) -> String {
+    match channel_id {
+        Some(channel_id) => format!("channel <#{channel_id}>"),
+        None => "this server".to_string(),
+    }
+}
@@ -0,0 +1,277 @@
+use poise::{
+    Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use crate::{
+    action_log::post_in_logging_channel,
+    context_data,
+    datastore::{
+        errors,
+        models::{Exemption, Incident, IncidentFilter, MessageResponse},
+        prelude::*,
+    },
+};
+
+use super::release_quarantined_member;
+
+/// How many of the user's incidents are read at a time while looking for one to pardon.
+const INCIDENT_PAGE_SIZE: u32 = 25;
+
+#[poise::command(slash_command, default_member_permissions = "ADMINISTRATOR")]
+pub async fn pardon(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "User to reverse the latest honeypot action on"] user: serenity::User,
+    #[description = "Only warn the user if they trigger a honeypot again (default false)"]
+    allowlist: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let datastore = &ctx.data().datastore;
+    let latest_incident = latest_pardonable_incident(datastore.as_ref(), guild_id, user.id).await;
+    let incident = match latest_incident {
+        Ok(Some(incident)) => incident,
+        Ok(None) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!(
+                        "No honeypot actions to pardon found for <@{}>",
+                        user.id
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error listing incidents: {why:?}");
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("Error reading incidents for <@{}>", user.id))
+                    .ephemeral(true),
+            )
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let mut results = vec![];
+    let reversal = match incident.action {
+        MessageResponse::Ban | MessageResponse::SoftBan => guild_id
+            .unban(ctx, user.id)
+            .await
+            .map(|_| "Unbanned the user".to_string())
+            .map_err(|why| format!("Error unbanning the user: {why}")),
+        MessageResponse::Timeout => guild_id
+            .edit_member(
+                ctx,
+                user.id,
+                serenity::EditMember::new()
+                    .enable_communication()
+                    .audit_log_reason("pardoned after a honeypot false positive"),
+            )
+            .await
+            .map(|_| "Removed the user's timeout".to_string())
+            .map_err(|why| format!("Error removing the user's timeout: {why}")),
+        MessageResponse::Quarantine => {
+            release_quarantined_member(ctx, datastore, guild_id, user.id)
+                .await
+                .map(|restored_role_count| {
+                    format!("Restored {restored_role_count} role(s) taken by the quarantine")
+                })
+        }
+        MessageResponse::Kick => {
+            Ok("Kicks can't be reversed, the user is free to rejoin".to_string())
+        }
+        MessageResponse::Respond | MessageResponse::Nothing => {
+            unreachable!("only incidents with an action to reverse are pardoned")
+        }
+    };
+    results.push(reversal.unwrap_or_else(|why| {
+        event!(Level::WARN, "Error reversing honeypot action: {why}");
+        why
+    }));
+
+    match datastore
+        .mark_incident_false_positive(guild_id, incident.id)
+        .await
+    {
+        Ok(_) => results.push(format!(
+            "Marked incident #{} as a false positive",
+            incident.id
+        )),
+        Err(why) => {
+            event!(
+                Level::WARN,
+                "Error marking incident as a false positive: {why:?}"
+            );
+            results.push(format!(
+                "Error marking incident #{} as a false positive",
+                incident.id
+            ));
+        }
+    }
+
+    // The offense was recorded with the incident's timestamp if the guild had an escalation
+    // policy at the time.
+    match datastore
+        .delete_offense(guild_id, user.id, incident.created_at)
+        .await
+    {
+        Ok(_) => results.push("Removed the offense from the user's escalation count".to_string()),
+        Err(errors::Error::DatabaseEntryNotFound) => {}
+        Err(why) => {
+            event!(Level::WARN, "Error deleting offense: {why:?}");
+            results.push("Error removing the offense from the user's escalation count".to_string());
+        }
+    }
+
+    if allowlist.unwrap_or(false) {
+        match datastore
+            .insert_exemption(guild_id, Exemption::User(user.id))
+            .await
+        {
+            Ok(_) => results.push("Added the user to the allowlist".to_string()),
+            Err(why) => {
+                event!(Level::WARN, "Error adding user to allowlist: {why:?}");
+                results.push("Error adding the user to the allowlist".to_string());
+            }
+        }
+    }
+
+    let summary = format!(
+        "<@{}> pardoned <@{}> for incident #{} ({}):\n{}",
+        ctx.author().id,
+        user.id,
+        incident.id,
+        incident.action.past_tense(),
+        results
+            .iter()
+            .map(|result| format!("- {result}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    );
+    post_in_logging_channel(
+        ctx,
+        datastore,
+        guild_id,
+        serenity::CreateMessage::new().embed(
+            serenity::CreateEmbed::new()
+                .title("Pardon")
+                .description(&summary)
+                .colour(serenity::Colour::DARK_GREEN),
+        ),
+    )
+    .await;
+    ctx.send(
+        poise::CreateReply::default()
+            .content(summary)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Finds the user's newest incident whose action was actually taken on them and hasn't been
+/// pardoned yet. Dry runs, failed actions and responses that don't need reversing are skipped.
+async fn latest_pardonable_incident(
+    datastore: &impl DatastoreReader,
+    guild_id: serenity::GuildId,
+    user_id: serenity::UserId,
+) -> Result<Option<Incident>, errors::Error> {
+    let mut filter = IncidentFilter {
+        guild_id,
+        user_id: Some(user_id),
+        channel_id: None,
+        action: None,
+        include_dry_runs: false,
+        limit: INCIDENT_PAGE_SIZE,
+        offset: 0,
+    };
+    loop {
+        let incidents = datastore.list_incidents(&filter).await?;
+        let page_len = incidents.len() as u32;
+        let incident = incidents.into_iter().find(|incident| {
+            incident.error.is_none()
+                && !incident.false_positive
+                && !matches!(
+                    incident.action,
+                    MessageResponse::Respond | MessageResponse::Nothing
+                )
+        });
+        if incident.is_some() || page_len < filter.limit {
+            return Ok(incident);
+        }
+        filter.offset += filter.limit;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::datastore::{models::TriggerKind, test_utils::test_each_backend};
+
+    use super::*;
+
+    test_each_backend! {
+        async fn skips_incidents_that_cant_be_pardoned(db) {
+
+            // Use a random guild ID since incidents can't be deleted
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let user_id = serenity::UserId::new(87654321);
+            let result = latest_pardonable_incident(&db, guild_id, user_id).await;
+            assert_eq!(result, Ok(None));
+
+            let incident = |action, created_at| Incident {
+                id: 0,
+                guild_id,
+                channel_id: Some(serenity::ChannelId::new(1)),
+                user_id,
+                action,
+                trigger_kind: TriggerKind::Message,
+                content: "free nitro".to_string(),
+                created_at: serenity::Timestamp::from_unix_timestamp(created_at).unwrap(),
+                outcome: None,
+                error: None,
+                false_positive: false,
+                dry_run: false,
+            };
+            let ban_id = db
+                .insert_incident(&incident(MessageResponse::Ban, 1_000_000))
+                .await
+                .unwrap();
+            // Newer incidents that can't be pardoned, on more than one page:
+            for created_at in 0..INCIDENT_PAGE_SIZE as i64 {
+                db.insert_incident(&incident(MessageResponse::Respond, 2_000_000 + created_at))
+                    .await
+                    .unwrap();
+            }
+            let mut failed_timeout = incident(MessageResponse::Timeout, 3_000_000);
+            failed_timeout.error = Some("Missing Permissions".to_string());
+            db.insert_incident(&failed_timeout).await.unwrap();
+            let mut dry_run_kick = incident(MessageResponse::Kick, 4_000_000);
+            dry_run_kick.dry_run = true;
+            db.insert_incident(&dry_run_kick).await.unwrap();
+            let pardoned_id = db
+                .insert_incident(&incident(MessageResponse::Quarantine, 5_000_000))
+                .await
+                .unwrap();
+            db.mark_incident_false_positive(guild_id, pardoned_id)
+                .await
+                .unwrap();
+
+            let result = latest_pardonable_incident(&db, guild_id, user_id).await;
+            assert_eq!(result.map(|incident| incident.map(|incident| incident.id)), Ok(Some(ban_id)));
+
+            db.mark_incident_false_positive(guild_id, ban_id)
+                .await
+                .unwrap();
+            let result = latest_pardonable_incident(&db, guild_id, user_id).await;
+            assert_eq!(result, Ok(None));
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use poise::{
+    ChoiceParameter, Context,
+    serenity_prelude::{self as serenity, Error},
+};
+use tracing::{Level, event};
+
+use super::timeout_duration;
+use crate::{
+    content_rules::CompiledPattern,
+    context_data,
+    datastore::{
+        errors,
+        models::{ContentRule, ContentRuleKind, MessageResponse},
+        prelude::*,
+    },
+    utils::format_duration,
+};
+
+#[poise::command(
+    slash_command,
+    subcommands("rule_add", "rule_remove", "rule_list", "rule_test"),
+    subcommand_required,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn rule(_ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+#[poise::command(slash_command, rename = "add")]
+async fn rule_add(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "How the pattern is matched"] kind: ContentRuleKind,
+    #[description = "Regex, comma separated keywords or domains, or number of mentions"]
+    #[max_length = 1000]
+    pattern: Option<String>,
+    #[description = "Action for messages matching the rule (bans delete 7 days of messages)"]
+    response: MessageResponse,
+    #[description = "How long to time out posters for, in minutes (timeout only)"]
+    #[min = 1]
+    #[max = 40320] // Discord caps timeouts at 28 days
+    timeout_minutes: Option<u64>,
+    #[description = "Role to replace the poster's roles with (quarantine only)"]
+    quarantine_role: Option<serenity::Role>,
+    #[description = "Delete the matching message"] delete_message: Option<bool>,
+    #[description = "Audit log reason, supports the {user} and {channel} placeholders"]
+    #[max_length = 400]
+    reason: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let pattern = pattern.unwrap_or_default();
+    let error = if kind != ContentRuleKind::Invite && pattern.trim().is_empty() {
+        Some("A `pattern` is required for this kind of rule".to_string())
+    } else if response == MessageResponse::Quarantine && quarantine_role.is_none() {
+        Some("A `quarantine_role` is required for the `quarantine` action".to_string())
+    } else {
+        CompiledPattern::new(kind, &pattern).err()
+    };
+    if let Some(error) = error {
+        ctx.send(poise::CreateReply::default().content(error).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+    let mut content_rule = ContentRule {
+        id: 0,
+        guild_id,
+        kind,
+        pattern,
+        response,
+        timeout_duration: (response == MessageResponse::Timeout)
+            .then(|| timeout_duration(timeout_minutes)),
+        quarantine_role_id: quarantine_role
+            .filter(|_| response == MessageResponse::Quarantine)
+            .map(|role| role.id),
+        delete_message: delete_message.unwrap_or(false),
+        reason,
+    };
+    let content = match ctx
+        .data()
+        .datastore
+        .insert_content_rule(&content_rule)
+        .await
+    {
+        Ok(content_rule_id) => {
+            content_rule.id = content_rule_id;
+            format!("Added rule {}", describe_rule(&content_rule))
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error inserting content rule: {why:?}");
+            "Error adding the rule".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "remove")]
+async fn rule_remove(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "ID of the rule to remove, shown by `/rule list`"] id: i64,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx.data().datastore.delete_content_rule(guild_id, id).await {
+        Ok(_) => format!("Removed rule `#{id}`"),
+        Err(errors::Error::DatabaseEntryNotFound) => format!("There is no rule `#{id}`"),
+        Err(why) => {
+            event!(Level::WARN, "Error deleting content rule: {why:?}");
+            format!("Error removing rule `#{id}`")
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "list")]
+async fn rule_list(ctx: Context<'_, context_data::ContextData, Error>) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx.data().datastore.get_content_rules(guild_id).await {
+        Ok(content_rules) if content_rules.is_empty() => "No content rules are set".to_string(),
+        Ok(content_rules) => content_rules
+            .iter()
+            .map(|content_rule| format!("- {}", describe_rule(content_rule)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Err(why) => {
+            event!(Level::WARN, "Error reading content rules: {why:?}");
+            "Error reading the content rules".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "test")]
+async fn rule_test(
+    ctx: Context<'_, context_data::ContextData, Error>,
+    #[description = "Sample message to check against the rules, no action is taken"]
+    #[max_length = 2000]
+    text: String,
+) -> Result<(), Error> {
+    let guild_id = ctx
+        .guild_id()
+        .expect("the bot should only be run in a guild");
+    let content = match ctx.data().datastore.get_content_rules(guild_id).await {
+        Ok(content_rules) => {
+            let matching = ctx
+                .data()
+                .content_rule_matcher
+                .matching(&content_rules, &text)
+                .await;
+            match matching.first() {
+                None => "No rules match".to_string(),
+                Some(first) => {
+                    let mut lines = vec![format!(
+                        "Rule `#{}` would be applied. Matching rules:",
+                        first.id
+                    )];
+                    lines.extend(
+                        matching
+                            .iter()
+                            .map(|content_rule| format!("- {}", describe_rule(content_rule))),
+                    );
+                    lines.join("\n")
+                }
+            }
+        }
+        Err(why) => {
+            event!(Level::WARN, "Error reading content rules: {why:?}");
+            "Error reading the content rules".to_string()
+        }
+    };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(content)
+            .ephemeral(true)
+            .allowed_mentions(serenity::CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
+
+fn describe_rule(content_rule: &ContentRule) -> String {
+    let mut description = format!("`#{}` {}", content_rule.id, content_rule.kind.name());
+    if content_rule.kind != ContentRuleKind::Invite {
+        description += &format!(" `{}`", content_rule.pattern.replace('`', "'"));
+    }
+    description += &format!(": **{}**", content_rule.response.name());
+    if let Some(timeout_duration) = content_rule.timeout_duration {
+        description += &format!(" for {}", format_duration(timeout_duration));
+    }
+    if let Some(role_id) = content_rule.quarantine_role_id {
+        description += &format!(" using <@&{role_id}>");
+    }
+    if content_rule.delete_message {
+        description += ", deleting the message";
+    }
+    description
+}
@@ -0,0 +1,267 @@
+use std::sync::{Arc, LazyLock};
+
+use moka::future::Cache;
+use regex::{Regex, RegexBuilder};
+
+use crate::datastore::models::{ContentRule, ContentRuleKind};
+
+/// How many compiled patterns are kept around at once.
+const MAX_COMPILED_PATTERNS: u64 = 10_000;
+
+/// Keeps regexes written by admins from using too much memory.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+static INVITE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(?:discord(?:app)?\.com/invite|discord\.gg)/[\w-]+")
+        .expect("invite regex should be valid")
+});
+
+static MENTION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<@[!&]?\d+>|@everyone|@here").expect("mention regex should be valid")
+});
+
+/// A content rule's pattern, ready to be matched against messages.
+pub enum CompiledPattern {
+    Regex(Regex),
+    MassMention(usize),
+}
+
+impl CompiledPattern {
+    /// Compiles a rule's pattern, returning a description of the problem if it's invalid.
+    pub fn new(kind: ContentRuleKind, pattern: &str) -> Result<Self, String> {
+        let alternatives = || {
+            pattern
+                .split(',')
+                .map(str::trim)
+                .filter(|alternative| !alternative.is_empty())
+                .map(regex::escape)
+                .collect::<Vec<_>>()
+        };
+        let regex = match kind {
+            ContentRuleKind::Regex => pattern.to_string(),
+            ContentRuleKind::Keyword => {
+                let keywords = alternatives();
+                if keywords.is_empty() {
+                    return Err("Give at least one keyword".to_string());
+                }
+                format!(r"(?i)(?:^|\W)(?:{})(?:$|\W)", keywords.join("|"))
+            }
+            ContentRuleKind::Domain => {
+                let domains = alternatives();
+                if domains.is_empty() {
+                    return Err("Give at least one domain".to_string());
+                }
+                format!(
+                    r"(?i)https?://(?:[^/\s]*\.)?(?:{})(?:[:/?#\s>]|$)",
+                    domains.join("|")
+                )
+            }
+            ContentRuleKind::Invite => return Ok(CompiledPattern::Regex(INVITE.clone())),
+            ContentRuleKind::MassMention => {
+                return match pattern.trim().parse() {
+                    Ok(count) if count > 0 => Ok(CompiledPattern::MassMention(count)),
+                    _ => Err(format!("`{pattern}` is not a positive number of mentions")),
+                };
+            }
+        };
+        RegexBuilder::new(&regex)
+            .size_limit(REGEX_SIZE_LIMIT)
+            .build()
+            .map(CompiledPattern::Regex)
+            .map_err(|why| format!("Invalid pattern: {why}"))
+    }
+
+    pub fn is_match(&self, content: &str) -> bool {
+        match self {
+            CompiledPattern::Regex(regex) => regex.is_match(content),
+            CompiledPattern::MassMention(count) => MENTION.find_iter(content).count() >= *count,
+        }
+    }
+}
+
+/// Matches messages against content rules, caching compiled patterns by rule ID. Rules can't be
+/// edited and their IDs aren't reused, so cached patterns never go stale.
+pub struct ContentRuleMatcher {
+    compiled_patterns: Cache<i64, Option<Arc<CompiledPattern>>>,
+}
+
+impl Default for ContentRuleMatcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContentRuleMatcher {
+    pub fn new() -> Self {
+        Self {
+            compiled_patterns: Cache::new(MAX_COMPILED_PATTERNS),
+        }
+    }
+
+    /// Returns every rule matching the content, in the order they're given.
+    pub async fn matching<'a>(
+        &self,
+        content_rules: &'a [ContentRule],
+        content: &str,
+    ) -> Vec<&'a ContentRule> {
+        let mut matching = vec![];
+        for content_rule in content_rules {
+            if self.is_match(content_rule, content).await {
+                matching.push(content_rule);
+            }
+        }
+        matching
+    }
+
+    /// Returns the first rule matching the content.
+    pub async fn first_match<'a>(
+        &self,
+        content_rules: &'a [ContentRule],
+        content: &str,
+    ) -> Option<&'a ContentRule> {
+        for content_rule in content_rules {
+            if self.is_match(content_rule, content).await {
+                return Some(content_rule);
+            }
+        }
+        None
+    }
+
+    async fn is_match(&self, content_rule: &ContentRule, content: &str) -> bool {
+        let compiled_pattern = self
+            .compiled_patterns
+            .get_with(content_rule.id, async {
+                match CompiledPattern::new(content_rule.kind, &content_rule.pattern) {
+                    Ok(compiled_pattern) => Some(Arc::new(compiled_pattern)),
+                    // Patterns are checked by `/rule add`, so this only happens if the regex
+                    // crate gets stricter.
+                    Err(why) => {
+                        tracing::error!("Error compiling content rule #{}: {why}", content_rule.id);
+                        None
+                    }
+                }
+            })
+            .await;
+        compiled_pattern.is_some_and(|compiled_pattern| compiled_pattern.is_match(content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::{self as serenity};
+
+    use super::*;
+    use crate::datastore::models::MessageResponse;
+
+    fn is_match(kind: ContentRuleKind, pattern: &str, content: &str) -> bool {
+        CompiledPattern::new(kind, pattern)
+            .unwrap()
+            .is_match(content)
+    }
+
+    fn content_rule(id: i64, kind: ContentRuleKind, pattern: &str) -> ContentRule {
+        ContentRule {
+            id,
+            guild_id: serenity::GuildId::new(1),
+            kind,
+            pattern: pattern.to_string(),
+            response: MessageResponse::Ban,
+            timeout_duration: None,
+            quarantine_role_id: None,
+            delete_message: true,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn keyword() {
+        let kind = ContentRuleKind::Keyword;
+        assert!(is_match(kind, "nitro, steam gift", "Free NITRO here"));
+        assert!(is_match(
+            kind,
+            "nitro, steam gift",
+            "claim your steam gift!"
+        ));
+        // Keywords match whole words only:
+        assert!(!is_match(kind, "nitro", "nitroglycerin"));
+        // Keywords are matched literally:
+        assert!(!is_match(kind, "a.c", "abc"));
+        assert!(CompiledPattern::new(kind, " , ").is_err());
+    }
+
+    #[test]
+    fn domain() {
+        let kind = ContentRuleKind::Domain;
+        assert!(is_match(
+            kind,
+            "scam.example",
+            "go to https://scam.example/login"
+        ));
+        assert!(is_match(kind, "scam.example", "http://www.SCAM.example"));
+        assert!(is_match(
+            kind,
+            "scam.example",
+            "<https://scam.example:8080>"
+        ));
+        assert!(!is_match(kind, "scam.example", "https://scam.example.org"));
+        assert!(!is_match(kind, "scam.example", "https://notscam.example"));
+        assert!(!is_match(kind, "scam.example", "scam.example"));
+        assert!(CompiledPattern::new(kind, "").is_err());
+    }
+
+    #[test]
+    fn regex() {
+        let kind = ContentRuleKind::Regex;
+        assert!(is_match(kind, r"^\d{4}$", "1234"));
+        assert!(!is_match(kind, r"^\d{4}$", "12345"));
+        assert!(CompiledPattern::new(kind, "(").is_err());
+    }
+
+    #[test]
+    fn invite() {
+        let kind = ContentRuleKind::Invite;
+        assert!(is_match(kind, "", "join discord.gg/abc-123"));
+        assert!(is_match(kind, "", "https://discord.com/invite/abc"));
+        assert!(is_match(kind, "", "https://DiscordApp.com/invite/abc"));
+        assert!(!is_match(kind, "", "https://discord.com/channels/1/2"));
+    }
+
+    #[test]
+    fn mass_mention() {
+        let kind = ContentRuleKind::MassMention;
+        assert!(is_match(kind, "3", "<@1> <@!2> <@&3>"));
+        assert!(is_match(kind, " 2 ", "@everyone @here"));
+        assert!(!is_match(kind, "3", "<@1> <@2> <#3>"));
+        assert!(CompiledPattern::new(kind, "0").is_err());
+        assert!(CompiledPattern::new(kind, "many").is_err());
+    }
+
+    #[tokio::test]
+    async fn matcher() {
+        let matcher = ContentRuleMatcher::new();
+        let content_rules = vec![
+            content_rule(1, ContentRuleKind::Keyword, "nitro"),
+            // Invalid rules never match:
+            content_rule(2, ContentRuleKind::Regex, "("),
+            content_rule(3, ContentRuleKind::Invite, ""),
+            content_rule(4, ContentRuleKind::Keyword, "free"),
+        ];
+
+        let content = "free nitro at discord.gg/scam";
+        let matching: Vec<i64> = matcher
+            .matching(&content_rules, content)
+            .await
+            .iter()
+            .map(|content_rule| content_rule.id)
+            .collect();
+        assert_eq!(matching, vec![1, 3, 4]);
+        assert_eq!(
+            matcher
+                .first_match(&content_rules, content)
+                .await
+                .map(|content_rule| content_rule.id),
+            Some(1)
+        );
+        assert_eq!(matcher.first_match(&content_rules, "hello").await, None);
+    }
+}
@@ -1,13 +1,21 @@
 use std::sync::Arc;
 
-use crate::datastore::Datastore;
+use crate::{
+    burst_detector::BurstDetector, content_rules::ContentRuleMatcher, datastore::Datastore,
+};
 
 pub struct ContextData {
     pub datastore: Arc<Datastore>,
+    pub burst_detector: Arc<BurstDetector>,
+    pub content_rule_matcher: Arc<ContentRuleMatcher>,
 }
 
 impl ContextData {
     pub fn new(datastore: Arc<Datastore>) -> Self {
-        Self { datastore }
+        Self {
+            datastore,
+            burst_detector: Arc::new(BurstDetector::new()),
+            content_rule_matcher: Arc::new(ContentRuleMatcher::new()),
+        }
     }
 }
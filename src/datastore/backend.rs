@@ -0,0 +1,369 @@
+use poise::serenity_prelude::{self as serenity};
+
+use crate::{
+    datastore::{
+        database::{Database, DatabaseOptions},
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, EscalationPolicy, Exemption, GuildSettings,
+            Incident, IncidentFilter, IncidentStats, MessageResponseConfig, MessageTemplate,
+            QuarantinedMember, RoleResponseConfig, TemplateKind,
+        },
+        postgres::{PostgresDatabase, PostgresDatabaseOptions},
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config::GuildConfig,
+};
+
+/// The database the bot runs against, chosen by the scheme of its URL when starting.
+pub enum Backend {
+    Sqlite(Database),
+    Postgres(PostgresDatabase),
+}
+
+pub struct BackendOptions {
+    /// A `postgres://` or `postgresql://` URL, or the path of a sqlite file, optionally prefixed
+    /// with `sqlite://`
+    pub url: String,
+    /// Directory to read migrations from instead of the ones built into the binary
+    pub migrations_path: Option<String>,
+    /// Whether pending migrations are applied when connecting. Otherwise the database has to be
+    /// up to date already.
+    pub migrate: bool,
+}
+
+/// Calls the same method on whichever database the backend wraps.
+macro_rules! dispatch {
+    ($backend:expr, $method:ident($($arg:expr),*)) => {
+        match $backend {
+            Backend::Sqlite(database) => database.$method($($arg),*).await,
+            Backend::Postgres(database) => database.$method($($arg),*).await,
+        }
+    };
+}
+
+impl DatastoreReader for Backend {
+    async fn get_message_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<MessageResponseConfig, Error> {
+        dispatch!(self, get_message_response_config(guild_id, channel_id))
+    }
+
+    async fn get_logging_channel(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<serenity::ChannelId, Error> {
+        dispatch!(self, get_logging_channel(guild_id))
+    }
+
+    async fn get_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error> {
+        dispatch!(self, get_quarantined_member(guild_id, user_id))
+    }
+
+    async fn get_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<String, Error> {
+        dispatch!(self, get_message_template(guild_id, channel_id, kind))
+    }
+
+    async fn list_message_templates(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error> {
+        dispatch!(self, list_message_templates(guild_id))
+    }
+
+    async fn get_escalation_policy(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error> {
+        dispatch!(self, get_escalation_policy(guild_id))
+    }
+
+    async fn count_offenses(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        since: serenity::Timestamp,
+    ) -> Result<u32, Error> {
+        dispatch!(self, count_offenses(guild_id, user_id, since))
+    }
+
+    async fn list_incidents(&self, filter: &IncidentFilter) -> Result<Vec<Incident>, Error> {
+        dispatch!(self, list_incidents(filter))
+    }
+
+    async fn get_incident_stats(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error> {
+        dispatch!(self, get_incident_stats(guild_id))
+    }
+
+    async fn list_message_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error> {
+        dispatch!(self, list_message_response_configs(guild_id))
+    }
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error> {
+        dispatch!(self, get_exemptions(guild_id))
+    }
+
+    async fn get_guild_settings(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<GuildSettings, Error> {
+        dispatch!(self, get_guild_settings(guild_id))
+    }
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error> {
+        dispatch!(self, get_role_response_configs(guild_id))
+    }
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error> {
+        dispatch!(self, get_burst_detection_config(guild_id))
+    }
+
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error> {
+        dispatch!(self, get_content_rules(guild_id))
+    }
+}
+
+impl DatastoreWriter for Backend {
+    async fn insert_message_response_config(
+        &self,
+        message_response_config: &MessageResponseConfig,
+    ) -> Result<(), Error> {
+        dispatch!(
+            self,
+            insert_message_response_config(message_response_config)
+        )
+    }
+
+    async fn delete_message_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_message_response_config(guild_id, channel_id))
+    }
+
+    async fn insert_logging_channel(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_logging_channel(guild_id, channel_id))
+    }
+
+    async fn delete_logging_channel(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        dispatch!(self, delete_logging_channel(guild_id))
+    }
+
+    async fn insert_quarantined_member(
+        &self,
+        quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_quarantined_member(quarantined_member))
+    }
+
+    async fn delete_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_quarantined_member(guild_id, user_id))
+    }
+
+    async fn insert_message_template(
+        &self,
+        message_template: &MessageTemplate,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_message_template(message_template))
+    }
+
+    async fn delete_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_message_template(guild_id, channel_id, kind))
+    }
+
+    async fn insert_escalation_policy(
+        &self,
+        escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_escalation_policy(escalation_policy))
+    }
+
+    async fn delete_escalation_policy(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        dispatch!(self, delete_escalation_policy(guild_id))
+    }
+
+    async fn insert_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_offense(guild_id, user_id, created_at))
+    }
+
+    async fn delete_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_offense(guild_id, user_id, created_at))
+    }
+
+    async fn insert_incident(&self, incident: &Incident) -> Result<i64, Error> {
+        dispatch!(self, insert_incident(incident))
+    }
+
+    async fn mark_incident_false_positive(
+        &self,
+        guild_id: serenity::GuildId,
+        incident_id: i64,
+    ) -> Result<(), Error> {
+        dispatch!(self, mark_incident_false_positive(guild_id, incident_id))
+    }
+
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_exemption(guild_id, exemption))
+    }
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_exemption(guild_id, exemption))
+    }
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error> {
+        dispatch!(self, insert_guild_settings(guild_settings))
+    }
+
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_role_response_config(role_response_config))
+    }
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_role_response_config(guild_id, role_id))
+    }
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error> {
+        dispatch!(self, insert_burst_detection_config(burst_detection_config))
+    }
+
+    async fn delete_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_burst_detection_config(guild_id))
+    }
+
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error> {
+        dispatch!(self, insert_content_rule(content_rule))
+    }
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rule_id: i64,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_content_rule(guild_id, content_rule_id))
+    }
+
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        dispatch!(self, delete_guild_configs(guild_id))
+    }
+
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        guild_config: &GuildConfig,
+    ) -> Result<(), Error> {
+        dispatch!(self, replace_guild_configs(guild_id, guild_config))
+    }
+
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        dispatch!(self, delete_channel_configs(guild_id, channel_id))
+    }
+}
+
+impl Backend {
+    pub async fn new(options: &BackendOptions) -> Result<Self, Error> {
+        let url = options.url.as_str();
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let database = PostgresDatabase::new(&PostgresDatabaseOptions {
+                url: url.to_string(),
+                migrations_path: options.migrations_path.clone(),
+                migrate: options.migrate,
+            })
+            .await?;
+            return Ok(Backend::Postgres(database));
+        }
+        let filename = url.strip_prefix("sqlite://").unwrap_or(url);
+        if filename.contains("://") {
+            return Err(Error::DatabaseMigrationErr(format!(
+                "`{url}` isn't a sqlite file or a `postgres://` URL"
+            )));
+        }
+        let database = Database::new(&DatabaseOptions {
+            filename: filename.to_string(),
+            migrations_path: options.migrations_path.clone(),
+            migrate: options.migrate,
+        })
+        .await?;
+        Ok(Backend::Sqlite(database))
+    }
+
+    /// Returns the version and description of every migration applied to the database, oldest
+    /// first.
+    pub async fn list_applied_migrations(&self) -> Result<Vec<(i64, String)>, Error> {
+        dispatch!(self, list_applied_migrations())
+    }
+}
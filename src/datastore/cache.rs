@@ -1,31 +1,112 @@
+use std::time::Duration;
+
 use moka::future::Cache;
 use poise::serenity_prelude::{self as serenity};
 
-use crate::datastore::{
-    errors::Error,
-    models::{MessageResponse, MessageResponseConfig},
-    traits::{DatastoreReader, DatastoreWriter},
+use crate::{
+    datastore::{
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, EscalationPolicy, Exemption, GuildSettings,
+            Incident, IncidentFilter, IncidentStats, MessageResponseConfig, MessageTemplate,
+            QuarantinedMember, RoleResponseConfig, TemplateKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config::GuildConfig,
 };
 
 pub struct DatabaseCache {
-    subscribed_channel_responses: Cache<(serenity::GuildId, serenity::ChannelId), MessageResponse>,
+    subscribed_channel_responses:
+        Cache<(serenity::GuildId, serenity::ChannelId), MessageResponseConfig>,
     logging_channels: Cache<serenity::GuildId, serenity::ChannelId>,
+    exemptions: Cache<serenity::GuildId, Vec<Exemption>>,
+    guild_settings: Cache<serenity::GuildId, GuildSettings>,
+    role_responses: Cache<serenity::GuildId, Vec<RoleResponseConfig>>,
+    /// `None` for guilds that don't use burst detection, since it's checked for every message
+    burst_detection: Cache<serenity::GuildId, Option<BurstDetectionConfig>>,
+    content_rules: Cache<serenity::GuildId, Vec<ContentRule>>,
 }
 
 impl DatabaseCache {
     pub fn new(options: &CacheOptions) -> Self {
+        let time_to_live = options.time_to_live;
         Self {
-            subscribed_channel_responses: Cache::new(
+            subscribed_channel_responses: build_cache(
                 options.subscribed_channel_responses_max_capacity,
+                time_to_live,
             ),
-            logging_channels: Cache::new(options.logging_channels_max_capacity),
+            logging_channels: build_cache(options.logging_channels_max_capacity, time_to_live),
+            exemptions: build_cache(options.exemptions_max_capacity, time_to_live),
+            guild_settings: build_cache(options.guild_settings_max_capacity, time_to_live),
+            role_responses: build_cache(options.role_responses_max_capacity, time_to_live),
+            burst_detection: build_cache(options.burst_detection_max_capacity, time_to_live),
+            content_rules: build_cache(options.content_rules_max_capacity, time_to_live),
         }
     }
+
+    /// Caches the full list of a guild's exemptions. Exemptions can't be inserted into the cache
+    /// one at a time since the cache wouldn't know whether it has the rest of the guild's list.
+    pub async fn insert_exemptions(&self, guild_id: serenity::GuildId, exemptions: Vec<Exemption>) {
+        self.exemptions.insert(guild_id, exemptions).await;
+    }
+
+    /// Caches the full list of a guild's honeypot roles, for the same reason as
+    /// `insert_exemptions`.
+    pub async fn insert_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        role_response_configs: Vec<RoleResponseConfig>,
+    ) {
+        self.role_responses
+            .insert(guild_id, role_response_configs)
+            .await;
+    }
+
+    pub async fn insert_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rules: Vec<ContentRule>,
+    ) {
+        self.content_rules.insert(guild_id, content_rules).await;
+    }
+
+    /// Caches a guild's burst detection config, or that it doesn't have one.
+    pub async fn cache_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+        burst_detection_config: Option<BurstDetectionConfig>,
+    ) {
+        self.burst_detection
+            .insert(guild_id, burst_detection_config)
+            .await;
+    }
 }
 
+#[derive(Clone, Copy)]
 pub struct CacheOptions {
     pub subscribed_channel_responses_max_capacity: u64,
     pub logging_channels_max_capacity: u64,
+    pub exemptions_max_capacity: u64,
+    pub guild_settings_max_capacity: u64,
+    pub role_responses_max_capacity: u64,
+    pub burst_detection_max_capacity: u64,
+    pub content_rules_max_capacity: u64,
+    /// How long entries are kept before being read from the database again, or `None` to keep
+    /// them until they're changed through this cache.
+    pub time_to_live: Option<Duration>,
+}
+
+fn build_cache<K, V>(max_capacity: u64, time_to_live: Option<Duration>) -> Cache<K, V>
+where
+    K: std::hash::Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    let builder = Cache::builder().max_capacity(max_capacity);
+    match time_to_live {
+        Some(time_to_live) => builder.time_to_live(time_to_live).build(),
+        None => builder.build(),
+    }
 }
 
 impl Default for DatabaseCache {
@@ -39,34 +120,29 @@ impl Default for CacheOptions {
         Self {
             subscribed_channel_responses_max_capacity: 10_000,
             logging_channels_max_capacity: 10_000,
+            exemptions_max_capacity: 10_000,
+            guild_settings_max_capacity: 10_000,
+            role_responses_max_capacity: 10_000,
+            burst_detection_max_capacity: 10_000,
+            content_rules_max_capacity: 10_000,
+            time_to_live: None,
         }
     }
 }
 
 impl DatastoreReader for DatabaseCache {
-    async fn get_message_response(
+    async fn get_message_response_config(
         &self,
         guild_id: serenity::GuildId,
         channel_id: serenity::ChannelId,
-    ) -> Result<MessageResponse, Error> {
+    ) -> Result<MessageResponseConfig, Error> {
         match self
             .subscribed_channel_responses
             .get(&(guild_id, channel_id))
             .await
         {
-            Some(response) => Ok(response),
-            None => {
-                // Insert dummy message response config into the cache to avoid querying the
-                // database for all messages in channels that the bot isn't configured to listen to.
-                let _ = self
-                    .insert_message_response_config(&MessageResponseConfig {
-                        guild_id,
-                        channel_id,
-                        response: MessageResponse::Nothing,
-                    })
-                    .await;
-                Ok(MessageResponse::Nothing)
-            }
+            Some(config) => Ok(config),
+            None => Err(Error::CacheEntryNotFound),
         }
     }
 
@@ -79,6 +155,117 @@ impl DatastoreReader for DatabaseCache {
             None => Err(Error::CacheEntryNotFound),
         }
     }
+
+    // Quarantined members are only read when a member is released, so they aren't cached.
+    async fn get_quarantined_member(
+        &self,
+        _guild_id: serenity::GuildId,
+        _user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    // Templates are only read when the bot takes action, so they aren't cached either.
+    async fn get_message_template(
+        &self,
+        _guild_id: serenity::GuildId,
+        _channel_id: Option<serenity::ChannelId>,
+        _kind: TemplateKind,
+    ) -> Result<String, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    async fn list_message_templates(
+        &self,
+        _guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    // Escalation policies and offenses are only read when a honeypot is triggered.
+    async fn get_escalation_policy(
+        &self,
+        _guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    async fn count_offenses(
+        &self,
+        _guild_id: serenity::GuildId,
+        _user_id: serenity::UserId,
+        _since: serenity::Timestamp,
+    ) -> Result<u32, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    // Incidents are never cached.
+    async fn list_incidents(&self, _filter: &IncidentFilter) -> Result<Vec<Incident>, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    async fn get_incident_stats(
+        &self,
+        _guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    // Only single honeypot channels are cached, since they're looked up for every message.
+    async fn list_message_response_configs(
+        &self,
+        _guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error> {
+        match self.exemptions.get(&guild_id).await {
+            Some(exemptions) => Ok(exemptions),
+            None => Err(Error::CacheEntryNotFound),
+        }
+    }
+
+    async fn get_guild_settings(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<GuildSettings, Error> {
+        match self.guild_settings.get(&guild_id).await {
+            Some(guild_settings) => Ok(guild_settings),
+            None => Err(Error::CacheEntryNotFound),
+        }
+    }
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error> {
+        match self.role_responses.get(&guild_id).await {
+            Some(role_response_configs) => Ok(role_response_configs),
+            None => Err(Error::CacheEntryNotFound),
+        }
+    }
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error> {
+        match self.burst_detection.get(&guild_id).await {
+            Some(Some(burst_detection_config)) => Ok(burst_detection_config),
+            Some(None) => Err(Error::DatabaseEntryNotFound),
+            None => Err(Error::CacheEntryNotFound),
+        }
+    }
+
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error> {
+        match self.content_rules.get(&guild_id).await {
+            Some(content_rules) => Ok(content_rules),
+            None => Err(Error::CacheEntryNotFound),
+        }
+    }
 }
 
 impl DatastoreWriter for DatabaseCache {
@@ -92,7 +279,7 @@ impl DatastoreWriter for DatabaseCache {
                     message_response_config.guild_id,
                     message_response_config.channel_id,
                 ),
-                message_response_config.response,
+                message_response_config.clone(),
             )
             .await;
         Ok(())
@@ -122,4 +309,200 @@ impl DatastoreWriter for DatabaseCache {
         self.logging_channels.remove(&guild_id).await;
         Ok(())
     }
+
+    async fn insert_quarantined_member(
+        &self,
+        _quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn delete_quarantined_member(
+        &self,
+        _guild_id: serenity::GuildId,
+        _user_id: serenity::UserId,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn insert_message_template(
+        &self,
+        _message_template: &MessageTemplate,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn delete_message_template(
+        &self,
+        _guild_id: serenity::GuildId,
+        _channel_id: Option<serenity::ChannelId>,
+        _kind: TemplateKind,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn insert_escalation_policy(
+        &self,
+        _escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn delete_escalation_policy(&self, _guild_id: serenity::GuildId) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn insert_offense(
+        &self,
+        _guild_id: serenity::GuildId,
+        _user_id: serenity::UserId,
+        _created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn delete_offense(
+        &self,
+        _guild_id: serenity::GuildId,
+        _user_id: serenity::UserId,
+        _created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn insert_incident(&self, _incident: &Incident) -> Result<i64, Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    async fn mark_incident_false_positive(
+        &self,
+        _guild_id: serenity::GuildId,
+        _incident_id: i64,
+    ) -> Result<(), Error> {
+        Err(Error::CacheEntryNotFound)
+    }
+
+    // The guild's exemptions are read from the database again after they change.
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        _exemption: Exemption,
+    ) -> Result<(), Error> {
+        self.exemptions.invalidate(&guild_id).await;
+        Ok(())
+    }
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        _exemption: Exemption,
+    ) -> Result<(), Error> {
+        self.exemptions.invalidate(&guild_id).await;
+        Ok(())
+    }
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error> {
+        self.guild_settings
+            .insert(guild_settings.guild_id, guild_settings.clone())
+            .await;
+        Ok(())
+    }
+
+    // The guild's honeypot roles are read from the database again after they change.
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error> {
+        self.role_responses
+            .invalidate(&role_response_config.guild_id)
+            .await;
+        Ok(())
+    }
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        _role_id: serenity::RoleId,
+    ) -> Result<(), Error> {
+        self.role_responses.invalidate(&guild_id).await;
+        Ok(())
+    }
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error> {
+        self.burst_detection
+            .insert(
+                burst_detection_config.guild_id,
+                Some(burst_detection_config.clone()),
+            )
+            .await;
+        Ok(())
+    }
+
+    async fn delete_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<(), Error> {
+        self.burst_detection.insert(guild_id, None).await;
+        Ok(())
+    }
+
+    // The guild's content rules are read from the database again after they change.
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error> {
+        self.content_rules.invalidate(&content_rule.guild_id).await;
+        Ok(content_rule.id)
+    }
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        _content_rule_id: i64,
+    ) -> Result<(), Error> {
+        self.content_rules.invalidate(&guild_id).await;
+        Ok(())
+    }
+
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let channel_keys: Vec<_> = self
+            .subscribed_channel_responses
+            .iter()
+            .filter(|(key, _)| key.0 == guild_id)
+            .map(|(key, _)| *key)
+            .collect();
+        for key in channel_keys {
+            self.subscribed_channel_responses.invalidate(&key).await;
+        }
+        self.logging_channels.invalidate(&guild_id).await;
+        self.exemptions.invalidate(&guild_id).await;
+        self.guild_settings.invalidate(&guild_id).await;
+        self.role_responses.invalidate(&guild_id).await;
+        self.burst_detection.invalidate(&guild_id).await;
+        self.content_rules.invalidate(&guild_id).await;
+        Ok(())
+    }
+
+    // The guild's configs are read from the database again after they're replaced.
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        _guild_config: &GuildConfig,
+    ) -> Result<(), Error> {
+        self.delete_guild_configs(guild_id).await
+    }
+
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        self.subscribed_channel_responses
+            .invalidate(&(guild_id, channel_id))
+            .await;
+        if self.logging_channels.get(&guild_id).await == Some(channel_id) {
+            self.logging_channels.invalidate(&guild_id).await;
+        }
+        Ok(())
+    }
 }
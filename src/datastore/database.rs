@@ -1,40 +1,63 @@
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
 use poise::serenity_prelude::{self as serenity};
-use sqlx::{Sqlite, migrate::Migrator, sqlite::SqliteConnectOptions};
+use sqlx::{
+    Row, Sqlite, SqliteConnection,
+    migrate::Migrator,
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqliteRow},
+};
 
-use crate::datastore::{
-    errors::Error,
-    models::{MessageResponse, MessageResponseConfig},
-    traits::{DatastoreReader, DatastoreWriter},
+use crate::{
+    datastore::{
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, ContentRuleKind, EscalationPolicy, Exemption,
+            ExemptionMode, GuildSettings, Incident, IncidentFilter, IncidentStats, MessageResponse,
+            MessageResponseConfig, MessageTemplate, QuarantinedMember, RoleResponseConfig,
+            TemplateKind, TriggerKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config::GuildConfig,
 };
 
+/// Stores everything in a sqlite file.
 pub struct Database {
     pool: sqlx::Pool<Sqlite>,
 }
 
+/// Migrations built into the binary, used unless `DatabaseOptions::migrations_path` is set.
+static EMBEDDED_MIGRATOR: Migrator = sqlx::migrate!("migrations/sqlite");
+
 pub struct DatabaseOptions {
     pub filename: String,
-    pub migrations_path: String,
+    /// Directory to read migrations from instead of the ones built into the binary
+    pub migrations_path: Option<String>,
+    /// Whether pending migrations are applied when connecting. Otherwise the database has to be
+    /// up to date already.
+    pub migrate: bool,
 }
 
 impl DatastoreReader for Database {
-    async fn get_message_response(
+    async fn get_message_response_config(
         &self,
         guild_id: serenity::GuildId,
         channel_id: serenity::ChannelId,
-    ) -> Result<MessageResponse, Error> {
-        let response: Result<i64, sqlx::Error> = sqlx::query_scalar(
-            "SELECT response FROM message_responses WHERE guild_id = ? AND channel_id = ?",
-        )
+    ) -> Result<MessageResponseConfig, Error> {
+        let row: Result<SqliteRow, sqlx::Error> = sqlx::query(concat!(
+            "SELECT guild_id, channel_id, response, timeout_duration, quarantine_role_id, ",
+            "delete_message, purge_window, delete_message_days, reason, new_account_age, ",
+            "new_account_response, established_member_age, established_member_response, ",
+            "trigger_kinds, dry_run FROM message_responses WHERE guild_id = ? AND channel_id = ?"
+        ))
         .bind(guild_id.get() as i64)
         .bind(channel_id.get() as i64)
         .fetch_one(&self.pool)
         .await;
-        match response {
+        match row {
             Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
             Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
-            Ok(response) => Ok(MessageResponse::from(response)),
+            Ok(row) => Ok(message_response_config_from_row(&row)),
         }
     }
 
@@ -53,6 +76,294 @@ impl DatastoreReader for Database {
             Ok(response) => Ok(serenity::ChannelId::new(response as u64)),
         }
     }
+
+    async fn get_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error> {
+        let row: Result<(i64, String), sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT quarantine_role_id, removed_role_ids FROM quarantined_members ",
+            "WHERE guild_id = ? AND user_id = ?"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok((quarantine_role_id, removed_role_ids)) => Ok(QuarantinedMember {
+                guild_id,
+                user_id,
+                quarantine_role_id: serenity::RoleId::new(quarantine_role_id as u64),
+                removed_role_ids: removed_role_ids
+                    .split(',')
+                    .filter_map(|role_id| role_id.parse().ok())
+                    .map(serenity::RoleId::new)
+                    .collect(),
+            }),
+        }
+    }
+
+    async fn get_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<String, Error> {
+        let template: Result<String, sqlx::Error> = sqlx::query_scalar(concat!(
+            "SELECT template FROM message_templates ",
+            "WHERE guild_id = ? AND channel_id = ? AND kind = ?"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(optional_channel_id(channel_id))
+        .bind(kind as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match template {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(template) => Ok(template),
+        }
+    }
+
+    async fn list_message_templates(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error> {
+        let rows: Result<Vec<(i64, i64, String)>, sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT channel_id, kind, template FROM message_templates ",
+            "WHERE guild_id = ? ORDER BY channel_id, kind"
+        ))
+        .bind(guild_id.get() as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|(channel_id, kind, template)| MessageTemplate {
+                    guild_id,
+                    channel_id: (channel_id != 0)
+                        .then(|| serenity::ChannelId::new(channel_id as u64)),
+                    kind: TemplateKind::from(kind),
+                    template,
+                })
+                .collect()),
+        }
+    }
+
+    async fn get_escalation_policy(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error> {
+        let row: Result<(String, i64), sqlx::Error> = sqlx::query_as(
+            "SELECT ladder, decay_window FROM escalation_policies WHERE guild_id = ?",
+        )
+        .bind(guild_id.get() as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok((ladder, decay_window)) => Ok(EscalationPolicy {
+                guild_id,
+                ladder: ladder
+                    .split(',')
+                    .filter_map(|response| response.parse::<i64>().ok())
+                    .map(MessageResponse::from)
+                    .collect(),
+                decay_window: Duration::from_secs(decay_window as u64),
+            }),
+        }
+    }
+
+    async fn count_offenses(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        since: serenity::Timestamp,
+    ) -> Result<u32, Error> {
+        let count: Result<i64, sqlx::Error> = sqlx::query_scalar(concat!(
+            "SELECT COUNT(*) FROM offenses ",
+            "WHERE guild_id = ? AND user_id = ? AND created_at >= ?"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(since.unix_timestamp())
+        .fetch_one(&self.pool)
+        .await;
+        match count {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(count) => Ok(count as u32),
+        }
+    }
+
+    async fn list_incidents(&self, filter: &IncidentFilter) -> Result<Vec<Incident>, Error> {
+        let rows: Result<Vec<SqliteRow>, sqlx::Error> = sqlx::query(concat!(
+            "SELECT * FROM incidents WHERE guild_id = $1 ",
+            "AND ($2 IS NULL OR user_id = $2) ",
+            "AND ($3 IS NULL OR channel_id = $3) ",
+            "AND ($4 IS NULL OR action = $4) ",
+            "AND ($5 OR dry_run = 0) ",
+            "ORDER BY created_at DESC, id DESC LIMIT $6 OFFSET $7"
+        ))
+        .bind(filter.guild_id.get() as i64)
+        .bind(filter.user_id.map(|user_id| user_id.get() as i64))
+        .bind(filter.channel_id.map(|channel_id| channel_id.get() as i64))
+        .bind(filter.action.map(|action| action as i64))
+        .bind(filter.include_dry_runs)
+        .bind(filter.limit as i64)
+        .bind(filter.offset as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(incident_from_row).collect()),
+        }
+    }
+
+    async fn get_incident_stats(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error> {
+        let rows: Result<Vec<(i64, i64, i64)>, sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT channel_id, COUNT(*), MAX(created_at) FROM incidents ",
+            "WHERE guild_id = ? GROUP BY channel_id ORDER BY channel_id"
+        ))
+        .bind(guild_id.get() as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|(channel_id, count, last_created_at)| IncidentStats {
+                    channel_id: match channel_id {
+                        0 => None,
+                        channel_id => Some(serenity::ChannelId::new(channel_id as u64)),
+                    },
+                    count: count as u32,
+                    last_created_at: serenity::Timestamp::from_unix_timestamp(last_created_at)
+                        .unwrap_or_default(),
+                })
+                .collect()),
+        }
+    }
+
+    async fn list_message_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error> {
+        let rows: Result<Vec<SqliteRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM message_responses WHERE guild_id = ? ORDER BY channel_id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(message_response_config_from_row).collect()),
+        }
+    }
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error> {
+        let rows: Result<Vec<(i64, i64)>, sqlx::Error> =
+            sqlx::query_as("SELECT kind, target_id FROM exemptions WHERE guild_id = ?")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .filter_map(|(kind, target_id)| match kind {
+                    EXEMPT_USER => Some(Exemption::User(serenity::UserId::new(target_id as u64))),
+                    EXEMPT_ROLE => Some(Exemption::Role(serenity::RoleId::new(target_id as u64))),
+                    _ => None,
+                })
+                .collect()),
+        }
+    }
+
+    async fn get_guild_settings(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<GuildSettings, Error> {
+        let row: Result<SqliteRow, sqlx::Error> =
+            sqlx::query("SELECT * FROM guild_settings WHERE guild_id = ?")
+                .bind(guild_id.get() as i64)
+                .fetch_one(&self.pool)
+                .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(row) => Ok(GuildSettings {
+                guild_id,
+                exemption_mode: ExemptionMode::from(row.get::<i64, _>("exemption_mode")),
+                exempt_moderators: row.get("exempt_moderators"),
+                dry_run: row.get("dry_run"),
+            }),
+        }
+    }
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error> {
+        let rows: Result<Vec<SqliteRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM role_responses WHERE guild_id = ? ORDER BY role_id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(role_response_config_from_row).collect()),
+        }
+    }
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error> {
+        let row: Result<SqliteRow, sqlx::Error> =
+            sqlx::query("SELECT * FROM burst_detection WHERE guild_id = ?")
+                .bind(guild_id.get() as i64)
+                .fetch_one(&self.pool)
+                .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(row) => Ok(BurstDetectionConfig {
+                guild_id,
+                channel_count: row.get::<i64, _>("channel_count") as u32,
+                window: Duration::from_secs(row.get::<i64, _>("window") as u64),
+                response: MessageResponse::from(row.get::<i64, _>("response")),
+                timeout_duration: row
+                    .get::<Option<i64>, _>("timeout_duration")
+                    .map(|secs| Duration::from_secs(secs as u64)),
+                quarantine_role_id: row
+                    .get::<Option<i64>, _>("quarantine_role_id")
+                    .map(|role_id| serenity::RoleId::new(role_id as u64)),
+                delete_messages: row.get("delete_messages"),
+            }),
+        }
+    }
+
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error> {
+        let rows: Result<Vec<SqliteRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM content_rules WHERE guild_id = ? ORDER BY id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(content_rule_from_row).collect()),
+        }
+    }
 }
 
 impl DatastoreWriter for Database {
@@ -74,16 +385,9 @@ impl DatastoreWriter for Database {
         &self,
         message_response_config: &MessageResponseConfig,
     ) -> Result<(), Error> {
-        // Try inserting into the db
-        let result = sqlx::query(concat!(
-            "INSERT INTO message_responses (guild_id, channel_id, response) VALUES ($1, $2, $3) ",
-            "ON CONFLICT(guild_id, channel_id) DO UPDATE SET response = $3"
-        ))
-        .bind(message_response_config.guild_id.get() as i64)
-        .bind(message_response_config.channel_id.get() as i64)
-        .bind(message_response_config.response as i64)
-        .execute(&self.pool)
-        .await;
+        let result = insert_message_response_config_query(message_response_config)
+            .execute(&self.pool)
+            .await;
         match result {
             Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
             Ok(_) => Ok(()),
@@ -103,12 +407,35 @@ impl DatastoreWriter for Database {
         guild_id: serenity::GuildId,
         channel_id: serenity::ChannelId,
     ) -> Result<(), Error> {
+        let result = insert_logging_channel_query(guild_id, channel_id)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_quarantined_member(
+        &self,
+        quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error> {
+        let removed_role_ids = quarantined_member
+            .removed_role_ids
+            .iter()
+            .map(|role_id| role_id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
         let result = sqlx::query(concat!(
-            "INSERT INTO logging_channels (guild_id, channel_id) VALUES ($1, $2) ",
-            "ON CONFLICT(guild_id) DO UPDATE SET channel_id = $2"
+            "INSERT INTO quarantined_members ",
+            "(guild_id, user_id, quarantine_role_id, removed_role_ids) VALUES ($1, $2, $3, $4) ",
+            "ON CONFLICT(guild_id, user_id) DO UPDATE SET ",
+            "quarantine_role_id = $3, removed_role_ids = $4"
         ))
-        .bind(guild_id.get() as i64)
-        .bind(channel_id.get() as i64)
+        .bind(quarantined_member.guild_id.get() as i64)
+        .bind(quarantined_member.user_id.get() as i64)
+        .bind(quarantined_member.quarantine_role_id.get() as i64)
+        .bind(removed_role_ids)
         .execute(&self.pool)
         .await;
         match result {
@@ -116,116 +443,1581 @@ impl DatastoreWriter for Database {
             Ok(_) => Ok(()),
         }
     }
-}
 
-impl Database {
-    pub async fn new(options: &DatabaseOptions) -> Self {
-        let db = Self {
-            pool: sqlx::Pool::connect_with(
-                SqliteConnectOptions::new()
-                    .filename(&options.filename)
-                    .create_if_missing(true),
-            )
-            .await
-            .unwrap(),
-        };
-        Migrator::new(Path::new(&options.migrations_path))
-            .await
-            .unwrap()
-            .run(&db.pool)
-            .await
-            .unwrap();
-        db
+    async fn delete_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("DELETE FROM quarantined_members WHERE guild_id = ? AND user_id = ?")
+                .bind(guild_id.get() as i64)
+                .bind(user_id.get() as i64)
+                .execute(&self.pool)
+                .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use serial_test::serial;
-
-    use crate::datastore::test_utils::get_test_db;
-
-    use super::*;
-
-    #[tokio::test]
-    #[serial]
-    async fn create_read_and_delete_message_response_config() {
-        let db = get_test_db().await;
-
-        let guild_id = 12345678;
-        let channel_id = 87654321;
-
-        // Create the message response configuration in db
-        let mut message_response = MessageResponseConfig {
-            guild_id: serenity::GuildId::from(guild_id),
-            channel_id: serenity::ChannelId::from(channel_id),
-            response: MessageResponse::Ban,
-        };
-        let result = db.insert_message_response_config(&message_response).await;
-        assert_eq!(result, Ok(()));
 
-        // Read the message response for guild and channel id
-        let result = db
-            .get_message_response(message_response.guild_id, message_response.channel_id)
+    async fn insert_message_template(
+        &self,
+        message_template: &MessageTemplate,
+    ) -> Result<(), Error> {
+        let result = insert_message_template_query(message_template)
+            .execute(&self.pool)
             .await;
-        assert_eq!(result, Ok(message_response.response));
-
-        // Update the message response for guild and channel id
-        message_response.response = MessageResponse::Kick;
-        let result = db.insert_message_response_config(&message_response).await;
-        assert_eq!(result, Ok(()));
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
 
-        // Read the updated message response for guild and channel id
-        let result = db
-            .get_message_response(message_response.guild_id, message_response.channel_id)
-            .await;
-        assert_eq!(result, Ok(message_response.response));
+    async fn delete_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<(), Error> {
+        let result = sqlx::query(
+            "DELETE FROM message_templates WHERE guild_id = ? AND channel_id = ? AND kind = ?",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(optional_channel_id(channel_id))
+        .bind(kind as i64)
+        .execute(&self.pool)
+        .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
 
-        // Delete the message response config
-        let result = db
-            .delete_message_response_config(message_response.guild_id, message_response.channel_id)
+    async fn insert_escalation_policy(
+        &self,
+        escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error> {
+        let result = insert_escalation_policy_query(escalation_policy)
+            .execute(&self.pool)
             .await;
-        assert_eq!(result, Ok(()));
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
 
-        // Message response config should be deleted
-        let result = db
-            .get_message_response(message_response.guild_id, message_response.channel_id)
+    async fn delete_escalation_policy(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM escalation_policies WHERE guild_id = ?")
+            .bind(guild_id.get() as i64)
+            .execute(&self.pool)
             .await;
-        assert_eq!(result, Err(Error::DatabaseEntryNotFound));
-
-        // Clean up rows:
-        db.delete_message_response_config(
-            serenity::GuildId::from(guild_id),
-            serenity::ChannelId::from(channel_id),
-        )
-        .await
-        .unwrap();
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
     }
 
-    #[tokio::test]
-    #[serial]
-    async fn create_read_and_delete_logging_channel() {
-        let db = get_test_db().await;
-
-        let guild_id = serenity::GuildId::new(12345678);
-        let channel_id = serenity::ChannelId::new(87654321);
-        let result = db.insert_logging_channel(guild_id, channel_id).await;
-        assert_eq!(result, Ok(()));
+    async fn insert_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("INSERT INTO offenses (guild_id, user_id, created_at) VALUES ($1, $2, $3)")
+                .bind(guild_id.get() as i64)
+                .bind(user_id.get() as i64)
+                .bind(created_at.unix_timestamp())
+                .execute(&self.pool)
+                .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
 
-        let result = db.get_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(channel_id));
+    async fn delete_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        // Offenses have no ID, and the same user can offend twice in the same second.
+        let result = sqlx::query(concat!(
+            "DELETE FROM offenses WHERE rowid IN ",
+            "(SELECT rowid FROM offenses WHERE guild_id = ? AND user_id = ? AND created_at = ? LIMIT 1)"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(created_at.unix_timestamp())
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
 
-        let channel_id = serenity::ChannelId::new(01234567);
-        let result = db.insert_logging_channel(guild_id, channel_id).await;
-        assert_eq!(result, Ok(()));
+    async fn insert_incident(&self, incident: &Incident) -> Result<i64, Error> {
+        let result = sqlx::query(concat!(
+            "INSERT INTO incidents ",
+            "(guild_id, channel_id, user_id, action, content, created_at, outcome, error, ",
+            "false_positive, trigger_kind, dry_run) ",
+            "VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)"
+        ))
+        .bind(incident.guild_id.get() as i64)
+        .bind(optional_channel_id(incident.channel_id))
+        .bind(incident.user_id.get() as i64)
+        .bind(incident.action as i64)
+        .bind(&incident.content)
+        .bind(incident.created_at.unix_timestamp())
+        .bind(&incident.outcome)
+        .bind(&incident.error)
+        .bind(incident.false_positive)
+        .bind(incident.trigger_kind as i64)
+        .bind(incident.dry_run)
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) => Ok(result.last_insert_rowid()),
+        }
+    }
 
-        let result = db.get_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(channel_id));
+    async fn mark_incident_false_positive(
+        &self,
+        guild_id: serenity::GuildId,
+        incident_id: i64,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("UPDATE incidents SET false_positive = 1 WHERE guild_id = ? AND id = ?")
+                .bind(guild_id.get() as i64)
+                .bind(incident_id)
+                .execute(&self.pool)
+                .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
 
-        let result = db.delete_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(()));
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        let result = insert_exemption_query(guild_id, exemption)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        let (kind, target_id) = exemption_columns(exemption);
+        let result =
+            sqlx::query("DELETE FROM exemptions WHERE guild_id = ? AND kind = ? AND target_id = ?")
+                .bind(guild_id.get() as i64)
+                .bind(kind)
+                .bind(target_id)
+                .execute(&self.pool)
+                .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error> {
+        let result = insert_guild_settings_query(guild_settings)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error> {
+        let result = insert_role_response_config_query(role_response_config)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM role_responses WHERE guild_id = ? AND role_id = ?")
+            .bind(guild_id.get() as i64)
+            .bind(role_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error> {
+        let result = insert_burst_detection_config_query(burst_detection_config)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM burst_detection WHERE guild_id = ?")
+            .bind(guild_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error> {
+        let result = insert_content_rule_query(content_rule)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) => Ok(result.last_insert_rowid()),
+        }
+    }
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rule_id: i64,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM content_rules WHERE guild_id = ? AND id = ?")
+            .bind(guild_id.get() as i64)
+            .bind(content_rule_id)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        delete_guild_config_rows(&mut transaction, guild_id)
+            .await
+            .map_err(unexpected)?;
+        transaction.commit().await.map_err(unexpected)
+    }
+
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        guild_config: &GuildConfig,
+    ) -> Result<(), Error> {
+        let models = guild_config.models(guild_id);
+        let mut queries = Vec::new();
+        if let Some(logging_channel_id) = models.logging_channel_id {
+            queries.push(insert_logging_channel_query(guild_id, logging_channel_id));
+        }
+        queries.push(insert_guild_settings_query(&models.guild_settings));
+        queries.extend(
+            models
+                .message_response_configs
+                .iter()
+                .map(insert_message_response_config_query),
+        );
+        queries.extend(
+            models
+                .role_response_configs
+                .iter()
+                .map(insert_role_response_config_query),
+        );
+        queries.extend(
+            models
+                .message_templates
+                .iter()
+                .map(insert_message_template_query),
+        );
+        queries.extend(
+            models
+                .escalation_policy
+                .iter()
+                .map(insert_escalation_policy_query),
+        );
+        queries.extend(
+            models
+                .exemptions
+                .iter()
+                .map(|exemption| insert_exemption_query(guild_id, *exemption)),
+        );
+        queries.extend(
+            models
+                .burst_detection_config
+                .iter()
+                .map(insert_burst_detection_config_query),
+        );
+
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        delete_guild_config_rows(&mut transaction, guild_id)
+            .await
+            .map_err(unexpected)?;
+        for query in queries {
+            query.execute(&mut *transaction).await.map_err(unexpected)?;
+        }
+        for query in models.content_rules.iter().map(insert_content_rule_query) {
+            query.execute(&mut *transaction).await.map_err(unexpected)?;
+        }
+        transaction.commit().await.map_err(unexpected)
+    }
+
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        for table in ["message_responses", "message_templates", "logging_channels"] {
+            sqlx::query(&format!(
+                "DELETE FROM {table} WHERE guild_id = ? AND channel_id = ?"
+            ))
+            .bind(guild_id.get() as i64)
+            .bind(channel_id.get() as i64)
+            .execute(&mut *transaction)
+            .await
+            .map_err(unexpected)?;
+        }
+        transaction.commit().await.map_err(unexpected)
+    }
+}
+
+/// Deletes every config of the guild, leaving its incidents, offenses and quarantined members.
+async fn delete_guild_config_rows(
+    connection: &mut SqliteConnection,
+    guild_id: serenity::GuildId,
+) -> Result<(), sqlx::Error> {
+    for table in GUILD_CONFIG_TABLES {
+        sqlx::query(&format!("DELETE FROM {table} WHERE guild_id = ?"))
+            .bind(guild_id.get() as i64)
+            .execute(&mut *connection)
+            .await?;
+    }
+    Ok(())
+}
+
+// The queries that insert configs are built apart from running them, so they can run on the pool
+// or in a transaction.
+fn insert_logging_channel_query<'q>(
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO logging_channels (guild_id, channel_id) VALUES ($1, $2) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET channel_id = $2"
+    ))
+    .bind(guild_id.get() as i64)
+    .bind(channel_id.get() as i64)
+}
+
+fn insert_guild_settings_query<'q>(
+    guild_settings: &'q GuildSettings,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO guild_settings (guild_id, exemption_mode, exempt_moderators, dry_run) ",
+        "VALUES ($1, $2, $3, $4) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ",
+        "exemption_mode = $2, exempt_moderators = $3, dry_run = $4"
+    ))
+    .bind(guild_settings.guild_id.get() as i64)
+    .bind(guild_settings.exemption_mode as i64)
+    .bind(guild_settings.exempt_moderators)
+    .bind(guild_settings.dry_run)
+}
+
+fn insert_message_response_config_query<'q>(
+    message_response_config: &'q MessageResponseConfig,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO message_responses ",
+        "(guild_id, channel_id, response, timeout_duration, quarantine_role_id, ",
+        "delete_message, purge_window, delete_message_days, reason, new_account_age, ",
+        "new_account_response, established_member_age, established_member_response, ",
+        "trigger_kinds, dry_run) ",
+        "VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) ",
+        "ON CONFLICT(guild_id, channel_id) DO UPDATE SET ",
+        "response = $3, timeout_duration = $4, quarantine_role_id = $5, ",
+        "delete_message = $6, purge_window = $7, delete_message_days = $8, reason = $9, ",
+        "new_account_age = $10, new_account_response = $11, ",
+        "established_member_age = $12, established_member_response = $13, ",
+        "trigger_kinds = $14, dry_run = $15"
+    ))
+    .bind(message_response_config.guild_id.get() as i64)
+    .bind(message_response_config.channel_id.get() as i64)
+    .bind(message_response_config.response as i64)
+    .bind(
+        message_response_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(message_response_config.delete_message)
+    .bind(
+        message_response_config
+            .purge_window
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(message_response_config.delete_message_days as i64)
+    .bind(&message_response_config.reason)
+    .bind(
+        message_response_config
+            .new_account_age
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .new_account_response
+            .map(|response| response as i64),
+    )
+    .bind(
+        message_response_config
+            .established_member_age
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .established_member_response
+            .map(|response| response as i64),
+    )
+    .bind(TriggerKind::to_mask(&message_response_config.trigger_kinds))
+    .bind(message_response_config.dry_run)
+}
+
+fn insert_role_response_config_query<'q>(
+    role_response_config: &'q RoleResponseConfig,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO role_responses ",
+        "(guild_id, role_id, response, timeout_duration, quarantine_role_id, purge_window, ",
+        "delete_message_days, reason) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ",
+        "ON CONFLICT(guild_id, role_id) DO UPDATE SET ",
+        "response = $3, timeout_duration = $4, quarantine_role_id = $5, purge_window = $6, ",
+        "delete_message_days = $7, reason = $8"
+    ))
+    .bind(role_response_config.guild_id.get() as i64)
+    .bind(role_response_config.role_id.get() as i64)
+    .bind(role_response_config.response as i64)
+    .bind(
+        role_response_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        role_response_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(
+        role_response_config
+            .purge_window
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(role_response_config.delete_message_days as i64)
+    .bind(&role_response_config.reason)
+}
+
+fn insert_message_template_query<'q>(
+    message_template: &'q MessageTemplate,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO message_templates (guild_id, channel_id, kind, template) ",
+        "VALUES ($1, $2, $3, $4) ",
+        "ON CONFLICT(guild_id, channel_id, kind) DO UPDATE SET template = $4"
+    ))
+    .bind(message_template.guild_id.get() as i64)
+    .bind(optional_channel_id(message_template.channel_id))
+    .bind(message_template.kind as i64)
+    .bind(&message_template.template)
+}
+
+fn insert_escalation_policy_query<'q>(
+    escalation_policy: &'q EscalationPolicy,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    let ladder = escalation_policy
+        .ladder
+        .iter()
+        .map(|response| (*response as i64).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    sqlx::query(concat!(
+        "INSERT INTO escalation_policies (guild_id, ladder, decay_window) VALUES ($1, $2, $3) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ladder = $2, decay_window = $3"
+    ))
+    .bind(escalation_policy.guild_id.get() as i64)
+    .bind(ladder)
+    .bind(escalation_policy.decay_window.as_secs() as i64)
+}
+
+fn insert_exemption_query<'q>(
+    guild_id: serenity::GuildId,
+    exemption: Exemption,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    let (kind, target_id) = exemption_columns(exemption);
+    sqlx::query(concat!(
+        "INSERT INTO exemptions (guild_id, kind, target_id) VALUES ($1, $2, $3) ",
+        "ON CONFLICT(guild_id, kind, target_id) DO NOTHING"
+    ))
+    .bind(guild_id.get() as i64)
+    .bind(kind)
+    .bind(target_id)
+}
+
+fn insert_burst_detection_config_query<'q>(
+    burst_detection_config: &'q BurstDetectionConfig,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO burst_detection ",
+        "(guild_id, channel_count, window, response, timeout_duration, quarantine_role_id, ",
+        "delete_messages) VALUES ($1, $2, $3, $4, $5, $6, $7) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ",
+        "channel_count = $2, window = $3, response = $4, timeout_duration = $5, ",
+        "quarantine_role_id = $6, delete_messages = $7"
+    ))
+    .bind(burst_detection_config.guild_id.get() as i64)
+    .bind(burst_detection_config.channel_count as i64)
+    .bind(burst_detection_config.window.as_secs() as i64)
+    .bind(burst_detection_config.response as i64)
+    .bind(
+        burst_detection_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        burst_detection_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(burst_detection_config.delete_messages)
+}
+
+fn insert_content_rule_query<'q>(
+    content_rule: &'q ContentRule,
+) -> sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(concat!(
+        "INSERT INTO content_rules ",
+        "(guild_id, kind, pattern, response, timeout_duration, quarantine_role_id, ",
+        "delete_message, reason) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+    ))
+    .bind(content_rule.guild_id.get() as i64)
+    .bind(content_rule.kind as i64)
+    .bind(&content_rule.pattern)
+    .bind(content_rule.response as i64)
+    .bind(
+        content_rule
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        content_rule
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(content_rule.delete_message)
+    .bind(&content_rule.reason)
+}
+
+/// Tables holding a guild's configs, cleared when the bot leaves the guild.
+pub(super) const GUILD_CONFIG_TABLES: [&str; 9] = [
+    "message_responses",
+    "logging_channels",
+    "message_templates",
+    "escalation_policies",
+    "exemptions",
+    "guild_settings",
+    "role_responses",
+    "burst_detection",
+    "content_rules",
+];
+
+/// Reads the migrations in a directory, failing if it has none. Migrations used to be directly in
+/// `migrations`, so an old `--migrations-path` would otherwise look like every applied migration
+/// is unknown.
+pub(super) async fn read_migrations(migrations_path: &str) -> Result<Migrator, Error> {
+    let migrator = Migrator::new(Path::new(migrations_path))
+        .await
+        .map_err(|why| {
+            Error::DatabaseMigrationErr(format!(
+                "Error reading migrations from `{migrations_path}`: {why}"
+            ))
+        })?;
+    if migrator.iter().next().is_none() {
+        return Err(Error::DatabaseMigrationErr(format!(
+            "No migrations found in `{migrations_path}`. Each database has its own migrations \
+            directory, e.g. `migrations/sqlite` or `migrations/postgres`"
+        )));
+    }
+    Ok(migrator)
+}
+
+/// Compares the applied migrations, as `(version, success, checksum)` rows of
+/// `_sqlx_migrations`, with the known ones. Pending migrations are only a problem if they won't
+/// be applied, or if they're older than an applied one, since they were probably written against
+/// an older schema.
+pub(super) fn check_migrations(
+    migrator: &Migrator,
+    applied: &[(i64, bool, Vec<u8>)],
+    migrate: bool,
+) -> Result<(), Error> {
+    let mut problems = vec![];
+    for (version, success, checksum) in applied {
+        if !success {
+            problems.push(format!(
+                "Migration {version} failed partway through and has to be fixed by hand"
+            ));
+            continue;
+        }
+        match migrator
+            .iter()
+            .find(|migration| migration.version == *version)
+        {
+            None => problems.push(format!(
+                "Migration {version} was applied but isn't known to this version of the bot"
+            )),
+            Some(migration) if *migration.checksum != **checksum => problems.push(format!(
+                "Migration {version} ({}) was changed after it was applied",
+                migration.description
+            )),
+            Some(_) => (),
+        }
+    }
+    let latest_applied = applied.last().map(|(version, _, _)| *version);
+    let pending = migrator.iter().filter(|migration| {
+        !migration.migration_type.is_down_migration()
+            && !applied
+                .iter()
+                .any(|(version, _, _)| *version == migration.version)
+    });
+    for migration in pending {
+        let version = migration.version;
+        let description = &migration.description;
+        match latest_applied {
+            Some(latest_applied) if version < latest_applied => problems.push(format!(
+                "Migration {version} ({description}) is older than the latest applied migration \
+                 {latest_applied}"
+            )),
+            _ if !migrate => {
+                problems.push(format!("Migration {version} ({description}) is pending"))
+            }
+            _ => (),
+        }
+    }
+
+    match problems.is_empty() {
+        true => Ok(()),
+        false => Err(Error::DatabaseMigrationErr(format!(
+            "The database's migrations have problems:\n{}",
+            problems
+                .iter()
+                .map(|problem| format!("- {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))),
+    }
+}
+
+pub(super) const EXEMPT_USER: i64 = 0;
+pub(super) const EXEMPT_ROLE: i64 = 1;
+
+pub(super) fn exemption_columns(exemption: Exemption) -> (i64, i64) {
+    match exemption {
+        Exemption::User(user_id) => (EXEMPT_USER, user_id.get() as i64),
+        Exemption::Role(role_id) => (EXEMPT_ROLE, role_id.get() as i64),
+    }
+}
+
+/// Stores a missing channel ID as 0, which no channel has.
+pub(super) fn optional_channel_id(channel_id: Option<serenity::ChannelId>) -> i64 {
+    channel_id.map_or(0, |channel_id| channel_id.get() as i64)
+}
+
+fn message_response_config_from_row(row: &SqliteRow) -> MessageResponseConfig {
+    MessageResponseConfig {
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        channel_id: serenity::ChannelId::new(row.get::<i64, _>("channel_id") as u64),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        trigger_kinds: TriggerKind::from_mask(row.get("trigger_kinds")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        delete_message: row.get("delete_message"),
+        purge_window: row
+            .get::<Option<i64>, _>("purge_window")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        delete_message_days: row.get::<i64, _>("delete_message_days") as u8,
+        reason: row.get("reason"),
+        new_account_age: row
+            .get::<Option<i64>, _>("new_account_age")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        new_account_response: row
+            .get::<Option<i64>, _>("new_account_response")
+            .map(MessageResponse::from),
+        established_member_age: row
+            .get::<Option<i64>, _>("established_member_age")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        established_member_response: row
+            .get::<Option<i64>, _>("established_member_response")
+            .map(MessageResponse::from),
+        dry_run: row.get("dry_run"),
+    }
+}
+
+fn incident_from_row(row: &SqliteRow) -> Incident {
+    Incident {
+        id: row.get("id"),
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        channel_id: match row.get::<i64, _>("channel_id") {
+            0 => None,
+            channel_id => Some(serenity::ChannelId::new(channel_id as u64)),
+        },
+        user_id: serenity::UserId::new(row.get::<i64, _>("user_id") as u64),
+        action: MessageResponse::from(row.get::<i64, _>("action")),
+        trigger_kind: TriggerKind::from(row.get::<i64, _>("trigger_kind")),
+        content: row.get("content"),
+        created_at: serenity::Timestamp::from_unix_timestamp(row.get("created_at"))
+            .unwrap_or_default(),
+        outcome: row.get("outcome"),
+        error: row.get("error"),
+        false_positive: row.get("false_positive"),
+        dry_run: row.get("dry_run"),
+    }
+}
+
+fn role_response_config_from_row(row: &SqliteRow) -> RoleResponseConfig {
+    RoleResponseConfig {
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        role_id: serenity::RoleId::new(row.get::<i64, _>("role_id") as u64),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        purge_window: row
+            .get::<Option<i64>, _>("purge_window")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        delete_message_days: row.get::<i64, _>("delete_message_days") as u8,
+        reason: row.get("reason"),
+    }
+}
+
+fn content_rule_from_row(row: &SqliteRow) -> ContentRule {
+    ContentRule {
+        id: row.get("id"),
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        kind: ContentRuleKind::from(row.get::<i64, _>("kind")),
+        pattern: row.get("pattern"),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        delete_message: row.get("delete_message"),
+        reason: row.get("reason"),
+    }
+}
+
+impl Database {
+    /// Opens the database and applies pending migrations if `options.migrate` is set, failing
+    /// with a list of problems if the migrations can't be applied or the database isn't up to
+    /// date.
+    pub async fn new(options: &DatabaseOptions) -> Result<Self, Error> {
+        let pool = sqlx::Pool::connect_with(
+            SqliteConnectOptions::new()
+                .filename(&options.filename)
+                .create_if_missing(true),
+        )
+        .await
+        .map_err(|why| {
+            Error::DatabaseMigrationErr(format!("Error opening `{}`: {why}", options.filename))
+        })?;
+        let db = Self { pool };
+
+        let directory_migrator;
+        let migrator = match &options.migrations_path {
+            Some(migrations_path) => {
+                directory_migrator = read_migrations(migrations_path).await?;
+                &directory_migrator
+            }
+            None => &EMBEDDED_MIGRATOR,
+        };
+        db.check_migrations(migrator, options.migrate).await?;
+        if options.migrate {
+            migrator.run(&db.pool).await.map_err(|why| {
+                Error::DatabaseMigrationErr(format!("Error applying migrations: {why}"))
+            })?;
+        }
+        Ok(db)
+    }
+
+    /// Fails if the applied migrations don't match the known ones, see `check_migrations`.
+    async fn check_migrations(&self, migrator: &Migrator, migrate: bool) -> Result<(), Error> {
+        let table_exists: bool = sqlx::query_scalar(concat!(
+            "SELECT COUNT(*) > 0 FROM sqlite_master ",
+            "WHERE type = 'table' AND name = '_sqlx_migrations'"
+        ))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))?;
+        let applied: Vec<(i64, bool, Vec<u8>)> = match table_exists {
+            true => sqlx::query_as(
+                "SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version",
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))?,
+            false => vec![],
+        };
+        check_migrations(migrator, &applied, migrate)
+    }
+
+    /// Returns the version and description of every migration applied to the database, oldest
+    /// first.
+    pub async fn list_applied_migrations(&self) -> Result<Vec<(i64, String)>, Error> {
+        let rows: Result<Vec<(i64, String)>, sqlx::Error> = sqlx::query_as(
+            "SELECT version, description FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await;
+        rows.map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::datastore::test_utils::{get_test_db, test_each_backend};
+
+    use super::*;
+
+    test_each_backend! {
+        async fn create_read_and_delete_message_response_config(db) {
+
+            let guild_id = 12345678;
+            let channel_id = 87654321;
+
+            // Create the message response configuration in db
+            let mut message_response = MessageResponseConfig::new(
+                serenity::GuildId::from(guild_id),
+                serenity::ChannelId::from(channel_id),
+                MessageResponse::Ban,
+            );
+            let result = db.insert_message_response_config(&message_response).await;
+            assert_eq!(result, Ok(()));
+
+            // Read the message response for guild and channel id
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Update the message response for guild and channel id
+            message_response.response = MessageResponse::Kick;
+            message_response.delete_message_days = 1;
+            message_response.reason = Some("{user} posted in #{channel}".to_string());
+            let result = db.insert_message_response_config(&message_response).await;
+            assert_eq!(result, Ok(()));
+
+            // Read the updated message response for guild and channel id
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Update the message response to a timeout with a duration
+            message_response.response = MessageResponse::Timeout;
+            message_response.timeout_duration = Some(Duration::from_secs(60 * 60));
+            let result = db.insert_message_response_config(&message_response).await;
+            assert_eq!(result, Ok(()));
+
+            // Read the timeout message response and duration for guild and channel id
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Update the message response to a quarantine with a role
+            message_response.response = MessageResponse::Quarantine;
+            message_response.timeout_duration = None;
+            message_response.quarantine_role_id = Some(serenity::RoleId::new(11223344));
+            message_response.delete_message = true;
+            message_response.purge_window = Some(Duration::from_secs(10 * 60));
+            let result = db.insert_message_response_config(&message_response).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Update the message response with account and join age rules
+            message_response.new_account_age = Some(Duration::from_secs(7 * 24 * 60 * 60));
+            message_response.new_account_response = Some(MessageResponse::Ban);
+            message_response.established_member_age = Some(Duration::from_secs(30 * 24 * 60 * 60));
+            message_response.established_member_response = Some(MessageResponse::Respond);
+            message_response.trigger_kinds = vec![TriggerKind::Reaction, TriggerKind::Thread];
+            message_response.dry_run = true;
+            let result = db.insert_message_response_config(&message_response).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // List the guild's honeypot channels
+            let mut other_channel = MessageResponseConfig::new(
+                message_response.guild_id,
+                serenity::ChannelId::new(channel_id + 1),
+                MessageResponse::Ban,
+            );
+            other_channel.trigger_kinds = vec![TriggerKind::Voice];
+            let result = db.insert_message_response_config(&other_channel).await;
+            assert_eq!(result, Ok(()));
+            let result = db
+                .list_message_response_configs(message_response.guild_id)
+                .await;
+            assert_eq!(
+                result,
+                Ok(vec![message_response.clone(), other_channel.clone()])
+            );
+            let result = db
+                .delete_message_response_config(other_channel.guild_id, other_channel.channel_id)
+                .await;
+            assert_eq!(result, Ok(()));
+
+            // Delete the message response config
+            let result = db
+                .delete_message_response_config(
+                    message_response.guild_id,
+                    message_response.channel_id,
+                )
+                .await;
+            assert_eq!(result, Ok(()));
+
+            // Message response config should be deleted
+            let result = db
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+
+            // Clean up rows:
+            db.delete_message_response_config(
+                serenity::GuildId::from(guild_id),
+                serenity::ChannelId::from(channel_id),
+            )
+            .await
+            .unwrap();
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_logging_channel(db) {
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let channel_id = serenity::ChannelId::new(87654321);
+            let result = db.insert_logging_channel(guild_id, channel_id).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db.get_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(channel_id));
+
+            let channel_id = serenity::ChannelId::new(1234567);
+            let result = db.insert_logging_channel(guild_id, channel_id).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db.get_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(channel_id));
+
+            let result = db.delete_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db.get_logging_channel(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_quarantined_member(db) {
+
+            let mut quarantined_member = QuarantinedMember {
+                guild_id: serenity::GuildId::new(12345678),
+                user_id: serenity::UserId::new(87654321),
+                quarantine_role_id: serenity::RoleId::new(11223344),
+                removed_role_ids: vec![serenity::RoleId::new(1), serenity::RoleId::new(2)],
+            };
+            let result = db.insert_quarantined_member(&quarantined_member).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db
+                .get_quarantined_member(quarantined_member.guild_id, quarantined_member.user_id)
+                .await;
+            assert_eq!(result, Ok(quarantined_member.clone()));
+
+            // Members without any removable roles should round trip too
+            quarantined_member.removed_role_ids = vec![];
+            let result = db.insert_quarantined_member(&quarantined_member).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db
+                .get_quarantined_member(quarantined_member.guild_id, quarantined_member.user_id)
+                .await;
+            assert_eq!(result, Ok(quarantined_member.clone()));
+
+            let result = db
+                .delete_quarantined_member(quarantined_member.guild_id, quarantined_member.user_id)
+                .await;
+            assert_eq!(result, Ok(()));
+
+            let result = db
+                .get_quarantined_member(quarantined_member.guild_id, quarantined_member.user_id)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_message_template(db) {
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let channel_id = serenity::ChannelId::new(87654321);
+            let guild_template = MessageTemplate {
+                guild_id,
+                channel_id: None,
+                kind: TemplateKind::Reply,
+                template: "Hello {user}".to_string(),
+            };
+            let channel_template = MessageTemplate {
+                channel_id: Some(channel_id),
+                template: "Hello {user}, welcome to {channel}".to_string(),
+                ..guild_template.clone()
+            };
+            let result = db.insert_message_template(&guild_template).await;
+            assert_eq!(result, Ok(()));
+            let result = db.insert_message_template(&channel_template).await;
+            assert_eq!(result, Ok(()));
+
+            // Guild-wide and channel templates are stored separately
+            let result = db
+                .get_message_template(guild_id, None, TemplateKind::Reply)
+                .await;
+            assert_eq!(result, Ok(guild_template.template.clone()));
+            let result = db
+                .get_message_template(guild_id, Some(channel_id), TemplateKind::Reply)
+                .await;
+            assert_eq!(result, Ok(channel_template.template.clone()));
+            let result = db
+                .get_message_template(guild_id, None, TemplateKind::Warning)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+
+            // Guild-wide templates are listed first
+            let result = db.list_message_templates(guild_id).await;
+            assert_eq!(
+                result,
+                Ok(vec![guild_template.clone(), channel_template.clone()])
+            );
+
+            let result = db
+                .delete_message_template(guild_id, Some(channel_id), TemplateKind::Reply)
+                .await;
+            assert_eq!(result, Ok(()));
+            let result = db
+                .get_message_template(guild_id, Some(channel_id), TemplateKind::Reply)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+
+            // Clean up rows:
+            db.delete_message_template(guild_id, None, TemplateKind::Reply)
+                .await
+                .unwrap();
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_escalation_policy(db) {
+
+            let escalation_policy = EscalationPolicy {
+                guild_id: serenity::GuildId::new(12345678),
+                ladder: vec![
+                    MessageResponse::Respond,
+                    MessageResponse::Kick,
+                    MessageResponse::Ban,
+                ],
+                decay_window: Duration::from_secs(30 * 24 * 60 * 60),
+            };
+            let result = db.insert_escalation_policy(&escalation_policy).await;
+            assert_eq!(result, Ok(()));
+
+            let result = db.get_escalation_policy(escalation_policy.guild_id).await;
+            assert_eq!(result, Ok(escalation_policy.clone()));
+
+            let result = db
+                .delete_escalation_policy(escalation_policy.guild_id)
+                .await;
+            assert_eq!(result, Ok(()));
+
+            let result = db.get_escalation_policy(escalation_policy.guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
+    }
+
+    test_each_backend! {
+        async fn insert_and_count_offenses(db) {
+
+            // Use a random guild ID since offenses can't be deleted
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let user_id = serenity::UserId::new(87654321);
+            let old = serenity::Timestamp::from_unix_timestamp(1_000_000).unwrap();
+            let recent = serenity::Timestamp::from_unix_timestamp(2_000_000).unwrap();
+
+            assert_eq!(db.insert_offense(guild_id, user_id, old).await, Ok(()));
+            assert_eq!(db.insert_offense(guild_id, user_id, recent).await, Ok(()));
+            assert_eq!(db.insert_offense(guild_id, user_id, recent).await, Ok(()));
+
+            let result = db.count_offenses(guild_id, user_id, old).await;
+            assert_eq!(result, Ok(3));
+
+            // Offenses before `since` have decayed
+            let result = db.count_offenses(guild_id, user_id, recent).await;
+            assert_eq!(result, Ok(2));
+
+            // Only one of the offenses at the same time is deleted
+            assert_eq!(db.delete_offense(guild_id, user_id, recent).await, Ok(()));
+            assert_eq!(db.count_offenses(guild_id, user_id, old).await, Ok(2));
+            assert_eq!(db.delete_offense(guild_id, user_id, recent).await, Ok(()));
+            assert_eq!(
+                db.delete_offense(guild_id, user_id, recent).await,
+                Err(Error::DatabaseEntryNotFound)
+            );
+            assert_eq!(db.count_offenses(guild_id, user_id, old).await, Ok(1));
+        }
+    }
+
+    test_each_backend! {
+        async fn insert_and_list_incidents(db) {
+
+            // Use a random guild ID since incidents can't be deleted
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let mut ban = Incident {
+                id: 0,
+                guild_id,
+                channel_id: Some(serenity::ChannelId::new(1)),
+                user_id: serenity::UserId::new(87654321),
+                action: MessageResponse::Ban,
+                trigger_kind: TriggerKind::Message,
+                content: "free nitro".to_string(),
+                created_at: serenity::Timestamp::from_unix_timestamp(1_000_000).unwrap(),
+                outcome: None,
+                error: None,
+                false_positive: false,
+                dry_run: false,
+            };
+            let mut kick = Incident {
+                channel_id: Some(serenity::ChannelId::new(2)),
+                user_id: serenity::UserId::new(12345678),
+                action: MessageResponse::Kick,
+                trigger_kind: TriggerKind::Reaction,
+                created_at: serenity::Timestamp::from_unix_timestamp(2_000_000).unwrap(),
+                error: Some("Missing Permissions".to_string()),
+                dry_run: true,
+                ..ban.clone()
+            };
+            ban.id = db.insert_incident(&ban).await.unwrap();
+            kick.id = db.insert_incident(&kick).await.unwrap();
+
+            let mut filter = IncidentFilter {
+                guild_id,
+                user_id: None,
+                channel_id: None,
+                action: None,
+                include_dry_runs: true,
+                limit: 10,
+                offset: 0,
+            };
+
+            // Newest incidents are listed first
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![kick.clone(), ban.clone()]));
+
+            filter.offset = 1;
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![ban.clone()]));
+            filter.offset = 0;
+
+            filter.user_id = Some(ban.user_id);
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![ban.clone()]));
+            filter.user_id = None;
+
+            filter.channel_id = kick.channel_id;
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![kick.clone()]));
+            filter.channel_id = None;
+
+            filter.action = Some(MessageResponse::Respond);
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![]));
+            filter.action = None;
+
+            filter.include_dry_runs = false;
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![ban.clone()]));
+            filter.include_dry_runs = true;
+
+            // Each honeypot channel's incidents are counted
+            let result = db.get_incident_stats(guild_id).await;
+            assert_eq!(
+                result,
+                Ok(vec![
+                    IncidentStats {
+                        channel_id: ban.channel_id,
+                        count: 1,
+                        last_created_at: ban.created_at,
+                    },
+                    IncidentStats {
+                        channel_id: kick.channel_id,
+                        count: 1,
+                        last_created_at: kick.created_at,
+                    },
+                ])
+            );
+
+            // Incidents can be marked as false positives
+            let result = db.mark_incident_false_positive(guild_id, ban.id).await;
+            assert_eq!(result, Ok(()));
+            ban.false_positive = true;
+            filter.user_id = Some(ban.user_id);
+            let result = db.list_incidents(&filter).await;
+            assert_eq!(result, Ok(vec![ban.clone()]));
+
+            // Incidents of other guilds can't be marked
+            let result = db
+                .mark_incident_false_positive(serenity::GuildId::new(1), kick.id)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_exemptions(db) {
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let user = Exemption::User(serenity::UserId::new(87654321));
+            let role = Exemption::Role(serenity::RoleId::new(87654321));
+            assert_eq!(db.insert_exemption(guild_id, user).await, Ok(()));
+            assert_eq!(db.insert_exemption(guild_id, role).await, Ok(()));
+            // Inserting an existing exemption does nothing
+            assert_eq!(db.insert_exemption(guild_id, user).await, Ok(()));
+
+            let result = db.get_exemptions(guild_id).await.map(|mut exemptions| {
+                exemptions.sort_by_key(|exemption| matches!(exemption, Exemption::Role(_)));
+                exemptions
+            });
+            assert_eq!(result, Ok(vec![user, role]));
+
+            assert_eq!(db.delete_exemption(guild_id, user).await, Ok(()));
+            assert_eq!(db.get_exemptions(guild_id).await, Ok(vec![role]));
+            assert_eq!(
+                db.delete_exemption(guild_id, user).await,
+                Err(Error::DatabaseEntryNotFound)
+            );
+
+            // Clean up rows:
+            db.delete_exemption(guild_id, role).await.unwrap();
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_role_response_configs(db) {
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let mut kick = RoleResponseConfig::new(
+                guild_id,
+                serenity::RoleId::new(11111111),
+                MessageResponse::Kick,
+            );
+            let mut timeout = RoleResponseConfig::new(
+                guild_id,
+                serenity::RoleId::new(22222222),
+                MessageResponse::Timeout,
+            );
+            timeout.timeout_duration = Some(Duration::from_secs(60 * 60));
+            timeout.purge_window = Some(Duration::from_secs(10 * 60));
+            timeout.reason = Some("{user} took {channel}".to_string());
+            assert_eq!(db.insert_role_response_config(&kick).await, Ok(()));
+            assert_eq!(db.insert_role_response_config(&timeout).await, Ok(()));
+
+            let result = db.get_role_response_configs(guild_id).await;
+            assert_eq!(result, Ok(vec![kick.clone(), timeout.clone()]));
+
+            // Update the kick role to a quarantine
+            kick.response = MessageResponse::Quarantine;
+            kick.quarantine_role_id = Some(serenity::RoleId::new(33333333));
+            assert_eq!(db.insert_role_response_config(&kick).await, Ok(()));
+            let result = db.get_role_response_configs(guild_id).await;
+            assert_eq!(result, Ok(vec![kick.clone(), timeout.clone()]));
+
+            // Delete the roles
+            let result = db.delete_role_response_config(guild_id, kick.role_id).await;
+            assert_eq!(result, Ok(()));
+            let result = db.get_role_response_configs(guild_id).await;
+            assert_eq!(result, Ok(vec![timeout.clone()]));
+            let result = db.delete_role_response_config(guild_id, kick.role_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+
+            // Clean up rows:
+            db.delete_role_response_config(guild_id, timeout.role_id)
+                .await
+                .unwrap();
+        }
+    }
+
+    test_each_backend! {
+        async fn create_read_and_delete_content_rules(db) {
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let mut keyword = ContentRule {
+                id: 0,
+                guild_id,
+                kind: ContentRuleKind::Keyword,
+                pattern: "free nitro, steam gift".to_string(),
+                response: MessageResponse::Ban,
+                timeout_duration: None,
+                quarantine_role_id: None,
+                delete_message: true,
+                reason: None,
+            };
+            let mut mass_mention = ContentRule {
+                id: 0,
+                guild_id,
+                kind: ContentRuleKind::MassMention,
+                pattern: "5".to_string(),
+                response: MessageResponse::Timeout,
+                timeout_duration: Some(Duration::from_secs(60 * 60)),
+                quarantine_role_id: None,
+                delete_message: false,
+                reason: Some("{user} mentioned too many people".to_string()),
+            };
+            keyword.id = db.insert_content_rule(&keyword).await.unwrap();
+            mass_mention.id = db.insert_content_rule(&mass_mention).await.unwrap();
+            assert!(keyword.id < mass_mention.id);
+
+            let result = db.get_content_rules(guild_id).await;
+            assert_eq!(result, Ok(vec![keyword.clone(), mass_mention.clone()]));
+
+            // Rules can only be deleted from their own guild
+            let result = db
+                .delete_content_rule(serenity::GuildId::new(87654321), keyword.id)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = db.delete_content_rule(guild_id, keyword.id).await;
+            assert_eq!(result, Ok(()));
+            let result = db.get_content_rules(guild_id).await;
+            assert_eq!(result, Ok(vec![mass_mention.clone()]));
+
+            // Clean up rows:
+            db.delete_content_rule(guild_id, mass_mention.id)
+                .await
+                .unwrap();
+        }
+    }
+
+    test_each_backend! {
+        async fn delete_channel_and_guild_configs(db) {
+
+            // Use a random guild ID since offenses can't be deleted
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let honeypot_channel_id = serenity::ChannelId::new(87654321);
+            let logging_channel_id = serenity::ChannelId::new(87654322);
+            let honeypot =
+                MessageResponseConfig::new(guild_id, honeypot_channel_id, MessageResponse::Ban);
+            db.insert_message_response_config(&honeypot).await.unwrap();
+            db.insert_logging_channel(guild_id, logging_channel_id)
+                .await
+                .unwrap();
+            db.insert_message_template(&MessageTemplate {
+                guild_id,
+                channel_id: Some(honeypot_channel_id),
+                kind: TemplateKind::Warning,
+                template: "Don't post here".to_string(),
+            })
+            .await
+            .unwrap();
+            db.insert_guild_settings(&GuildSettings::new(guild_id))
+                .await
+                .unwrap();
+            let content_rule_id = db
+                .insert_content_rule(&ContentRule {
+                    id: 0,
+                    guild_id,
+                    kind: ContentRuleKind::Invite,
+                    pattern: String::new(),
+                    response: MessageResponse::Kick,
+                    timeout_duration: None,
+                    quarantine_role_id: None,
+                    delete_message: false,
+                    reason: None,
+                })
+                .await
+                .unwrap();
+            let offense_at = serenity::Timestamp::now();
+            db.insert_offense(guild_id, serenity::UserId::new(1), offense_at)
+                .await
+                .unwrap();
+
+            // Deleting the honeypot channel removes its config and templates, but not the logging
+            // channel
+            let result = db
+                .delete_channel_configs(guild_id, honeypot_channel_id)
+                .await;
+            assert_eq!(result, Ok(()));
+            let result = db
+                .get_message_response_config(guild_id, honeypot_channel_id)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = db
+                .get_message_template(guild_id, Some(honeypot_channel_id), TemplateKind::Warning)
+                .await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = db.get_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(logging_channel_id));
+
+            // Deleting the logging channel removes it
+            let result = db
+                .delete_channel_configs(guild_id, logging_channel_id)
+                .await;
+            assert_eq!(result, Ok(()));
+            let result = db.get_logging_channel(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+
+            // Leaving the guild removes its configs, but keeps offenses
+            let result = db.delete_guild_configs(guild_id).await;
+            assert_eq!(result, Ok(()));
+            let result = db.get_content_rules(guild_id).await;
+            assert_eq!(result, Ok(vec![]));
+            let result = db.get_guild_settings(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = db.delete_content_rule(guild_id, content_rule_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = db
+                .count_offenses(guild_id, serenity::UserId::new(1), offense_at)
+                .await;
+            assert_eq!(result, Ok(1));
+        }
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn list_applied_migrations() {
+        let db = get_test_db().await;
+
+        // Every migration in the directory is applied, oldest first
+        let migration_count = std::fs::read_dir("migrations/sqlite").unwrap().count();
+        let migrations = db.list_applied_migrations().await.unwrap();
+        assert_eq!(migrations.len(), migration_count);
+        assert!(migrations.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn check_migrations() {
+        let filename = "/tmp/honeybot-migrations-test.db";
+        let _ = std::fs::remove_file(filename);
+        let options = |migrations_path: Option<&Path>, migrate: bool| DatabaseOptions {
+            filename: filename.to_string(),
+            migrations_path: migrations_path.map(|path| path.display().to_string()),
+            migrate,
+        };
+        let problems = |result: Result<Database, Error>| match result {
+            Ok(_) => String::new(),
+            Err(why) => why.to_string(),
+        };
+
+        // Every migration of a new database is pending, so it can't be used without migrating
+        let result = Database::new(&options(None, false)).await;
+        assert!(problems(result).contains("(create-burst-detection-table) is pending"));
+        let db = Database::new(&options(None, true)).await.unwrap();
+        assert!(Database::new(&options(None, false)).await.is_ok());
+
+        // Applied migrations that the bot doesn't know are reported
+        let migrations_path = std::env::temp_dir().join("honeybot-test-migrations");
+        let _ = std::fs::remove_dir_all(&migrations_path);
+        std::fs::create_dir(&migrations_path).unwrap();
+        let mut migration_paths: Vec<_> = std::fs::read_dir("migrations/sqlite")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        migration_paths.sort();
+        let (latest_path, older_paths) = migration_paths.split_last().unwrap();
+        for path in older_paths {
+            std::fs::copy(path, migrations_path.join(path.file_name().unwrap())).unwrap();
+        }
+        let result = Database::new(&options(Some(&migrations_path), true)).await;
+        let latest_version = latest_path.file_name().unwrap().to_str().unwrap()[..14].to_string();
+        assert!(problems(result).contains(&format!(
+            "Migration {latest_version} was applied but isn't known"
+        )));
+
+        // Pending migrations older than an applied one aren't applied
+        sqlx::query(concat!(
+            "DELETE FROM _sqlx_migrations ",
+            "WHERE version = (SELECT MIN(version) FROM _sqlx_migrations)"
+        ))
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let result = Database::new(&options(None, true)).await;
+        assert!(problems(result).contains("is older than the latest applied migration"));
 
-        let result = db.get_logging_channel(guild_id).await;
-        assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        // Directories without migrations, like the old `migrations` path, are rejected
+        let result = Database::new(&options(Some(Path::new("migrations")), true)).await;
+        assert!(problems(result).contains("No migrations found in `migrations`"));
     }
 }
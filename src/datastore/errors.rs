@@ -1,6 +1,21 @@
+use std::fmt;
+
 #[derive(Debug, PartialEq)]
 pub enum Error {
     DatabaseEntryNotFound,
     DatabaseUnexpectedErr(String),
+    /// The database couldn't be opened or its migrations are broken, described for the user
+    DatabaseMigrationErr(String),
     CacheEntryNotFound,
 }
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::DatabaseEntryNotFound => write!(f, "Entry not found in the database"),
+            Error::DatabaseUnexpectedErr(why) => write!(f, "Unexpected database error: {why}"),
+            Error::DatabaseMigrationErr(why) => write!(f, "{why}"),
+            Error::CacheEntryNotFound => write!(f, "Entry not found in the cache"),
+        }
+    }
+}
@@ -1,63 +1,81 @@
 use poise::serenity_prelude::{self as serenity};
 
-use crate::datastore::{
-    errors::Error,
-    models::{MessageResponse, MessageResponseConfig},
-    traits::{DatastoreReader, DatastoreWriter},
+use crate::{
+    datastore::{
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, EscalationPolicy, Exemption, GuildSettings,
+            Incident, IncidentFilter, IncidentStats, MessageResponse, MessageResponseConfig,
+            MessageTemplate, QuarantinedMember, RoleResponseConfig, TemplateKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config::GuildConfig,
 };
 
+pub mod backend;
 pub mod cache;
 pub mod database;
 pub mod errors;
 pub mod models;
+pub mod postgres;
 pub mod traits;
 
 #[cfg(test)]
-mod test_utils;
+pub(crate) mod test_utils;
 
 pub mod prelude {
     pub use super::traits::*;
 }
 
-pub struct Datastore {
+/// How long cached configs are kept when the database is shared with other instances of the bot.
+pub const SHARED_CACHE_TIME_TO_LIVE: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Reads through the cache to the database `D`, which is whichever backend was configured
+/// outside of tests.
+pub struct Datastore<D = backend::Backend> {
     cache: cache::DatabaseCache,
-    database: database::Database,
+    database: D,
 }
 
 pub struct DatastoreOptions {
     pub cache_options: cache::CacheOptions,
-    pub database_options: database::DatabaseOptions,
+    pub backend_options: backend::BackendOptions,
 }
 
-impl DatastoreReader for Datastore {
-    async fn get_message_response(
+impl<D: DatastoreReader> DatastoreReader for Datastore<D> {
+    async fn get_message_response_config(
         &self,
         guild_id: serenity::GuildId,
         channel_id: serenity::ChannelId,
-    ) -> Result<MessageResponse, Error> {
+    ) -> Result<MessageResponseConfig, Error> {
         // Return cached value if it's found
-        let result = self.cache.get_message_response(guild_id, channel_id).await;
+        let result = self
+            .cache
+            .get_message_response_config(guild_id, channel_id)
+            .await;
         if result.is_ok() {
             return result;
         }
 
-        // Read from database after cache miss
-        let response = self
+        // Read from database after cache miss. Channels that aren't honeypots get a placeholder
+        // config so the database isn't queried for every message in them.
+        let config = match self
             .database
-            .get_message_response(guild_id, channel_id)
-            .await?;
+            .get_message_response_config(guild_id, channel_id)
+            .await
+        {
+            Ok(config) => config,
+            Err(Error::DatabaseEntryNotFound) => {
+                MessageResponseConfig::new(guild_id, channel_id, MessageResponse::Nothing)
+            }
+            Err(why) => return Err(why),
+        };
 
         // Ignore cache insertion errors
-        let _ = self
-            .cache
-            .insert_message_response_config(&MessageResponseConfig {
-                guild_id,
-                channel_id,
-                response,
-            })
-            .await;
+        let _ = self.cache.insert_message_response_config(&config).await;
 
-        Ok(response)
+        Ok(config)
     }
 
     async fn get_logging_channel(
@@ -80,9 +98,171 @@ impl DatastoreReader for Datastore {
 
         Ok(channel_id)
     }
+
+    async fn get_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error> {
+        self.database
+            .get_quarantined_member(guild_id, user_id)
+            .await
+    }
+
+    async fn get_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<String, Error> {
+        self.database
+            .get_message_template(guild_id, channel_id, kind)
+            .await
+    }
+
+    async fn list_message_templates(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error> {
+        self.database.list_message_templates(guild_id).await
+    }
+
+    async fn get_escalation_policy(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error> {
+        self.database.get_escalation_policy(guild_id).await
+    }
+
+    async fn count_offenses(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        since: serenity::Timestamp,
+    ) -> Result<u32, Error> {
+        self.database.count_offenses(guild_id, user_id, since).await
+    }
+
+    async fn list_incidents(&self, filter: &IncidentFilter) -> Result<Vec<Incident>, Error> {
+        self.database.list_incidents(filter).await
+    }
+
+    async fn get_incident_stats(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error> {
+        self.database.get_incident_stats(guild_id).await
+    }
+
+    async fn list_message_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error> {
+        self.database.list_message_response_configs(guild_id).await
+    }
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error> {
+        let result = self.cache.get_exemptions(guild_id).await;
+        if result.is_ok() {
+            return result;
+        }
+
+        // Read from database after cache miss
+        let exemptions = self.database.get_exemptions(guild_id).await?;
+        self.cache
+            .insert_exemptions(guild_id, exemptions.clone())
+            .await;
+
+        Ok(exemptions)
+    }
+
+    async fn get_guild_settings(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<GuildSettings, Error> {
+        let result = self.cache.get_guild_settings(guild_id).await;
+        if result.is_ok() {
+            return result;
+        }
+
+        // Read from database after cache miss, using the default settings for guilds that haven't
+        // changed them.
+        let guild_settings = match self.database.get_guild_settings(guild_id).await {
+            Ok(guild_settings) => guild_settings,
+            Err(Error::DatabaseEntryNotFound) => GuildSettings::new(guild_id),
+            Err(why) => return Err(why),
+        };
+
+        // Ignore cache insertion errors
+        let _ = self.cache.insert_guild_settings(&guild_settings).await;
+
+        Ok(guild_settings)
+    }
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error> {
+        let result = self.cache.get_role_response_configs(guild_id).await;
+        if result.is_ok() {
+            return result;
+        }
+
+        // Read from database after cache miss
+        let role_response_configs = self.database.get_role_response_configs(guild_id).await?;
+        self.cache
+            .insert_role_response_configs(guild_id, role_response_configs.clone())
+            .await;
+
+        Ok(role_response_configs)
+    }
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error> {
+        match self.cache.get_burst_detection_config(guild_id).await {
+            Err(Error::CacheEntryNotFound) => (),
+            result => return result,
+        }
+
+        // Read from database after cache miss, remembering guilds without burst detection too
+        let result = self.database.get_burst_detection_config(guild_id).await;
+        match &result {
+            Ok(burst_detection_config) => {
+                self.cache
+                    .cache_burst_detection_config(guild_id, Some(burst_detection_config.clone()))
+                    .await
+            }
+            Err(Error::DatabaseEntryNotFound) => {
+                self.cache
+                    .cache_burst_detection_config(guild_id, None)
+                    .await
+            }
+            Err(_) => (),
+        }
+        result
+    }
+
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error> {
+        let result = self.cache.get_content_rules(guild_id).await;
+        if result.is_ok() {
+            return result;
+        }
+
+        // Read from database after cache miss
+        let content_rules = self.database.get_content_rules(guild_id).await?;
+        self.cache
+            .insert_content_rules(guild_id, content_rules.clone())
+            .await;
+        Ok(content_rules)
+    }
 }
 
-impl DatastoreWriter for Datastore {
+impl<D: DatastoreWriter> DatastoreWriter for Datastore<D> {
     async fn insert_message_response_config(
         &self,
         message_response_config: &MessageResponseConfig,
@@ -125,19 +305,242 @@ impl DatastoreWriter for Datastore {
         self.database.delete_logging_channel(guild_id).await?;
         self.cache.delete_logging_channel(guild_id).await
     }
+
+    async fn insert_quarantined_member(
+        &self,
+        quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_quarantined_member(quarantined_member)
+            .await
+    }
+
+    async fn delete_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_quarantined_member(guild_id, user_id)
+            .await
+    }
+
+    async fn insert_message_template(
+        &self,
+        message_template: &MessageTemplate,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_message_template(message_template)
+            .await
+    }
+
+    async fn delete_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_message_template(guild_id, channel_id, kind)
+            .await
+    }
+
+    async fn insert_escalation_policy(
+        &self,
+        escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_escalation_policy(escalation_policy)
+            .await
+    }
+
+    async fn delete_escalation_policy(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        self.database.delete_escalation_policy(guild_id).await
+    }
+
+    async fn insert_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_offense(guild_id, user_id, created_at)
+            .await
+    }
+
+    async fn delete_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_offense(guild_id, user_id, created_at)
+            .await
+    }
+
+    async fn insert_incident(&self, incident: &Incident) -> Result<i64, Error> {
+        self.database.insert_incident(incident).await
+    }
+
+    async fn mark_incident_false_positive(
+        &self,
+        guild_id: serenity::GuildId,
+        incident_id: i64,
+    ) -> Result<(), Error> {
+        self.database
+            .mark_incident_false_positive(guild_id, incident_id)
+            .await
+    }
+
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        self.database.insert_exemption(guild_id, exemption).await?;
+        self.cache.insert_exemption(guild_id, exemption).await
+    }
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        self.database.delete_exemption(guild_id, exemption).await?;
+        self.cache.delete_exemption(guild_id, exemption).await
+    }
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error> {
+        self.database.insert_guild_settings(guild_settings).await?;
+        self.cache.insert_guild_settings(guild_settings).await
+    }
+
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_role_response_config(role_response_config)
+            .await?;
+        self.cache
+            .insert_role_response_config(role_response_config)
+            .await
+    }
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_role_response_config(guild_id, role_id)
+            .await?;
+        self.cache
+            .delete_role_response_config(guild_id, role_id)
+            .await
+    }
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error> {
+        self.database
+            .insert_burst_detection_config(burst_detection_config)
+            .await?;
+        self.cache
+            .insert_burst_detection_config(burst_detection_config)
+            .await
+    }
+
+    async fn delete_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_burst_detection_config(guild_id)
+            .await?;
+        self.cache.delete_burst_detection_config(guild_id).await
+    }
+
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error> {
+        let content_rule_id = self.database.insert_content_rule(content_rule).await?;
+        self.cache.insert_content_rule(content_rule).await?;
+        Ok(content_rule_id)
+    }
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rule_id: i64,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_content_rule(guild_id, content_rule_id)
+            .await?;
+        self.cache
+            .delete_content_rule(guild_id, content_rule_id)
+            .await
+    }
+
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        self.database.delete_guild_configs(guild_id).await?;
+        self.cache.delete_guild_configs(guild_id).await
+    }
+
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        guild_config: &GuildConfig,
+    ) -> Result<(), Error> {
+        self.database
+            .replace_guild_configs(guild_id, guild_config)
+            .await?;
+        self.cache
+            .replace_guild_configs(guild_id, guild_config)
+            .await
+    }
+
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        self.database
+            .delete_channel_configs(guild_id, channel_id)
+            .await?;
+        self.cache
+            .delete_channel_configs(guild_id, channel_id)
+            .await
+    }
 }
 
-impl Datastore {
+impl<D> Datastore<D> {
     #[cfg(test)]
-    pub fn new(cache: cache::DatabaseCache, database: database::Database) -> Self {
+    pub fn new(cache: cache::DatabaseCache, database: D) -> Self {
         Self { cache, database }
     }
+}
 
-    pub async fn new_with_options(options: &DatastoreOptions) -> Self {
-        Self {
-            cache: cache::DatabaseCache::new(&options.cache_options),
-            database: database::Database::new(&options.database_options).await,
-        }
+impl Datastore {
+    pub async fn new_with_options(options: &DatastoreOptions) -> Result<Self, Error> {
+        let database = backend::Backend::new(&options.backend_options).await?;
+        // A PostgreSQL database can be shared by several instances of the bot, which only
+        // invalidate their own caches, so entries expire to pick up changes made by the others.
+        let cache_options = match database {
+            backend::Backend::Postgres(_) => cache::CacheOptions {
+                time_to_live: options
+                    .cache_options
+                    .time_to_live
+                    .or(Some(SHARED_CACHE_TIME_TO_LIVE)),
+                ..options.cache_options
+            },
+            backend::Backend::Sqlite(_) => options.cache_options,
+        };
+        Ok(Self {
+            cache: cache::DatabaseCache::new(&cache_options),
+            database,
+        })
     }
 }
 
@@ -145,100 +548,301 @@ impl Datastore {
 mod tests {
     use serial_test::serial;
 
-    use crate::datastore::test_utils::get_test_db;
+    use crate::datastore::{models::ExemptionMode, test_utils::test_each_backend};
 
     use super::*;
 
-    #[tokio::test]
-    #[serial]
-    async fn create_read_and_delete_message_response_config() {
-        let datastore = Datastore::new(
-            /*cache=*/ Default::default(),
-            /*database=*/ get_test_db().await,
-        );
-        let guild_id = 12345678;
-        let channel_id = 87654321;
-
-        // Create the message response configuration in db
-        let mut message_response = MessageResponseConfig {
-            guild_id: serenity::GuildId::from(guild_id),
-            channel_id: serenity::ChannelId::from(channel_id),
-            response: MessageResponse::Ban,
-        };
-        let result = datastore
-            .insert_message_response_config(&message_response)
-            .await;
-        assert_eq!(result, Ok(()));
+    test_each_backend! {
+        async fn create_read_and_delete_message_response_config(db) {
+            let datastore = Datastore::new(/*cache=*/ Default::default(), /*database=*/ db);
+            let guild_id = 12345678;
+            let channel_id = 87654321;
 
-        // Read the message response for guild and channel id
-        let result = datastore
-            .get_message_response(message_response.guild_id, message_response.channel_id)
-            .await;
-        assert_eq!(result, Ok(message_response.response));
+            // Create the message response configuration in db
+            let mut message_response = MessageResponseConfig::new(
+                serenity::GuildId::from(guild_id),
+                serenity::ChannelId::from(channel_id),
+                MessageResponse::Ban,
+            );
+            let result = datastore
+                .insert_message_response_config(&message_response)
+                .await;
+            assert_eq!(result, Ok(()));
+
+            // Read the message response for guild and channel id
+            let result = datastore
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Update the message response for guild and channel id
+            message_response.response = MessageResponse::Kick;
+            let result = datastore
+                .insert_message_response_config(&message_response)
+                .await;
+            assert_eq!(result, Ok(()));
+
+            // Read the updated message response for guild and channel id
+            let result = datastore
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(result, Ok(message_response.clone()));
+
+            // Delete the message response config
+            let result = datastore
+                .delete_message_response_config(
+                    message_response.guild_id,
+                    message_response.channel_id,
+                )
+                .await;
+            assert_eq!(result, Ok(()));
+
+            // Message response config should be deleted, and a placeholder "Nothing" config is
+            // returned since the channel isn't a honeypot anymore
+            let result = datastore
+                .get_message_response_config(message_response.guild_id, message_response.channel_id)
+                .await;
+            assert_eq!(
+                result.map(|config| config.response),
+                Ok(MessageResponse::Nothing)
+            );
+
+            // Clean up rows:
+            datastore
+                .delete_message_response_config(
+                    serenity::GuildId::from(guild_id),
+                    serenity::ChannelId::from(channel_id),
+                )
+                .await
+                .unwrap();
+        }
+    }
 
-        // Update the message response for guild and channel id
-        message_response.response = MessageResponse::Kick;
-        let result = datastore
-            .insert_message_response_config(&message_response)
-            .await;
-        assert_eq!(result, Ok(()));
+    test_each_backend! {
+        async fn read_changes_from_other_instances(db, other_db, restarted_db) {
+            let time_to_live = std::time::Duration::from_millis(200);
+            let datastore = Datastore::new(
+                /* cache= */
+                cache::DatabaseCache::new(&cache::CacheOptions {
+                    time_to_live: Some(time_to_live),
+                    ..Default::default()
+                }),
+                /* database= */ db,
+            );
+            let other_datastore =
+                Datastore::new(/* cache= */ Default::default(), /* database= */ other_db);
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let channel_id = serenity::ChannelId::new(87654321);
+
+            // The channel isn't a honeypot yet, which is cached
+            let result = datastore.get_message_response_config(guild_id, channel_id).await;
+            assert_eq!(
+                result.map(|config| config.response),
+                Ok(MessageResponse::Nothing)
+            );
+
+            // Another instance starts listening to the channel, which is seen once the cached
+            // placeholder expires
+            let message_response =
+                MessageResponseConfig::new(guild_id, channel_id, MessageResponse::Ban);
+            other_datastore
+                .insert_message_response_config(&message_response)
+                .await
+                .unwrap();
+            tokio::time::sleep(time_to_live * 2).await;
+            let result = datastore.get_message_response_config(guild_id, channel_id).await;
+            assert_eq!(result, Ok(message_response));
+
+            // A restarted instance reads the config from the database instead of a placeholder
+            let restarted_datastore =
+                Datastore::new(/* cache= */ Default::default(), /* database= */ restarted_db);
+            let result = restarted_datastore
+                .get_message_response_config(guild_id, channel_id)
+                .await;
+            assert_eq!(result.map(|config| config.response), Ok(MessageResponse::Ban));
+
+            // Clean up rows:
+            other_datastore
+                .delete_guild_configs(guild_id)
+                .await
+                .unwrap();
+        }
+    }
 
-        // Read the updated message response for guild and channel id
-        let result = datastore
-            .get_message_response(message_response.guild_id, message_response.channel_id)
-            .await;
-        assert_eq!(result, Ok(message_response.response));
+    test_each_backend! {
+        async fn replace_guild_configs(db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let old_channel_id = serenity::ChannelId::new(11111111);
+            let new_channel_id = serenity::ChannelId::new(22222222);
+
+            // Cache the guild's current configs
+            datastore
+                .insert_message_response_config(&MessageResponseConfig::new(
+                    guild_id,
+                    old_channel_id,
+                    MessageResponse::Ban,
+                ))
+                .await
+                .unwrap();
+            datastore
+                .insert_logging_channel(guild_id, old_channel_id)
+                .await
+                .unwrap();
+            assert_eq!(datastore.get_content_rules(guild_id).await, Ok(vec![]));
+
+            let guild_config = GuildConfig::parse(&format!(
+                r#"{{
+                    "version": {},
+                    "honeypot_channels": [{{"channel_id": "{new_channel_id}", "response": "kick"}}],
+                    "exemptions": {{"users": ["33333333"]}},
+                    "content_rules": [
+                        {{"kind": "keyword", "pattern": "airdrop", "response": "ban"}}
+                    ]
+                }}"#,
+                crate::guild_config::SCHEMA_VERSION
+            ))
+            .unwrap();
+            let result = datastore.replace_guild_configs(guild_id, &guild_config).await;
+            assert_eq!(result, Ok(()));
+
+            // The old configs are gone, from the cache too, and the document's are read back
+            let result = datastore
+                .get_message_response_config(guild_id, old_channel_id)
+                .await;
+            assert_eq!(
+                result.map(|config| config.response),
+                Ok(MessageResponse::Nothing)
+            );
+            let result = datastore
+                .get_message_response_config(guild_id, new_channel_id)
+                .await;
+            assert_eq!(
+                result.map(|config| config.response),
+                Ok(MessageResponse::Kick)
+            );
+            let result = datastore.get_logging_channel(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = datastore.get_exemptions(guild_id).await;
+            assert_eq!(
+                result,
+                Ok(vec![Exemption::User(serenity::UserId::new(33333333))])
+            );
+            let result = datastore.get_content_rules(guild_id).await;
+            assert_eq!(
+                result.map(|content_rules| content_rules.len()),
+                Ok(1)
+            );
+
+            // Clean up rows:
+            datastore.delete_guild_configs(guild_id).await.unwrap();
+        }
+    }
 
-        // Delete the message response config
-        let result = datastore
-            .delete_message_response_config(message_response.guild_id, message_response.channel_id)
-            .await;
-        assert_eq!(result, Ok(()));
+    test_each_backend! {
+        async fn create_read_and_delete_logging_channel(db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
 
-        // Message response config should be deleted, but a dummy "Nothing" config should be
-        // returned from the cache
-        let result = datastore
-            .get_message_response(message_response.guild_id, message_response.channel_id)
-            .await;
-        assert_eq!(result, Ok(MessageResponse::Nothing));
+            let guild_id = serenity::GuildId::new(12345678);
+            let channel_id = serenity::ChannelId::new(87654321);
+            let result = datastore.insert_logging_channel(guild_id, channel_id).await;
+            assert_eq!(result, Ok(()));
 
-        // Clean up rows:
-        datastore
-            .delete_message_response_config(
-                serenity::GuildId::from(guild_id),
-                serenity::ChannelId::from(channel_id),
-            )
-            .await
-            .unwrap();
-    }
+            let result = datastore.get_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(channel_id));
+
+            let channel_id = serenity::ChannelId::new(1234567);
+            let result = datastore.insert_logging_channel(guild_id, channel_id).await;
+            assert_eq!(result, Ok(()));
+
+            let result = datastore.get_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(channel_id));
 
-    #[tokio::test]
-    #[serial]
-    async fn create_read_and_delete_logging_channel() {
-        let datastore = Datastore::new(
-            /* cache= */ Default::default(),
-            /* database= */ get_test_db().await,
-        );
+            let result = datastore.delete_logging_channel(guild_id).await;
+            assert_eq!(result, Ok(()));
 
-        let guild_id = serenity::GuildId::new(12345678);
-        let channel_id = serenity::ChannelId::new(87654321);
-        let result = datastore.insert_logging_channel(guild_id, channel_id).await;
-        assert_eq!(result, Ok(()));
+            let result = datastore.get_logging_channel(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
+    }
 
-        let result = datastore.get_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(channel_id));
+    test_each_backend! {
+        async fn create_read_and_delete_exemptions(db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
+
+            let guild_id = serenity::GuildId::new(12345678);
+            let user = Exemption::User(serenity::UserId::new(87654321));
+            let role = Exemption::Role(serenity::RoleId::new(87654321));
+
+            // Cache the guild's (empty) exemptions, then make sure inserts aren't hidden by the
+            // cache
+            assert_eq!(datastore.get_exemptions(guild_id).await, Ok(vec![]));
+            assert_eq!(datastore.insert_exemption(guild_id, user).await, Ok(()));
+            assert_eq!(datastore.get_exemptions(guild_id).await, Ok(vec![user]));
+            assert_eq!(datastore.insert_exemption(guild_id, role).await, Ok(()));
+            assert_eq!(datastore.delete_exemption(guild_id, user).await, Ok(()));
+            assert_eq!(datastore.get_exemptions(guild_id).await, Ok(vec![role]));
+
+            // Clean up rows:
+            datastore.delete_exemption(guild_id, role).await.unwrap();
+        }
+    }
 
-        let channel_id = serenity::ChannelId::new(01234567);
-        let result = datastore.insert_logging_channel(guild_id, channel_id).await;
-        assert_eq!(result, Ok(()));
+    test_each_backend! {
+        async fn read_and_update_guild_settings(db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
+
+            // Guilds start out with the default settings
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let mut guild_settings = GuildSettings::new(guild_id);
+            let result = datastore.get_guild_settings(guild_id).await;
+            assert_eq!(result, Ok(guild_settings.clone()));
+
+            guild_settings.exemption_mode = ExemptionMode::Ignore;
+            guild_settings.exempt_moderators = true;
+            guild_settings.dry_run = true;
+            let result = datastore.insert_guild_settings(&guild_settings).await;
+            assert_eq!(result, Ok(()));
+
+            let result = datastore.get_guild_settings(guild_id).await;
+            assert_eq!(result, Ok(guild_settings));
+        }
+    }
 
-        let result = datastore.get_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(channel_id));
+    test_each_backend! {
+        async fn create_read_and_delete_burst_detection_config(db) {
+            let datastore = Datastore::new(/* cache= */ Default::default(), /* database= */ db);
 
-        let result = datastore.delete_logging_channel(guild_id).await;
-        assert_eq!(result, Ok(()));
+            // Guilds without burst detection are remembered by the cache
+            let guild_id = serenity::GuildId::new(12345678);
+            let result = datastore.get_burst_detection_config(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let result = datastore.get_burst_detection_config(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
 
-        let result = datastore.get_logging_channel(guild_id).await;
-        assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+            let burst_detection_config = BurstDetectionConfig {
+                guild_id,
+                channel_count: 3,
+                window: std::time::Duration::from_secs(60),
+                response: MessageResponse::Ban,
+                timeout_duration: None,
+                quarantine_role_id: None,
+                delete_messages: true,
+            };
+            let result = datastore
+                .insert_burst_detection_config(&burst_detection_config)
+                .await;
+            assert_eq!(result, Ok(()));
+            let result = datastore.get_burst_detection_config(guild_id).await;
+            assert_eq!(result, Ok(burst_detection_config));
+
+            let result = datastore.delete_burst_detection_config(guild_id).await;
+            assert_eq!(result, Ok(()));
+            let result = datastore.get_burst_detection_config(guild_id).await;
+            assert_eq!(result, Err(Error::DatabaseEntryNotFound));
+        }
     }
 }
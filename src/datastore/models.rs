@@ -1,11 +1,18 @@
+use std::time::Duration;
+
 use poise::serenity_prelude::{self as serenity};
+use serde::{Deserialize, Serialize};
 
 const BAN: isize = 0;
 const KICK: isize = 1;
 const RESPOND: isize = 2;
 const NOTHING: isize = 3;
+const TIMEOUT: isize = 4;
+const QUARANTINE: isize = 5;
+const SOFT_BAN: isize = 6;
 
-#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MessageResponse {
     #[name = "ban"]
     Ban = BAN,
@@ -15,6 +22,12 @@ pub enum MessageResponse {
     Respond = RESPOND,
     #[name = "nothing"]
     Nothing = NOTHING,
+    #[name = "timeout"]
+    Timeout = TIMEOUT,
+    #[name = "quarantine"]
+    Quarantine = QUARANTINE,
+    #[name = "soft ban"]
+    SoftBan = SOFT_BAN,
 }
 
 impl From<i64> for MessageResponse {
@@ -24,13 +37,677 @@ impl From<i64> for MessageResponse {
             KICK => MessageResponse::Kick,
             RESPOND => MessageResponse::Respond,
             NOTHING => MessageResponse::Nothing,
+            TIMEOUT => MessageResponse::Timeout,
+            QUARANTINE => MessageResponse::Quarantine,
+            SOFT_BAN => MessageResponse::SoftBan,
             _ => panic!("invalid message response"),
         }
     }
 }
 
+impl MessageResponse {
+    /// What happens to the poster, e.g. "you want to be {past_tense}" or "user was {past_tense}".
+    pub fn past_tense(&self) -> &'static str {
+        match self {
+            MessageResponse::Ban => "banned",
+            MessageResponse::Kick => "kicked",
+            MessageResponse::Respond => "warned",
+            MessageResponse::Nothing => "ignored",
+            MessageResponse::Timeout => "timed out",
+            MessageResponse::Quarantine => "quarantined",
+            MessageResponse::SoftBan => "soft banned",
+        }
+    }
+}
+
+const MESSAGE_TRIGGER: isize = 0;
+const REACTION_TRIGGER: isize = 1;
+const THREAD_TRIGGER: isize = 2;
+const ROLE_TRIGGER: isize = 3;
+const VOICE_TRIGGER: isize = 4;
+const BURST_TRIGGER: isize = 5;
+const CONTENT_RULE_TRIGGER: isize = 6;
+
+/// The kinds of activity in a honeypot channel that can trigger a response.
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKind {
+    #[name = "message"]
+    Message = MESSAGE_TRIGGER,
+    #[name = "reaction"]
+    Reaction = REACTION_TRIGGER,
+    /// A thread, or a post in a forum channel
+    #[name = "thread"]
+    Thread = THREAD_TRIGGER,
+    /// A member giving themselves a honeypot role, configured with `/listen_role`
+    #[name = "role"]
+    Role = ROLE_TRIGGER,
+    /// Joining a voice or stage channel
+    #[name = "voice"]
+    Voice = VOICE_TRIGGER,
+    /// The same message posted in several channels, configured with `/burst_detection`
+    #[name = "burst"]
+    Burst = BURST_TRIGGER,
+    /// A message in any channel matching a content rule, configured with `/rule`
+    #[name = "rule"]
+    ContentRule = CONTENT_RULE_TRIGGER,
+}
+
+impl From<i64> for TriggerKind {
+    fn from(value: i64) -> Self {
+        match value as isize {
+            MESSAGE_TRIGGER => TriggerKind::Message,
+            REACTION_TRIGGER => TriggerKind::Reaction,
+            THREAD_TRIGGER => TriggerKind::Thread,
+            ROLE_TRIGGER => TriggerKind::Role,
+            VOICE_TRIGGER => TriggerKind::Voice,
+            BURST_TRIGGER => TriggerKind::Burst,
+            CONTENT_RULE_TRIGGER => TriggerKind::ContentRule,
+            _ => panic!("invalid trigger kind"),
+        }
+    }
+}
+
+impl TriggerKind {
+    /// The trigger kinds that can be chosen for honeypot channels.
+    pub const CHANNEL: [TriggerKind; 4] = [
+        TriggerKind::Message,
+        TriggerKind::Reaction,
+        TriggerKind::Thread,
+        TriggerKind::Voice,
+    ];
+
+    /// Packs a set of trigger kinds into a bitmask for storage.
+    pub fn to_mask(trigger_kinds: &[TriggerKind]) -> i64 {
+        trigger_kinds
+            .iter()
+            .fold(0, |mask, trigger_kind| mask | 1 << *trigger_kind as i64)
+    }
+
+    pub fn from_mask(mask: i64) -> Vec<TriggerKind> {
+        TriggerKind::CHANNEL
+            .into_iter()
+            .filter(|trigger_kind| mask & 1 << *trigger_kind as i64 != 0)
+            .collect()
+    }
+}
+
+const REPLY_TEMPLATE: isize = 0;
+const WARNING_TEMPLATE: isize = 1;
+const LOG_TEMPLATE: isize = 2;
+
+/// The messages the bot sends that can be customized with `/message_template`.
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateKind {
+    /// Reply to messages in `MessageResponse::Respond` honeypot channels
+    #[name = "reply"]
+    Reply = REPLY_TEMPLATE,
+    /// Banner posted in a channel when `/listen` is used
+    #[name = "warning"]
+    Warning = WARNING_TEMPLATE,
+    /// Line posted in the logging channel when action is taken
+    #[name = "log"]
+    Log = LOG_TEMPLATE,
+}
+
+impl From<i64> for TemplateKind {
+    fn from(value: i64) -> Self {
+        match value as isize {
+            REPLY_TEMPLATE => TemplateKind::Reply,
+            WARNING_TEMPLATE => TemplateKind::Warning,
+            LOG_TEMPLATE => TemplateKind::Log,
+            _ => panic!("invalid template kind"),
+        }
+    }
+}
+
+/// A custom template for a guild, or for a single channel when `channel_id` is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageTemplate {
+    pub guild_id: serenity::GuildId,
+    pub channel_id: Option<serenity::ChannelId>,
+    pub kind: TemplateKind,
+    pub template: String,
+}
+
+/// Used when a `Timeout` response was configured without a duration.
+pub const DEFAULT_TIMEOUT_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// How many days of messages bans delete unless configured otherwise. Burst detection and content
+/// rules always use this.
+pub const DEFAULT_DELETE_MESSAGE_DAYS: u8 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct MessageResponseConfig {
     pub guild_id: serenity::GuildId,
     pub channel_id: serenity::ChannelId,
     pub response: MessageResponse,
+    /// Which kinds of activity in the channel trigger the response.
+    pub trigger_kinds: Vec<TriggerKind>,
+    /// How long the poster is timed out for. Only used by `MessageResponse::Timeout`.
+    pub timeout_duration: Option<Duration>,
+    /// Role that replaces the poster's roles. Only used by `MessageResponse::Quarantine`.
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    /// Whether the message that triggered the honeypot is deleted.
+    pub delete_message: bool,
+    /// How far back the poster's messages are purged from every text channel and active
+    /// thread in the guild.
+    pub purge_window: Option<Duration>,
+    /// How many days of the poster's messages are deleted by `MessageResponse::Ban` and
+    /// `MessageResponse::SoftBan` (0 to 7).
+    pub delete_message_days: u8,
+    /// Audit log reason template. Supports the `{user}` and `{channel}` placeholders.
+    pub reason: Option<String>,
+    /// Posters whose accounts are younger than this get `new_account_response` instead.
+    pub new_account_age: Option<Duration>,
+    pub new_account_response: Option<MessageResponse>,
+    /// Posters that joined the guild longer ago than this get `established_member_response`
+    /// instead, unless the new account rule matched.
+    pub established_member_age: Option<Duration>,
+    pub established_member_response: Option<MessageResponse>,
+    /// Only log what would have been done to posters, see `GuildSettings::dry_run`.
+    pub dry_run: bool,
+}
+
+impl MessageResponseConfig {
+    pub fn new(
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+        response: MessageResponse,
+    ) -> Self {
+        Self {
+            guild_id,
+            channel_id,
+            response,
+            trigger_kinds: vec![TriggerKind::Message],
+            timeout_duration: None,
+            quarantine_role_id: None,
+            delete_message: false,
+            purge_window: None,
+            delete_message_days: DEFAULT_DELETE_MESSAGE_DAYS,
+            reason: None,
+            new_account_age: None,
+            new_account_response: None,
+            established_member_age: None,
+            established_member_response: None,
+            dry_run: false,
+        }
+    }
+
+    /// Picks the response for a poster from their account age and how long they've been in the
+    /// guild, returning `None` if no age rule matched and the channel's response should be used.
+    pub fn response_for_ages(
+        &self,
+        account_age: Duration,
+        member_age: Option<Duration>,
+    ) -> Option<(MessageResponse, AgeRule)> {
+        if let (Some(threshold), Some(response)) = (self.new_account_age, self.new_account_response)
+            && account_age < threshold
+        {
+            return Some((response, AgeRule::NewAccount(threshold)));
+        }
+        if let (Some(threshold), Some(response), Some(member_age)) = (
+            self.established_member_age,
+            self.established_member_response,
+            member_age,
+        ) && member_age >= threshold
+        {
+            return Some((response, AgeRule::EstablishedMember(threshold)));
+        }
+        None
+    }
+}
+
+/// An account or join age rule of a `MessageResponseConfig`, along with its threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AgeRule {
+    NewAccount(Duration),
+    EstablishedMember(Duration),
+}
+
+/// A role that triggers a honeypot response when a member gets it, e.g. by clicking every reaction
+/// role or onboarding option.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleResponseConfig {
+    pub guild_id: serenity::GuildId,
+    pub role_id: serenity::RoleId,
+    pub response: MessageResponse,
+    /// How long the member is timed out for. Only used by `MessageResponse::Timeout`.
+    pub timeout_duration: Option<Duration>,
+    /// Role that replaces the member's roles. Only used by `MessageResponse::Quarantine`.
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    /// How far back the member's messages are purged from every text channel and active
+    /// thread in the guild.
+    pub purge_window: Option<Duration>,
+    /// How many days of the member's messages are deleted by `MessageResponse::Ban` and
+    /// `MessageResponse::SoftBan` (0 to 7).
+    pub delete_message_days: u8,
+    /// Audit log reason template. Supports the `{user}` and `{channel}` placeholders, where
+    /// `{channel}` is the role's name.
+    pub reason: Option<String>,
+}
+
+impl RoleResponseConfig {
+    pub fn new(
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+        response: MessageResponse,
+    ) -> Self {
+        Self {
+            guild_id,
+            role_id,
+            response,
+            timeout_duration: None,
+            quarantine_role_id: None,
+            purge_window: None,
+            delete_message_days: DEFAULT_DELETE_MESSAGE_DAYS,
+            reason: None,
+        }
+    }
+}
+
+const REGEX_RULE: isize = 0;
+const KEYWORD_RULE: isize = 1;
+const DOMAIN_RULE: isize = 2;
+const INVITE_RULE: isize = 3;
+const MASS_MENTION_RULE: isize = 4;
+
+/// How a content rule's pattern is matched against messages.
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentRuleKind {
+    #[name = "regex"]
+    Regex = REGEX_RULE,
+    /// Comma separated words or phrases, matched ignoring case
+    #[name = "keyword"]
+    Keyword = KEYWORD_RULE,
+    /// Comma separated domains, matching links to them or their subdomains
+    #[name = "domain"]
+    Domain = DOMAIN_RULE,
+    /// Discord invite links, the pattern is unused
+    #[name = "invite"]
+    Invite = INVITE_RULE,
+    /// At least the pattern's number of user, role, `@everyone` or `@here` mentions
+    #[name = "mass mention"]
+    MassMention = MASS_MENTION_RULE,
+}
+
+impl From<i64> for ContentRuleKind {
+    fn from(value: i64) -> Self {
+        match value as isize {
+            REGEX_RULE => ContentRuleKind::Regex,
+            KEYWORD_RULE => ContentRuleKind::Keyword,
+            DOMAIN_RULE => ContentRuleKind::Domain,
+            INVITE_RULE => ContentRuleKind::Invite,
+            MASS_MENTION_RULE => ContentRuleKind::MassMention,
+            _ => panic!("invalid content rule kind"),
+        }
+    }
+}
+
+/// A pattern that triggers a response when a message matching it is posted in any channel of the
+/// guild, not just honeypot channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentRule {
+    /// Assigned by the database, and never reused so compiled patterns can be cached by it
+    pub id: i64,
+    pub guild_id: serenity::GuildId,
+    pub kind: ContentRuleKind,
+    pub pattern: String,
+    pub response: MessageResponse,
+    /// How long the poster is timed out for. Only used by `MessageResponse::Timeout`.
+    pub timeout_duration: Option<Duration>,
+    /// Role that replaces the poster's roles. Only used by `MessageResponse::Quarantine`.
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    /// Whether the matching message is deleted.
+    pub delete_message: bool,
+    /// Audit log reason template. Supports the `{user}` and `{channel}` placeholders.
+    pub reason: Option<String>,
+}
+
+/// Acts on users that post the same message in `channel_count` different channels within
+/// `window`, which spam bots tend to do even when they avoid the honeypot channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BurstDetectionConfig {
+    pub guild_id: serenity::GuildId,
+    pub channel_count: u32,
+    pub window: Duration,
+    pub response: MessageResponse,
+    /// How long the user is timed out for. Only used by `MessageResponse::Timeout`.
+    pub timeout_duration: Option<Duration>,
+    /// Role that replaces the user's roles. Only used by `MessageResponse::Quarantine`.
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    /// Whether the user's messages from the burst are deleted.
+    pub delete_messages: bool,
+}
+
+/// How the bot acts on a user that triggered a honeypot, taken from a channel or role honeypot's
+/// configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoneypotResponse {
+    pub response: MessageResponse,
+    pub timeout_duration: Option<Duration>,
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    pub delete_trigger: bool,
+    pub purge_window: Option<Duration>,
+    pub delete_message_days: u8,
+    pub reason: Option<String>,
+    pub dry_run: bool,
+}
+
+impl From<&MessageResponseConfig> for HoneypotResponse {
+    fn from(config: &MessageResponseConfig) -> Self {
+        Self {
+            response: config.response,
+            timeout_duration: config.timeout_duration,
+            quarantine_role_id: config.quarantine_role_id,
+            delete_trigger: config.delete_message,
+            purge_window: config.purge_window,
+            delete_message_days: config.delete_message_days,
+            reason: config.reason.clone(),
+            dry_run: config.dry_run,
+        }
+    }
+}
+
+impl From<&BurstDetectionConfig> for HoneypotResponse {
+    fn from(config: &BurstDetectionConfig) -> Self {
+        Self {
+            response: config.response,
+            timeout_duration: config.timeout_duration,
+            quarantine_role_id: config.quarantine_role_id,
+            delete_trigger: config.delete_messages,
+            purge_window: config.delete_messages.then_some(config.window),
+            delete_message_days: DEFAULT_DELETE_MESSAGE_DAYS,
+            reason: Some(format!(
+                "posted the same message in {} channels",
+                config.channel_count
+            )),
+            dry_run: false,
+        }
+    }
+}
+
+impl From<&ContentRule> for HoneypotResponse {
+    fn from(rule: &ContentRule) -> Self {
+        Self {
+            response: rule.response,
+            timeout_duration: rule.timeout_duration,
+            quarantine_role_id: rule.quarantine_role_id,
+            delete_trigger: rule.delete_message,
+            purge_window: None,
+            delete_message_days: DEFAULT_DELETE_MESSAGE_DAYS,
+            reason: Some(
+                rule.reason
+                    .clone()
+                    .unwrap_or(format!("matched content rule #{}", rule.id)),
+            ),
+            dry_run: false,
+        }
+    }
+}
+
+impl From<&RoleResponseConfig> for HoneypotResponse {
+    fn from(config: &RoleResponseConfig) -> Self {
+        Self {
+            response: config.response,
+            timeout_duration: config.timeout_duration,
+            quarantine_role_id: config.quarantine_role_id,
+            delete_trigger: false,
+            purge_window: config.purge_window,
+            delete_message_days: config.delete_message_days,
+            reason: config.reason.clone(),
+            dry_run: false,
+        }
+    }
+}
+
+/// Picks the response to a honeypot trigger from the number of times the user has triggered a
+/// honeypot in the guild, instead of using the channel's response.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EscalationPolicy {
+    pub guild_id: serenity::GuildId,
+    /// Response for the 1st, 2nd, ... offense. The last response is used for any further offenses.
+    pub ladder: Vec<MessageResponse>,
+    /// How long an offense counts towards the ladder.
+    pub decay_window: Duration,
+}
+
+impl EscalationPolicy {
+    /// Returns `None` if the ladder is empty.
+    pub fn response_for_offense(&self, offense_count: u32) -> Option<MessageResponse> {
+        let last = self.ladder.len().checked_sub(1)?;
+        let index = (offense_count.max(1) as usize - 1).min(last);
+        Some(self.ladder[index])
+    }
+}
+
+/// A user or role that only gets warned when they trigger a honeypot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exemption {
+    User(serenity::UserId),
+    Role(serenity::RoleId),
+}
+
+const EXEMPTION_MODE_WARN: isize = 0;
+const EXEMPTION_MODE_IGNORE: isize = 1;
+
+/// What happens when an exempt user triggers a honeypot.
+#[derive(Debug, Clone, Copy, PartialEq, poise::ChoiceParameter, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExemptionMode {
+    #[name = "warn"]
+    Warn = EXEMPTION_MODE_WARN,
+    #[name = "ignore"]
+    Ignore = EXEMPTION_MODE_IGNORE,
+}
+
+impl From<i64> for ExemptionMode {
+    fn from(value: i64) -> Self {
+        match value as isize {
+            EXEMPTION_MODE_WARN => ExemptionMode::Warn,
+            EXEMPTION_MODE_IGNORE => ExemptionMode::Ignore,
+            _ => panic!("invalid exemption mode"),
+        }
+    }
+}
+
+/// Settings that apply to every honeypot in a guild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuildSettings {
+    pub guild_id: serenity::GuildId,
+    pub exemption_mode: ExemptionMode,
+    /// Whether members with the Administrator or Manage Messages permission are exempt
+    pub exempt_moderators: bool,
+    /// Whether every honeypot in the guild only logs and records what it would have done,
+    /// without touching the user or their messages
+    pub dry_run: bool,
+}
+
+impl GuildSettings {
+    pub fn new(guild_id: serenity::GuildId) -> Self {
+        Self {
+            guild_id,
+            exemption_mode: ExemptionMode::Warn,
+            exempt_moderators: false,
+            dry_run: false,
+        }
+    }
+}
+
+/// A member that was quarantined, along with the roles that were taken from them so they can be
+/// restored with `/release`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuarantinedMember {
+    pub guild_id: serenity::GuildId,
+    pub user_id: serenity::UserId,
+    pub quarantine_role_id: serenity::RoleId,
+    pub removed_role_ids: Vec<serenity::RoleId>,
+}
+
+/// A record of the bot taking action on a user after they triggered a honeypot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    /// Assigned by the database, ignored when inserting
+    pub id: i64,
+    pub guild_id: serenity::GuildId,
+    /// The honeypot channel, or `None` for role triggers
+    pub channel_id: Option<serenity::ChannelId>,
+    pub user_id: serenity::UserId,
+    pub action: MessageResponse,
+    pub trigger_kind: TriggerKind,
+    /// Start of the message, thread name, reaction or role name that triggered the honeypot
+    pub content: String,
+    pub created_at: serenity::Timestamp,
+    /// Extra detail about how the action went
+    pub outcome: Option<String>,
+    /// Why the action failed, if it did
+    pub error: Option<String>,
+    /// Set by a moderator when the user shouldn't have been actioned
+    pub false_positive: bool,
+    /// Whether the action was only logged, not taken
+    pub dry_run: bool,
+}
+
+/// How often a honeypot channel has been triggered, or role honeypots if `channel_id` is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncidentStats {
+    pub channel_id: Option<serenity::ChannelId>,
+    pub count: u32,
+    pub last_created_at: serenity::Timestamp,
+}
+
+/// Which incidents of a guild to list, newest first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IncidentFilter {
+    pub guild_id: serenity::GuildId,
+    pub user_id: Option<serenity::UserId>,
+    pub channel_id: Option<serenity::ChannelId>,
+    pub action: Option<MessageResponse>,
+    /// Whether dry run incidents are listed too
+    pub include_dry_runs: bool,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escalation_policy(ladder: Vec<MessageResponse>) -> EscalationPolicy {
+        EscalationPolicy {
+            guild_id: serenity::GuildId::new(1),
+            ladder,
+            decay_window: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn trigger_kind_mask_round_trip() {
+        // Every subset of the channel trigger kinds:
+        for subset in 0..1 << TriggerKind::CHANNEL.len() {
+            let trigger_kinds: Vec<TriggerKind> = TriggerKind::CHANNEL
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| subset & 1 << i != 0)
+                .map(|(_, trigger_kind)| trigger_kind)
+                .collect();
+            let mask = TriggerKind::to_mask(&trigger_kinds);
+            assert_eq!(TriggerKind::from_mask(mask), trigger_kinds);
+        }
+
+        assert_eq!(TriggerKind::to_mask(&[]), 0);
+        // Duplicates and order don't change the mask:
+        assert_eq!(
+            TriggerKind::to_mask(&[TriggerKind::Voice, TriggerKind::Message, TriggerKind::Voice]),
+            TriggerKind::to_mask(&[TriggerKind::Message, TriggerKind::Voice])
+        );
+        // Only channel trigger kinds are read back:
+        let mask = TriggerKind::to_mask(&[TriggerKind::Message, TriggerKind::Burst]);
+        assert_eq!(TriggerKind::from_mask(mask), vec![TriggerKind::Message]);
+    }
+
+    #[test]
+    fn response_for_ages() {
+        const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+        let mut config = MessageResponseConfig::new(
+            serenity::GuildId::new(1),
+            serenity::ChannelId::new(2),
+            MessageResponse::Timeout,
+        );
+        assert_eq!(
+            config.response_for_ages(Duration::ZERO, Some(Duration::ZERO)),
+            None
+        );
+
+        config.new_account_age = Some(7 * DAY);
+        config.new_account_response = Some(MessageResponse::Ban);
+        config.established_member_age = Some(30 * DAY);
+        config.established_member_response = Some(MessageResponse::Nothing);
+
+        let new_account = Some((MessageResponse::Ban, AgeRule::NewAccount(7 * DAY)));
+        let established_member = Some((
+            MessageResponse::Nothing,
+            AgeRule::EstablishedMember(30 * DAY),
+        ));
+        assert_eq!(config.response_for_ages(6 * DAY, None), new_account);
+        assert_eq!(config.response_for_ages(7 * DAY, None), None);
+        assert_eq!(
+            config.response_for_ages(365 * DAY, Some(30 * DAY)),
+            established_member
+        );
+        assert_eq!(config.response_for_ages(365 * DAY, Some(29 * DAY)), None);
+        // The new account rule wins if both match:
+        assert_eq!(
+            config.response_for_ages(6 * DAY, Some(30 * DAY)),
+            new_account
+        );
+
+        // A threshold without a response is ignored:
+        config.new_account_response = None;
+        config.established_member_response = None;
+        assert_eq!(
+            config.response_for_ages(Duration::ZERO, Some(365 * DAY)),
+            None
+        );
+    }
+
+    #[test]
+    fn response_for_offense() {
+        let policy = escalation_policy(vec![
+            MessageResponse::Respond,
+            MessageResponse::Timeout,
+            MessageResponse::Ban,
+        ]);
+
+        assert_eq!(
+            policy.response_for_offense(1),
+            Some(MessageResponse::Respond)
+        );
+        assert_eq!(
+            policy.response_for_offense(2),
+            Some(MessageResponse::Timeout)
+        );
+        assert_eq!(policy.response_for_offense(3), Some(MessageResponse::Ban));
+        // Offenses past the end of the ladder use the last response:
+        assert_eq!(policy.response_for_offense(4), Some(MessageResponse::Ban));
+        assert_eq!(
+            policy.response_for_offense(u32::MAX),
+            Some(MessageResponse::Ban)
+        );
+        // A count of 0 is treated as the first offense:
+        assert_eq!(
+            policy.response_for_offense(0),
+            Some(MessageResponse::Respond)
+        );
+    }
+
+    #[test]
+    fn response_for_offense_with_empty_ladder() {
+        let policy = escalation_policy(vec![]);
+
+        assert_eq!(policy.response_for_offense(0), None);
+        assert_eq!(policy.response_for_offense(1), None);
+    }
 }
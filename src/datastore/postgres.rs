@@ -0,0 +1,1425 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{self as serenity};
+use sqlx::{
+    Postgres, Row,
+    migrate::Migrator,
+    postgres::{PgArguments, PgConnection, PgPoolOptions, PgRow},
+};
+
+use crate::{
+    datastore::{
+        database::{
+            EXEMPT_ROLE, EXEMPT_USER, GUILD_CONFIG_TABLES, check_migrations, exemption_columns,
+            optional_channel_id, read_migrations,
+        },
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, ContentRuleKind, EscalationPolicy, Exemption,
+            ExemptionMode, GuildSettings, Incident, IncidentFilter, IncidentStats, MessageResponse,
+            MessageResponseConfig, MessageTemplate, QuarantinedMember, RoleResponseConfig,
+            TemplateKind, TriggerKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    guild_config::GuildConfig,
+};
+
+/// Migrations built into the binary, used unless `PostgresDatabaseOptions::migrations_path` is
+/// set. They're kept apart from the sqlite ones since the column types differ.
+static EMBEDDED_MIGRATOR: Migrator = sqlx::migrate!("migrations/postgres");
+
+/// Stores everything in PostgreSQL, so several bot replicas can share it.
+pub struct PostgresDatabase {
+    pool: sqlx::Pool<Postgres>,
+}
+
+pub struct PostgresDatabaseOptions {
+    /// `postgres://` connection URL
+    pub url: String,
+    /// Directory to read migrations from instead of the ones built into the binary
+    pub migrations_path: Option<String>,
+    /// Whether pending migrations are applied when connecting. Otherwise the database has to be
+    /// up to date already.
+    pub migrate: bool,
+}
+
+impl DatastoreReader for PostgresDatabase {
+    async fn get_message_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<MessageResponseConfig, Error> {
+        let row: Result<PgRow, sqlx::Error> = sqlx::query(concat!(
+            "SELECT guild_id, channel_id, response, timeout_duration, quarantine_role_id, ",
+            "delete_message, purge_window, delete_message_days, reason, new_account_age, ",
+            "new_account_response, established_member_age, established_member_response, ",
+            "trigger_kinds, dry_run FROM message_responses WHERE guild_id = $1 AND channel_id = $2"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(channel_id.get() as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(row) => Ok(message_response_config_from_row(&row)),
+        }
+    }
+
+    async fn get_logging_channel(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<serenity::ChannelId, Error> {
+        let response: Result<i64, sqlx::Error> =
+            sqlx::query_scalar("SELECT channel_id FROM logging_channels WHERE guild_id = $1")
+                .bind(guild_id.get() as i64)
+                .fetch_one(&self.pool)
+                .await;
+        match response {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(response) => Ok(serenity::ChannelId::new(response as u64)),
+        }
+    }
+
+    async fn get_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error> {
+        let row: Result<(i64, String), sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT quarantine_role_id, removed_role_ids FROM quarantined_members ",
+            "WHERE guild_id = $1 AND user_id = $2"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok((quarantine_role_id, removed_role_ids)) => Ok(QuarantinedMember {
+                guild_id,
+                user_id,
+                quarantine_role_id: serenity::RoleId::new(quarantine_role_id as u64),
+                removed_role_ids: removed_role_ids
+                    .split(',')
+                    .filter_map(|role_id| role_id.parse().ok())
+                    .map(serenity::RoleId::new)
+                    .collect(),
+            }),
+        }
+    }
+
+    async fn get_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<String, Error> {
+        let template: Result<String, sqlx::Error> = sqlx::query_scalar(concat!(
+            "SELECT template FROM message_templates ",
+            "WHERE guild_id = $1 AND channel_id = $2 AND kind = $3"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(optional_channel_id(channel_id))
+        .bind(kind as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match template {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(template) => Ok(template),
+        }
+    }
+
+    async fn list_message_templates(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error> {
+        let rows: Result<Vec<(i64, i64, String)>, sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT channel_id, kind, template FROM message_templates ",
+            "WHERE guild_id = $1 ORDER BY channel_id, kind"
+        ))
+        .bind(guild_id.get() as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|(channel_id, kind, template)| MessageTemplate {
+                    guild_id,
+                    channel_id: (channel_id != 0)
+                        .then(|| serenity::ChannelId::new(channel_id as u64)),
+                    kind: TemplateKind::from(kind),
+                    template,
+                })
+                .collect()),
+        }
+    }
+
+    async fn get_escalation_policy(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error> {
+        let row: Result<(String, i64), sqlx::Error> = sqlx::query_as(
+            "SELECT ladder, decay_window FROM escalation_policies WHERE guild_id = $1",
+        )
+        .bind(guild_id.get() as i64)
+        .fetch_one(&self.pool)
+        .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok((ladder, decay_window)) => Ok(EscalationPolicy {
+                guild_id,
+                ladder: ladder
+                    .split(',')
+                    .filter_map(|response| response.parse::<i64>().ok())
+                    .map(MessageResponse::from)
+                    .collect(),
+                decay_window: Duration::from_secs(decay_window as u64),
+            }),
+        }
+    }
+
+    async fn count_offenses(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        since: serenity::Timestamp,
+    ) -> Result<u32, Error> {
+        let count: Result<i64, sqlx::Error> = sqlx::query_scalar(concat!(
+            "SELECT COUNT(*) FROM offenses ",
+            "WHERE guild_id = $1 AND user_id = $2 AND created_at >= $3"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(since.unix_timestamp())
+        .fetch_one(&self.pool)
+        .await;
+        match count {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(count) => Ok(count as u32),
+        }
+    }
+
+    async fn list_incidents(&self, filter: &IncidentFilter) -> Result<Vec<Incident>, Error> {
+        let rows: Result<Vec<PgRow>, sqlx::Error> = sqlx::query(concat!(
+            "SELECT * FROM incidents WHERE guild_id = $1 ",
+            "AND ($2 IS NULL OR user_id = $2) ",
+            "AND ($3 IS NULL OR channel_id = $3) ",
+            "AND ($4 IS NULL OR action = $4) ",
+            "AND ($5 OR NOT dry_run) ",
+            "ORDER BY created_at DESC, id DESC LIMIT $6 OFFSET $7"
+        ))
+        .bind(filter.guild_id.get() as i64)
+        .bind(filter.user_id.map(|user_id| user_id.get() as i64))
+        .bind(filter.channel_id.map(|channel_id| channel_id.get() as i64))
+        .bind(filter.action.map(|action| action as i64))
+        .bind(filter.include_dry_runs)
+        .bind(filter.limit as i64)
+        .bind(filter.offset as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(incident_from_row).collect()),
+        }
+    }
+
+    async fn get_incident_stats(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error> {
+        let rows: Result<Vec<(i64, i64, i64)>, sqlx::Error> = sqlx::query_as(concat!(
+            "SELECT channel_id, COUNT(*), MAX(created_at) FROM incidents ",
+            "WHERE guild_id = $1 GROUP BY channel_id ORDER BY channel_id"
+        ))
+        .bind(guild_id.get() as i64)
+        .fetch_all(&self.pool)
+        .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .map(|(channel_id, count, last_created_at)| IncidentStats {
+                    channel_id: match channel_id {
+                        0 => None,
+                        channel_id => Some(serenity::ChannelId::new(channel_id as u64)),
+                    },
+                    count: count as u32,
+                    last_created_at: serenity::Timestamp::from_unix_timestamp(last_created_at)
+                        .unwrap_or_default(),
+                })
+                .collect()),
+        }
+    }
+
+    async fn list_message_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error> {
+        let rows: Result<Vec<PgRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM message_responses WHERE guild_id = $1 ORDER BY channel_id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(message_response_config_from_row).collect()),
+        }
+    }
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error> {
+        let rows: Result<Vec<(i64, i64)>, sqlx::Error> =
+            sqlx::query_as("SELECT kind, target_id FROM exemptions WHERE guild_id = $1")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows
+                .into_iter()
+                .filter_map(|(kind, target_id)| match kind {
+                    EXEMPT_USER => Some(Exemption::User(serenity::UserId::new(target_id as u64))),
+                    EXEMPT_ROLE => Some(Exemption::Role(serenity::RoleId::new(target_id as u64))),
+                    _ => None,
+                })
+                .collect()),
+        }
+    }
+
+    async fn get_guild_settings(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<GuildSettings, Error> {
+        let row: Result<PgRow, sqlx::Error> =
+            sqlx::query("SELECT * FROM guild_settings WHERE guild_id = $1")
+                .bind(guild_id.get() as i64)
+                .fetch_one(&self.pool)
+                .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(row) => Ok(GuildSettings {
+                guild_id,
+                exemption_mode: ExemptionMode::from(row.get::<i64, _>("exemption_mode")),
+                exempt_moderators: row.get("exempt_moderators"),
+                dry_run: row.get("dry_run"),
+            }),
+        }
+    }
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error> {
+        let rows: Result<Vec<PgRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM role_responses WHERE guild_id = $1 ORDER BY role_id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(role_response_config_from_row).collect()),
+        }
+    }
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error> {
+        let row: Result<PgRow, sqlx::Error> =
+            sqlx::query("SELECT * FROM burst_detection WHERE guild_id = $1")
+                .bind(guild_id.get() as i64)
+                .fetch_one(&self.pool)
+                .await;
+        match row {
+            Err(sqlx::Error::RowNotFound) => Err(Error::DatabaseEntryNotFound),
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(row) => Ok(BurstDetectionConfig {
+                guild_id,
+                channel_count: row.get::<i64, _>("channel_count") as u32,
+                window: Duration::from_secs(row.get::<i64, _>("window") as u64),
+                response: MessageResponse::from(row.get::<i64, _>("response")),
+                timeout_duration: row
+                    .get::<Option<i64>, _>("timeout_duration")
+                    .map(|secs| Duration::from_secs(secs as u64)),
+                quarantine_role_id: row
+                    .get::<Option<i64>, _>("quarantine_role_id")
+                    .map(|role_id| serenity::RoleId::new(role_id as u64)),
+                delete_messages: row.get("delete_messages"),
+            }),
+        }
+    }
+
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error> {
+        let rows: Result<Vec<PgRow>, sqlx::Error> =
+            sqlx::query("SELECT * FROM content_rules WHERE guild_id = $1 ORDER BY id")
+                .bind(guild_id.get() as i64)
+                .fetch_all(&self.pool)
+                .await;
+        match rows {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(rows) => Ok(rows.iter().map(content_rule_from_row).collect()),
+        }
+    }
+}
+
+impl DatastoreWriter for PostgresDatabase {
+    async fn delete_message_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("DELETE FROM message_responses WHERE guild_id = $1 AND channel_id = $2")
+                .bind(guild_id.get() as i64)
+                .bind(channel_id.get() as i64)
+                .execute(&self.pool)
+                .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
+
+    async fn insert_message_response_config(
+        &self,
+        message_response_config: &MessageResponseConfig,
+    ) -> Result<(), Error> {
+        let result = insert_message_response_config_query(message_response_config)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_logging_channel(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM logging_channels WHERE guild_id = $1")
+            .bind(guild_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
+
+    async fn insert_logging_channel(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        let result = insert_logging_channel_query(guild_id, channel_id)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_quarantined_member(
+        &self,
+        quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error> {
+        let removed_role_ids = quarantined_member
+            .removed_role_ids
+            .iter()
+            .map(|role_id| role_id.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let result = sqlx::query(concat!(
+            "INSERT INTO quarantined_members ",
+            "(guild_id, user_id, quarantine_role_id, removed_role_ids) VALUES ($1, $2, $3, $4) ",
+            "ON CONFLICT(guild_id, user_id) DO UPDATE SET ",
+            "quarantine_role_id = $3, removed_role_ids = $4"
+        ))
+        .bind(quarantined_member.guild_id.get() as i64)
+        .bind(quarantined_member.user_id.get() as i64)
+        .bind(quarantined_member.quarantine_role_id.get() as i64)
+        .bind(removed_role_ids)
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("DELETE FROM quarantined_members WHERE guild_id = $1 AND user_id = $2")
+                .bind(guild_id.get() as i64)
+                .bind(user_id.get() as i64)
+                .execute(&self.pool)
+                .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
+
+    async fn insert_message_template(
+        &self,
+        message_template: &MessageTemplate,
+    ) -> Result<(), Error> {
+        let result = insert_message_template_query(message_template)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<(), Error> {
+        let result = sqlx::query(
+            "DELETE FROM message_templates WHERE guild_id = $1 AND channel_id = $2 AND kind = $3",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(optional_channel_id(channel_id))
+        .bind(kind as i64)
+        .execute(&self.pool)
+        .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
+
+    async fn insert_escalation_policy(
+        &self,
+        escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error> {
+        let result = insert_escalation_policy_query(escalation_policy)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_escalation_policy(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM escalation_policies WHERE guild_id = $1")
+            .bind(guild_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        result.map(|_| ()).or(Err(Error::DatabaseEntryNotFound))
+    }
+
+    async fn insert_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        let result =
+            sqlx::query("INSERT INTO offenses (guild_id, user_id, created_at) VALUES ($1, $2, $3)")
+                .bind(guild_id.get() as i64)
+                .bind(user_id.get() as i64)
+                .bind(created_at.unix_timestamp())
+                .execute(&self.pool)
+                .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error> {
+        // Offenses have no ID, and the same user can offend twice in the same second.
+        let result = sqlx::query(concat!(
+            "DELETE FROM offenses WHERE ctid IN ",
+            "(SELECT ctid FROM offenses WHERE guild_id = $1 AND user_id = $2 AND created_at = $3 LIMIT 1)"
+        ))
+        .bind(guild_id.get() as i64)
+        .bind(user_id.get() as i64)
+        .bind(created_at.unix_timestamp())
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_incident(&self, incident: &Incident) -> Result<i64, Error> {
+        let id: Result<i64, sqlx::Error> = sqlx::query_scalar(concat!(
+            "INSERT INTO incidents ",
+            "(guild_id, channel_id, user_id, action, content, created_at, outcome, error, ",
+            "false_positive, trigger_kind, dry_run) ",
+            "VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) RETURNING id"
+        ))
+        .bind(incident.guild_id.get() as i64)
+        .bind(optional_channel_id(incident.channel_id))
+        .bind(incident.user_id.get() as i64)
+        .bind(incident.action as i64)
+        .bind(&incident.content)
+        .bind(incident.created_at.unix_timestamp())
+        .bind(&incident.outcome)
+        .bind(&incident.error)
+        .bind(incident.false_positive)
+        .bind(incident.trigger_kind as i64)
+        .bind(incident.dry_run)
+        .fetch_one(&self.pool)
+        .await;
+        id.map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))
+    }
+
+    async fn mark_incident_false_positive(
+        &self,
+        guild_id: serenity::GuildId,
+        incident_id: i64,
+    ) -> Result<(), Error> {
+        let result = sqlx::query(
+            "UPDATE incidents SET false_positive = TRUE WHERE guild_id = $1 AND id = $2",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(incident_id)
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        let result = insert_exemption_query(guild_id, exemption)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error> {
+        let (kind, target_id) = exemption_columns(exemption);
+        let result = sqlx::query(
+            "DELETE FROM exemptions WHERE guild_id = $1 AND kind = $2 AND target_id = $3",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(kind)
+        .bind(target_id)
+        .execute(&self.pool)
+        .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error> {
+        let result = insert_guild_settings_query(guild_settings)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error> {
+        let result = insert_role_response_config_query(role_response_config)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM role_responses WHERE guild_id = $1 AND role_id = $2")
+            .bind(guild_id.get() as i64)
+            .bind(role_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error> {
+        let result = insert_burst_detection_config_query(burst_detection_config)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM burst_detection WHERE guild_id = $1")
+            .bind(guild_id.get() as i64)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error> {
+        let id: Result<i64, sqlx::Error> = insert_content_rule_query(content_rule)
+            .fetch_one(&self.pool)
+            .await;
+        id.map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))
+    }
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rule_id: i64,
+    ) -> Result<(), Error> {
+        let result = sqlx::query("DELETE FROM content_rules WHERE guild_id = $1 AND id = $2")
+            .bind(guild_id.get() as i64)
+            .bind(content_rule_id)
+            .execute(&self.pool)
+            .await;
+        match result {
+            Err(why) => Err(Error::DatabaseUnexpectedErr(format!("{why:?}"))),
+            Ok(result) if result.rows_affected() == 0 => Err(Error::DatabaseEntryNotFound),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error> {
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        delete_guild_config_rows(&mut transaction, guild_id)
+            .await
+            .map_err(unexpected)?;
+        transaction.commit().await.map_err(unexpected)
+    }
+
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        guild_config: &GuildConfig,
+    ) -> Result<(), Error> {
+        let models = guild_config.models(guild_id);
+        let mut queries = Vec::new();
+        if let Some(logging_channel_id) = models.logging_channel_id {
+            queries.push(insert_logging_channel_query(guild_id, logging_channel_id));
+        }
+        queries.push(insert_guild_settings_query(&models.guild_settings));
+        queries.extend(
+            models
+                .message_response_configs
+                .iter()
+                .map(insert_message_response_config_query),
+        );
+        queries.extend(
+            models
+                .role_response_configs
+                .iter()
+                .map(insert_role_response_config_query),
+        );
+        queries.extend(
+            models
+                .message_templates
+                .iter()
+                .map(insert_message_template_query),
+        );
+        queries.extend(
+            models
+                .escalation_policy
+                .iter()
+                .map(insert_escalation_policy_query),
+        );
+        queries.extend(
+            models
+                .exemptions
+                .iter()
+                .map(|exemption| insert_exemption_query(guild_id, *exemption)),
+        );
+        queries.extend(
+            models
+                .burst_detection_config
+                .iter()
+                .map(insert_burst_detection_config_query),
+        );
+
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        delete_guild_config_rows(&mut transaction, guild_id)
+            .await
+            .map_err(unexpected)?;
+        for query in queries {
+            query.execute(&mut *transaction).await.map_err(unexpected)?;
+        }
+        for query in models.content_rules.iter().map(insert_content_rule_query) {
+            query
+                .fetch_one(&mut *transaction)
+                .await
+                .map_err(unexpected)?;
+        }
+        transaction.commit().await.map_err(unexpected)
+    }
+
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error> {
+        let unexpected = |why: sqlx::Error| Error::DatabaseUnexpectedErr(format!("{why:?}"));
+        let mut transaction = self.pool.begin().await.map_err(unexpected)?;
+        for table in ["message_responses", "message_templates", "logging_channels"] {
+            sqlx::query(&format!(
+                "DELETE FROM {table} WHERE guild_id = $1 AND channel_id = $2"
+            ))
+            .bind(guild_id.get() as i64)
+            .bind(channel_id.get() as i64)
+            .execute(&mut *transaction)
+            .await
+            .map_err(unexpected)?;
+        }
+        transaction.commit().await.map_err(unexpected)
+    }
+}
+
+/// Deletes every config of the guild, leaving its incidents, offenses and quarantined members.
+async fn delete_guild_config_rows(
+    connection: &mut PgConnection,
+    guild_id: serenity::GuildId,
+) -> Result<(), sqlx::Error> {
+    for table in GUILD_CONFIG_TABLES {
+        sqlx::query(&format!("DELETE FROM {table} WHERE guild_id = $1"))
+            .bind(guild_id.get() as i64)
+            .execute(&mut *connection)
+            .await?;
+    }
+    Ok(())
+}
+
+// The queries that insert configs are built apart from running them, so they can run on the pool
+// or in a transaction.
+fn insert_logging_channel_query<'q>(
+    guild_id: serenity::GuildId,
+    channel_id: serenity::ChannelId,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO logging_channels (guild_id, channel_id) VALUES ($1, $2) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET channel_id = $2"
+    ))
+    .bind(guild_id.get() as i64)
+    .bind(channel_id.get() as i64)
+}
+
+fn insert_guild_settings_query<'q>(
+    guild_settings: &'q GuildSettings,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO guild_settings (guild_id, exemption_mode, exempt_moderators, dry_run) ",
+        "VALUES ($1, $2, $3, $4) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ",
+        "exemption_mode = $2, exempt_moderators = $3, dry_run = $4"
+    ))
+    .bind(guild_settings.guild_id.get() as i64)
+    .bind(guild_settings.exemption_mode as i64)
+    .bind(guild_settings.exempt_moderators)
+    .bind(guild_settings.dry_run)
+}
+
+fn insert_message_response_config_query<'q>(
+    message_response_config: &'q MessageResponseConfig,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO message_responses ",
+        "(guild_id, channel_id, response, timeout_duration, quarantine_role_id, ",
+        "delete_message, purge_window, delete_message_days, reason, new_account_age, ",
+        "new_account_response, established_member_age, established_member_response, ",
+        "trigger_kinds, dry_run) ",
+        "VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15) ",
+        "ON CONFLICT(guild_id, channel_id) DO UPDATE SET ",
+        "response = $3, timeout_duration = $4, quarantine_role_id = $5, ",
+        "delete_message = $6, purge_window = $7, delete_message_days = $8, reason = $9, ",
+        "new_account_age = $10, new_account_response = $11, ",
+        "established_member_age = $12, established_member_response = $13, ",
+        "trigger_kinds = $14, dry_run = $15"
+    ))
+    .bind(message_response_config.guild_id.get() as i64)
+    .bind(message_response_config.channel_id.get() as i64)
+    .bind(message_response_config.response as i64)
+    .bind(
+        message_response_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(message_response_config.delete_message)
+    .bind(
+        message_response_config
+            .purge_window
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(message_response_config.delete_message_days as i64)
+    .bind(&message_response_config.reason)
+    .bind(
+        message_response_config
+            .new_account_age
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .new_account_response
+            .map(|response| response as i64),
+    )
+    .bind(
+        message_response_config
+            .established_member_age
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        message_response_config
+            .established_member_response
+            .map(|response| response as i64),
+    )
+    .bind(TriggerKind::to_mask(&message_response_config.trigger_kinds))
+    .bind(message_response_config.dry_run)
+}
+
+fn insert_role_response_config_query<'q>(
+    role_response_config: &'q RoleResponseConfig,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO role_responses ",
+        "(guild_id, role_id, response, timeout_duration, quarantine_role_id, purge_window, ",
+        "delete_message_days, reason) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ",
+        "ON CONFLICT(guild_id, role_id) DO UPDATE SET ",
+        "response = $3, timeout_duration = $4, quarantine_role_id = $5, purge_window = $6, ",
+        "delete_message_days = $7, reason = $8"
+    ))
+    .bind(role_response_config.guild_id.get() as i64)
+    .bind(role_response_config.role_id.get() as i64)
+    .bind(role_response_config.response as i64)
+    .bind(
+        role_response_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        role_response_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(
+        role_response_config
+            .purge_window
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(role_response_config.delete_message_days as i64)
+    .bind(&role_response_config.reason)
+}
+
+fn insert_message_template_query<'q>(
+    message_template: &'q MessageTemplate,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO message_templates (guild_id, channel_id, kind, template) ",
+        "VALUES ($1, $2, $3, $4) ",
+        "ON CONFLICT(guild_id, channel_id, kind) DO UPDATE SET template = $4"
+    ))
+    .bind(message_template.guild_id.get() as i64)
+    .bind(optional_channel_id(message_template.channel_id))
+    .bind(message_template.kind as i64)
+    .bind(&message_template.template)
+}
+
+fn insert_escalation_policy_query<'q>(
+    escalation_policy: &'q EscalationPolicy,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    let ladder = escalation_policy
+        .ladder
+        .iter()
+        .map(|response| (*response as i64).to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    sqlx::query(concat!(
+        "INSERT INTO escalation_policies (guild_id, ladder, decay_window) VALUES ($1, $2, $3) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ladder = $2, decay_window = $3"
+    ))
+    .bind(escalation_policy.guild_id.get() as i64)
+    .bind(ladder)
+    .bind(escalation_policy.decay_window.as_secs() as i64)
+}
+
+fn insert_exemption_query<'q>(
+    guild_id: serenity::GuildId,
+    exemption: Exemption,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    let (kind, target_id) = exemption_columns(exemption);
+    sqlx::query(concat!(
+        "INSERT INTO exemptions (guild_id, kind, target_id) VALUES ($1, $2, $3) ",
+        "ON CONFLICT(guild_id, kind, target_id) DO NOTHING"
+    ))
+    .bind(guild_id.get() as i64)
+    .bind(kind)
+    .bind(target_id)
+}
+
+fn insert_burst_detection_config_query<'q>(
+    burst_detection_config: &'q BurstDetectionConfig,
+) -> sqlx::query::Query<'q, Postgres, PgArguments> {
+    sqlx::query(concat!(
+        "INSERT INTO burst_detection ",
+        "(guild_id, channel_count, \"window\", response, timeout_duration, ",
+        "quarantine_role_id, delete_messages) VALUES ($1, $2, $3, $4, $5, $6, $7) ",
+        "ON CONFLICT(guild_id) DO UPDATE SET ",
+        "channel_count = $2, \"window\" = $3, response = $4, timeout_duration = $5, ",
+        "quarantine_role_id = $6, delete_messages = $7"
+    ))
+    .bind(burst_detection_config.guild_id.get() as i64)
+    .bind(burst_detection_config.channel_count as i64)
+    .bind(burst_detection_config.window.as_secs() as i64)
+    .bind(burst_detection_config.response as i64)
+    .bind(
+        burst_detection_config
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        burst_detection_config
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(burst_detection_config.delete_messages)
+}
+
+fn insert_content_rule_query<'q>(
+    content_rule: &'q ContentRule,
+) -> sqlx::query::QueryScalar<'q, Postgres, i64, PgArguments> {
+    sqlx::query_scalar(concat!(
+        "INSERT INTO content_rules ",
+        "(guild_id, kind, pattern, response, timeout_duration, quarantine_role_id, ",
+        "delete_message, reason) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) RETURNING id"
+    ))
+    .bind(content_rule.guild_id.get() as i64)
+    .bind(content_rule.kind as i64)
+    .bind(&content_rule.pattern)
+    .bind(content_rule.response as i64)
+    .bind(
+        content_rule
+            .timeout_duration
+            .map(|duration| duration.as_secs() as i64),
+    )
+    .bind(
+        content_rule
+            .quarantine_role_id
+            .map(|role_id| role_id.get() as i64),
+    )
+    .bind(content_rule.delete_message)
+    .bind(&content_rule.reason)
+}
+
+fn message_response_config_from_row(row: &PgRow) -> MessageResponseConfig {
+    MessageResponseConfig {
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        channel_id: serenity::ChannelId::new(row.get::<i64, _>("channel_id") as u64),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        trigger_kinds: TriggerKind::from_mask(row.get("trigger_kinds")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        delete_message: row.get("delete_message"),
+        purge_window: row
+            .get::<Option<i64>, _>("purge_window")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        delete_message_days: row.get::<i64, _>("delete_message_days") as u8,
+        reason: row.get("reason"),
+        new_account_age: row
+            .get::<Option<i64>, _>("new_account_age")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        new_account_response: row
+            .get::<Option<i64>, _>("new_account_response")
+            .map(MessageResponse::from),
+        established_member_age: row
+            .get::<Option<i64>, _>("established_member_age")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        established_member_response: row
+            .get::<Option<i64>, _>("established_member_response")
+            .map(MessageResponse::from),
+        dry_run: row.get("dry_run"),
+    }
+}
+
+fn incident_from_row(row: &PgRow) -> Incident {
+    Incident {
+        id: row.get("id"),
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        channel_id: match row.get::<i64, _>("channel_id") {
+            0 => None,
+            channel_id => Some(serenity::ChannelId::new(channel_id as u64)),
+        },
+        user_id: serenity::UserId::new(row.get::<i64, _>("user_id") as u64),
+        action: MessageResponse::from(row.get::<i64, _>("action")),
+        trigger_kind: TriggerKind::from(row.get::<i64, _>("trigger_kind")),
+        content: row.get("content"),
+        created_at: serenity::Timestamp::from_unix_timestamp(row.get("created_at"))
+            .unwrap_or_default(),
+        outcome: row.get("outcome"),
+        error: row.get("error"),
+        false_positive: row.get("false_positive"),
+        dry_run: row.get("dry_run"),
+    }
+}
+
+fn role_response_config_from_row(row: &PgRow) -> RoleResponseConfig {
+    RoleResponseConfig {
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        role_id: serenity::RoleId::new(row.get::<i64, _>("role_id") as u64),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        purge_window: row
+            .get::<Option<i64>, _>("purge_window")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        delete_message_days: row.get::<i64, _>("delete_message_days") as u8,
+        reason: row.get("reason"),
+    }
+}
+
+fn content_rule_from_row(row: &PgRow) -> ContentRule {
+    ContentRule {
+        id: row.get("id"),
+        guild_id: serenity::GuildId::new(row.get::<i64, _>("guild_id") as u64),
+        kind: ContentRuleKind::from(row.get::<i64, _>("kind")),
+        pattern: row.get("pattern"),
+        response: MessageResponse::from(row.get::<i64, _>("response")),
+        timeout_duration: row
+            .get::<Option<i64>, _>("timeout_duration")
+            .map(|secs| Duration::from_secs(secs as u64)),
+        quarantine_role_id: row
+            .get::<Option<i64>, _>("quarantine_role_id")
+            .map(|role_id| serenity::RoleId::new(role_id as u64)),
+        delete_message: row.get("delete_message"),
+        reason: row.get("reason"),
+    }
+}
+
+impl PostgresDatabase {
+    /// Connects to the database and applies pending migrations if `options.migrate` is set,
+    /// failing with a list of problems if the migrations can't be applied or the database isn't
+    /// up to date.
+    pub async fn new(options: &PostgresDatabaseOptions) -> Result<Self, Error> {
+        let pool = PgPoolOptions::new()
+            .connect(&options.url)
+            .await
+            .map_err(|why| {
+                Error::DatabaseMigrationErr(format!("Error connecting to PostgreSQL: {why}"))
+            })?;
+        let db = Self { pool };
+
+        let directory_migrator;
+        let migrator = match &options.migrations_path {
+            Some(migrations_path) => {
+                directory_migrator = read_migrations(migrations_path).await?;
+                &directory_migrator
+            }
+            None => &EMBEDDED_MIGRATOR,
+        };
+        db.check_migrations(migrator, options.migrate).await?;
+        if options.migrate {
+            migrator.run(&db.pool).await.map_err(|why| {
+                Error::DatabaseMigrationErr(format!("Error applying migrations: {why}"))
+            })?;
+        }
+        Ok(db)
+    }
+
+    /// Fails if the applied migrations don't match the known ones, see `check_migrations`.
+    async fn check_migrations(&self, migrator: &Migrator, migrate: bool) -> Result<(), Error> {
+        let table_exists: bool =
+            sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))?;
+        let applied: Vec<(i64, bool, Vec<u8>)> = match table_exists {
+            true => sqlx::query_as(
+                "SELECT version, success, checksum FROM _sqlx_migrations ORDER BY version",
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))?,
+            false => vec![],
+        };
+        check_migrations(migrator, &applied, migrate)
+    }
+
+    /// Returns the version and description of every migration applied to the database, oldest
+    /// first.
+    pub async fn list_applied_migrations(&self) -> Result<Vec<(i64, String)>, Error> {
+        let rows: Result<Vec<(i64, String)>, sqlx::Error> = sqlx::query_as(
+            "SELECT version, description FROM _sqlx_migrations WHERE success ORDER BY version",
+        )
+        .fetch_all(&self.pool)
+        .await;
+        rows.map_err(|why| Error::DatabaseUnexpectedErr(format!("{why:?}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::datastore::test_utils::{get_test_db, get_test_postgres_db};
+
+    use super::*;
+
+    /// Inserts a channel and a role incident, dry run and not, then lists them back with the
+    /// database assigned IDs replaced by their insertion order.
+    async fn round_trip_incidents(
+        db: &(impl DatastoreReader + DatastoreWriter),
+        guild_id: serenity::GuildId,
+    ) -> (Vec<Incident>, Vec<IncidentStats>) {
+        let channel_incident = Incident {
+            id: 0,
+            guild_id,
+            channel_id: Some(serenity::ChannelId::new(87654321)),
+            user_id: serenity::UserId::new(11111111),
+            action: MessageResponse::Timeout,
+            trigger_kind: TriggerKind::Thread,
+            content: "free nitro".to_string(),
+            created_at: serenity::Timestamp::from_unix_timestamp(1_700_000_000).unwrap(),
+            outcome: Some("Timed out for 1 hour".to_string()),
+            error: None,
+            false_positive: false,
+            dry_run: false,
+        };
+        let role_incident = Incident {
+            channel_id: None,
+            action: MessageResponse::Ban,
+            trigger_kind: TriggerKind::Role,
+            content: "honeypot role".to_string(),
+            created_at: serenity::Timestamp::from_unix_timestamp(1_700_000_060).unwrap(),
+            outcome: None,
+            error: Some("Missing permissions".to_string()),
+            dry_run: true,
+            ..channel_incident.clone()
+        };
+        let first_id = db.insert_incident(&channel_incident).await.unwrap();
+        let second_id = db.insert_incident(&role_incident).await.unwrap();
+        assert!(first_id > 0 && second_id > first_id);
+        assert_eq!(
+            db.mark_incident_false_positive(guild_id, second_id).await,
+            Ok(())
+        );
+        assert_eq!(
+            db.mark_incident_false_positive(serenity::GuildId::new(1), second_id)
+                .await,
+            Err(Error::DatabaseEntryNotFound)
+        );
+
+        let mut filter = IncidentFilter {
+            guild_id,
+            user_id: None,
+            channel_id: None,
+            action: None,
+            include_dry_runs: false,
+            limit: 10,
+            offset: 0,
+        };
+        assert_eq!(db.list_incidents(&filter).await.unwrap().len(), 1);
+        filter.include_dry_runs = true;
+        let mut incidents = db.list_incidents(&filter).await.unwrap();
+        for incident in &mut incidents {
+            incident.id = match incident.id {
+                id if id == first_id => 1,
+                id if id == second_id => 2,
+                id => panic!("unexpected incident id {id}"),
+            };
+        }
+        (incidents, db.get_incident_stats(guild_id).await.unwrap())
+    }
+
+    /// Inserts two content rules, deletes the first and inserts a third, then lists them back
+    /// with the database assigned IDs replaced by their insertion order.
+    async fn round_trip_content_rules(
+        db: &(impl DatastoreReader + DatastoreWriter),
+        guild_id: serenity::GuildId,
+    ) -> Vec<ContentRule> {
+        let regex_rule = ContentRule {
+            id: 0,
+            guild_id,
+            kind: ContentRuleKind::Regex,
+            pattern: "free (nitro|robux)".to_string(),
+            response: MessageResponse::Timeout,
+            timeout_duration: Some(Duration::from_secs(3600)),
+            quarantine_role_id: None,
+            delete_message: true,
+            reason: Some("Spam from {user}".to_string()),
+        };
+        let keyword_rule = ContentRule {
+            kind: ContentRuleKind::Keyword,
+            pattern: "airdrop, giveaway".to_string(),
+            response: MessageResponse::Quarantine,
+            timeout_duration: None,
+            quarantine_role_id: Some(serenity::RoleId::new(22222222)),
+            delete_message: false,
+            reason: None,
+            ..regex_rule.clone()
+        };
+        let invite_rule = ContentRule {
+            kind: ContentRuleKind::Invite,
+            pattern: String::new(),
+            response: MessageResponse::Nothing,
+            ..keyword_rule.clone()
+        };
+        let first_id = db.insert_content_rule(&regex_rule).await.unwrap();
+        let second_id = db.insert_content_rule(&keyword_rule).await.unwrap();
+        assert!(first_id > 0 && second_id > first_id);
+        db.delete_content_rule(guild_id, first_id).await.unwrap();
+        // IDs are never reused, so compiled patterns cached by ID can't go stale
+        let third_id = db.insert_content_rule(&invite_rule).await.unwrap();
+        assert!(third_id > second_id);
+
+        let mut content_rules = db.get_content_rules(guild_id).await.unwrap();
+        for content_rule in &mut content_rules {
+            content_rule.id = match content_rule.id {
+                id if id == second_id => 2,
+                id if id == third_id => 3,
+                id => panic!("unexpected content rule id {id}"),
+            };
+        }
+        content_rules
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn list_applied_migrations() {
+        let Some(db) = get_test_postgres_db().await else {
+            return;
+        };
+
+        // Every migration in the directory is applied, oldest first
+        let migration_count = std::fs::read_dir("migrations/postgres").unwrap().count();
+        let migrations = db.list_applied_migrations().await.unwrap();
+        assert_eq!(migrations.len(), migration_count);
+        assert!(migrations.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+        // Nothing is pending once the migrations are applied
+        let result = db.check_migrations(&EMBEDDED_MIGRATOR, false).await;
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn incidents_round_trip_like_sqlite() {
+        let Some(db) = get_test_postgres_db().await else {
+            return;
+        };
+        let guild_id = serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+        let (incidents, stats) = round_trip_incidents(&db, guild_id).await;
+
+        // Booleans and the missing channel of role incidents are read back as they were written
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(incidents[0].id, 2);
+        assert_eq!(incidents[0].channel_id, None);
+        assert!(incidents[0].dry_run);
+        assert!(incidents[0].false_positive);
+        assert_eq!(incidents[1].id, 1);
+        assert_eq!(
+            incidents[1].channel_id,
+            Some(serenity::ChannelId::new(87654321))
+        );
+        assert!(!incidents[1].dry_run);
+        assert!(!incidents[1].false_positive);
+        assert_eq!(
+            stats
+                .iter()
+                .map(|stats| stats.channel_id)
+                .collect::<Vec<_>>(),
+            vec![None, Some(serenity::ChannelId::new(87654321))]
+        );
+
+        // Postgres and sqlite read back the same incidents
+        let sqlite_db = get_test_db().await;
+        let sqlite_result = round_trip_incidents(&sqlite_db, guild_id).await;
+        assert_eq!(sqlite_result, (incidents, stats));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn content_rules_round_trip_like_sqlite() {
+        let Some(db) = get_test_postgres_db().await else {
+            return;
+        };
+        let guild_id = serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+        let content_rules = round_trip_content_rules(&db, guild_id).await;
+
+        assert_eq!(
+            content_rules
+                .iter()
+                .map(|content_rule| (content_rule.id, content_rule.kind))
+                .collect::<Vec<_>>(),
+            vec![(2, ContentRuleKind::Keyword), (3, ContentRuleKind::Invite)]
+        );
+        assert!(!content_rules[0].delete_message);
+        assert_eq!(
+            content_rules[0].quarantine_role_id,
+            Some(serenity::RoleId::new(22222222))
+        );
+
+        // Postgres and sqlite read back the same content rules
+        let sqlite_db = get_test_db().await;
+        let sqlite_content_rules = round_trip_content_rules(&sqlite_db, guild_id).await;
+        assert_eq!(sqlite_content_rules, content_rules);
+
+        // Clean up rows:
+        db.delete_guild_configs(guild_id).await.unwrap();
+        sqlite_db.delete_guild_configs(guild_id).await.unwrap();
+    }
+}
@@ -1,11 +1,176 @@
-use crate::datastore::database::{Database, DatabaseOptions};
+use std::{path::Path, process::Command, sync::OnceLock};
+
+use crate::datastore::{
+    database::{Database, DatabaseOptions},
+    postgres::{PostgresDatabase, PostgresDatabaseOptions},
+};
 
 const TEST_DATABASE_PATH: &str = "/tmp/honeybot-test.db";
 
+/// Where the PostgreSQL server started for tests keeps its data, socket and log.
+const TEST_POSTGRES_DIR: &str = "/tmp/honeybot-test-postgres";
+const TEST_POSTGRES_PORT: &str = "55432";
+
+/// Defines a test that runs once against each backend, as `<name>::sqlite` and
+/// `<name>::postgres`, with the backend's database bound to the given name. The PostgreSQL half
+/// passes without doing anything when there's no PostgreSQL to test with. Each extra name gets
+/// another connection to the same database, like other instances of the bot would have.
+macro_rules! test_each_backend {
+    (async fn $name:ident($($db:ident),+) $body:block) => {
+        mod $name {
+            use super::*;
+
+            #[tokio::test]
+            #[serial]
+            async fn sqlite() {
+                $(let $db = $crate::datastore::test_utils::get_test_db().await;)+
+                $body
+            }
+
+            #[tokio::test]
+            #[serial]
+            async fn postgres() {
+                $(
+                    let Some($db) = $crate::datastore::test_utils::get_test_postgres_db().await
+                    else {
+                        return;
+                    };
+                )+
+                $body
+            }
+        }
+    };
+}
+
+pub(crate) use test_each_backend;
+
 pub async fn get_test_db() -> Database {
     Database::new(&DatabaseOptions {
         filename: TEST_DATABASE_PATH.to_string(),
-        migrations_path: "migrations".to_string(),
+        migrations_path: None,
+        migrate: true,
     })
     .await
+    .unwrap()
+}
+
+/// Connects to `HONEYBOT_TEST_POSTGRES_URL` if it's set, or else to an empty database in a
+/// PostgreSQL server started for the tests. `None` when neither is available.
+pub async fn get_test_postgres_db() -> Option<PostgresDatabase> {
+    static URL: OnceLock<Option<String>> = OnceLock::new();
+    let url = URL.get_or_init(|| {
+        let url = std::env::var("HONEYBOT_TEST_POSTGRES_URL")
+            .ok()
+            .or_else(start_test_postgres);
+        if url.is_none() {
+            eprintln!(
+                "Skipping PostgreSQL tests: set HONEYBOT_TEST_POSTGRES_URL or install PostgreSQL"
+            );
+        }
+        url
+    });
+    let database = PostgresDatabase::new(&PostgresDatabaseOptions {
+        url: url.clone()?,
+        migrations_path: None,
+        migrate: true,
+    })
+    .await
+    .unwrap();
+    Some(database)
+}
+
+/// Starts a PostgreSQL server with `initdb` and `pg_ctl`, or reuses the one left running by an
+/// earlier test run, then recreates the test database so every run starts empty. PostgreSQL
+/// refuses to run as root, so it's run as the `postgres` user then. `None` when PostgreSQL isn't
+/// installed.
+fn start_test_postgres() -> Option<String> {
+    let as_root = Command::new("id")
+        .arg("-u")
+        .output()
+        .is_ok_and(|output| output.stdout.trim_ascii() == b"0");
+    let installed = ["initdb", "pg_ctl", "psql"]
+        .iter()
+        .all(|program| Command::new(program).arg("--version").output().is_ok());
+    let has_postgres_user = !as_root
+        || Command::new("id")
+            .arg("postgres")
+            .output()
+            .is_ok_and(|output| output.status.success());
+    if !installed || !has_postgres_user {
+        return None;
+    }
+    let run = |program: &str, args: &[&str]| {
+        let mut command = match as_root {
+            true => {
+                let mut command = Command::new("runuser");
+                command.args(["-u", "postgres", "--", program]);
+                command
+            }
+            false => Command::new(program),
+        };
+        command
+            .args(args)
+            .output()
+            .unwrap_or_else(|why| panic!("Error running `{program}` to test PostgreSQL: {why}"))
+    };
+
+    let data_dir = format!("{TEST_POSTGRES_DIR}/data");
+    if !Path::new(&data_dir).exists() {
+        std::fs::create_dir_all(TEST_POSTGRES_DIR).unwrap();
+        if as_root {
+            Command::new("chown")
+                .args(["postgres", TEST_POSTGRES_DIR])
+                .status()
+                .unwrap();
+        }
+        let output = run(
+            "initdb",
+            &["-D", &data_dir, "-A", "trust", "-U", "honeybot"],
+        );
+        assert!(output.status.success(), "{output:?}");
+    }
+    if !run("pg_ctl", &["status", "-D", &data_dir]).status.success() {
+        let server_options =
+            format!("-p {TEST_POSTGRES_PORT} -k {TEST_POSTGRES_DIR} -c listen_addresses=''");
+        let log = format!("{TEST_POSTGRES_DIR}/log");
+        let output = run(
+            "pg_ctl",
+            &[
+                "start",
+                "-w",
+                "-D",
+                &data_dir,
+                "-o",
+                &server_options,
+                "-l",
+                &log,
+            ],
+        );
+        assert!(output.status.success(), "{output:?}");
+    }
+    for sql in [
+        "DROP DATABASE IF EXISTS honeybot_test",
+        "CREATE DATABASE honeybot_test",
+    ] {
+        let output = run(
+            "psql",
+            &[
+                "-h",
+                TEST_POSTGRES_DIR,
+                "-p",
+                TEST_POSTGRES_PORT,
+                "-U",
+                "honeybot",
+                "-d",
+                "postgres",
+                "-c",
+                sql,
+            ],
+        );
+        assert!(output.status.success(), "{output:?}");
+    }
+    Some(format!(
+        "postgres://honeybot@localhost/honeybot_test?host={TEST_POSTGRES_DIR}&port={}",
+        TEST_POSTGRES_PORT
+    ))
 }
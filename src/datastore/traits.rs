@@ -1,21 +1,97 @@
 use poise::serenity_prelude::{self as serenity};
 
-use crate::datastore::{
-    errors::Error,
-    models::{MessageResponse, MessageResponseConfig},
+use crate::{
+    datastore::{
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, EscalationPolicy, Exemption, GuildSettings,
+            Incident, IncidentFilter, IncidentStats, MessageResponseConfig, MessageTemplate,
+            QuarantinedMember, RoleResponseConfig, TemplateKind,
+        },
+    },
+    guild_config::GuildConfig,
 };
 
 pub trait DatastoreReader {
-    async fn get_message_response(
+    async fn get_message_response_config(
         &self,
         guild_id: serenity::GuildId,
         channel_id: serenity::ChannelId,
-    ) -> Result<MessageResponse, Error>;
+    ) -> Result<MessageResponseConfig, Error>;
 
     async fn get_logging_channel(
         &self,
         guild_id: serenity::GuildId,
     ) -> Result<serenity::ChannelId, Error>;
+
+    async fn get_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<QuarantinedMember, Error>;
+
+    /// Reads the template set for exactly this guild and channel, where a `channel_id` of `None`
+    /// is the guild-wide template.
+    async fn get_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<String, Error>;
+
+    /// Returns every template of the guild, guild-wide templates first.
+    async fn list_message_templates(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageTemplate>, Error>;
+
+    async fn get_escalation_policy(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<EscalationPolicy, Error>;
+
+    /// Counts the user's offenses in the guild at or after `since`.
+    async fn count_offenses(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        since: serenity::Timestamp,
+    ) -> Result<u32, Error>;
+
+    async fn list_incidents(&self, filter: &IncidentFilter) -> Result<Vec<Incident>, Error>;
+
+    /// Returns how often each of the guild's honeypots has been triggered, dry runs included.
+    async fn get_incident_stats(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<IncidentStats>, Error>;
+
+    /// Returns every honeypot channel of the guild, ordered by channel ID.
+    async fn list_message_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<MessageResponseConfig>, Error>;
+
+    async fn get_exemptions(&self, guild_id: serenity::GuildId) -> Result<Vec<Exemption>, Error>;
+
+    async fn get_guild_settings(&self, guild_id: serenity::GuildId)
+    -> Result<GuildSettings, Error>;
+
+    async fn get_role_response_configs(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<RoleResponseConfig>, Error>;
+
+    async fn get_burst_detection_config(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<BurstDetectionConfig, Error>;
+
+    /// Returns the guild's content rules, oldest first.
+    async fn get_content_rules(
+        &self,
+        guild_id: serenity::GuildId,
+    ) -> Result<Vec<ContentRule>, Error>;
 }
 
 pub trait DatastoreWriter {
@@ -36,8 +112,122 @@ pub trait DatastoreWriter {
         channel_id: serenity::ChannelId,
     ) -> Result<(), Error>;
 
-    // Might add a command to use this method later. As of this commit, this method is only used in
-    // tests.
-    #[allow(dead_code)]
     async fn delete_logging_channel(&self, guild_id: serenity::GuildId) -> Result<(), Error>;
+
+    async fn insert_quarantined_member(
+        &self,
+        quarantined_member: &QuarantinedMember,
+    ) -> Result<(), Error>;
+
+    async fn delete_quarantined_member(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+    ) -> Result<(), Error>;
+
+    async fn insert_message_template(
+        &self,
+        message_template: &MessageTemplate,
+    ) -> Result<(), Error>;
+
+    async fn delete_message_template(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: Option<serenity::ChannelId>,
+        kind: TemplateKind,
+    ) -> Result<(), Error>;
+
+    async fn insert_escalation_policy(
+        &self,
+        escalation_policy: &EscalationPolicy,
+    ) -> Result<(), Error>;
+
+    async fn delete_escalation_policy(&self, guild_id: serenity::GuildId) -> Result<(), Error>;
+
+    async fn insert_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error>;
+
+    /// Deletes one of the user's offenses recorded at `created_at`, returning
+    /// `DatabaseEntryNotFound` if there wasn't one.
+    async fn delete_offense(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        created_at: serenity::Timestamp,
+    ) -> Result<(), Error>;
+
+    /// Inserts the incident, returning the ID assigned to it.
+    async fn insert_incident(&self, incident: &Incident) -> Result<i64, Error>;
+
+    async fn mark_incident_false_positive(
+        &self,
+        guild_id: serenity::GuildId,
+        incident_id: i64,
+    ) -> Result<(), Error>;
+
+    async fn insert_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error>;
+
+    async fn delete_exemption(
+        &self,
+        guild_id: serenity::GuildId,
+        exemption: Exemption,
+    ) -> Result<(), Error>;
+
+    async fn insert_guild_settings(&self, guild_settings: &GuildSettings) -> Result<(), Error>;
+
+    async fn insert_role_response_config(
+        &self,
+        role_response_config: &RoleResponseConfig,
+    ) -> Result<(), Error>;
+
+    async fn delete_role_response_config(
+        &self,
+        guild_id: serenity::GuildId,
+        role_id: serenity::RoleId,
+    ) -> Result<(), Error>;
+
+    async fn insert_burst_detection_config(
+        &self,
+        burst_detection_config: &BurstDetectionConfig,
+    ) -> Result<(), Error>;
+
+    async fn delete_burst_detection_config(&self, guild_id: serenity::GuildId)
+    -> Result<(), Error>;
+
+    /// Returns the new rule's ID, the rule's `id` is ignored.
+    async fn insert_content_rule(&self, content_rule: &ContentRule) -> Result<i64, Error>;
+
+    async fn delete_content_rule(
+        &self,
+        guild_id: serenity::GuildId,
+        content_rule_id: i64,
+    ) -> Result<(), Error>;
+
+    /// Deletes every config of the guild, for when the bot leaves it. Incidents, offenses and
+    /// quarantined members are kept as a record of what the bot did.
+    async fn delete_guild_configs(&self, guild_id: serenity::GuildId) -> Result<(), Error>;
+
+    /// Replaces every config of the guild with the document's, all at once so a failure leaves
+    /// the old configs in place. The document should be validated first.
+    async fn replace_guild_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        guild_config: &GuildConfig,
+    ) -> Result<(), Error>;
+
+    /// Deletes every config tied to a deleted channel: its honeypot config, its message
+    /// templates, and the logging channel if it was the logging channel.
+    async fn delete_channel_configs(
+        &self,
+        guild_id: serenity::GuildId,
+        channel_id: serenity::ChannelId,
+    ) -> Result<(), Error>;
 }
@@ -1,68 +1,586 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use poise::serenity_prelude::{self as serenity, EventHandler, async_trait};
 
-use crate::datastore::{Datastore, models::MessageResponse, traits::DatastoreReader};
+use crate::{
+    action_log::{ActionDetails, handle_action_button, log_action_in_channel},
+    burst_detector::BurstDetector,
+    cleanup,
+    content_rules::ContentRuleMatcher,
+    datastore::{
+        Datastore,
+        errors::Error,
+        models::{
+            AgeRule, DEFAULT_TIMEOUT_DURATION, Exemption, ExemptionMode, GuildSettings,
+            HoneypotResponse, Incident, MessageResponse, QuarantinedMember, TemplateKind,
+            TriggerKind,
+        },
+        traits::{DatastoreReader, DatastoreWriter},
+    },
+    permissions,
+    templates::{render_at, resolve_template},
+    trigger::Trigger,
+    utils::render_template,
+};
+
+/// How many times unbanning a soft banned user is attempted before giving up.
+const SOFT_BAN_UNBAN_ATTEMPTS: u32 = 3;
+
+/// Audit log reasons used when a honeypot channel or role wasn't configured with one.
+const DEFAULT_REASON: &str = "posted in a honeypot channel";
+const DEFAULT_ROLE_REASON: &str = "took a honeypot role";
+
+/// How many pages of 100 messages are read from each channel when purging a user's messages, to
+/// keep long purge windows in busy channels from using up the bot's rate limits.
+const MAX_PURGE_PAGES: usize = 20;
+
+/// How much of the triggering message is saved with an incident.
+const INCIDENT_CONTENT_LENGTH: usize = 200;
 
 pub struct HoneybotEventHandler {
     datastore: Arc<Datastore>,
+    burst_detector: Arc<BurstDetector>,
+    content_rule_matcher: Arc<ContentRuleMatcher>,
 }
 
 impl HoneybotEventHandler {
-    pub fn new(datastore: Arc<Datastore>) -> Self {
-        Self { datastore }
+    pub fn new(
+        datastore: Arc<Datastore>,
+        burst_detector: Arc<BurstDetector>,
+        content_rule_matcher: Arc<ContentRuleMatcher>,
+    ) -> Self {
+        Self {
+            datastore,
+            burst_detector,
+            content_rule_matcher,
+        }
     }
-}
 
-#[async_trait]
-impl EventHandler for HoneybotEventHandler {
-    async fn message(&self, ctx: serenity::Context, new_message: serenity::Message) {
-        // The bot shouldn't ban, kick, or respond to itself (even if it would be hilarious)
-        if new_message.author.id == ctx.cache.current_user().id {
-            return;
+    /// Returns how to treat the user behind a trigger if they're exempt from honeypot actions,
+    /// either because they or one of their roles is on the guild's exemption list, or because
+    /// they're a moderator and the guild exempts moderators.
+    async fn exemption_mode(
+        &self,
+        guild: &serenity::Guild,
+        guild_settings: &GuildSettings,
+        trigger: &Trigger,
+    ) -> Option<ExemptionMode> {
+        let user_id = trigger.user.id;
+
+        if guild_settings.exempt_moderators
+            && (guild.owner_id == user_id
+                || trigger.permissions.is_some_and(|permissions| {
+                    permissions.administrator() || permissions.manage_messages()
+                }))
+        {
+            return Some(guild_settings.exemption_mode);
         }
 
-        let guild_id = new_message.guild_id.unwrap();
-        let channel_id = new_message.channel_id;
-        let response = match self
+        let exempt = match self.datastore.get_exemptions(guild.id).await {
+            Ok(exemptions) => exemptions.iter().any(|exemption| match exemption {
+                Exemption::User(exempt_user_id) => *exempt_user_id == user_id,
+                Exemption::Role(exempt_role_id) => trigger.role_ids.contains(exempt_role_id),
+            }),
+            Err(why) => {
+                tracing::error!("Error retrieving exemptions from database: {why:?}");
+                false
+            }
+        };
+        exempt.then_some(guild_settings.exemption_mode)
+    }
+
+    /// Records an offense for the user and picks a response from the guild's escalation policy,
+    /// returning the response and the user's offense count. Returns `None` if the guild doesn't
+    /// have an escalation policy or its ladder is empty. Dry runs pick the response without
+    /// recording the offense. The offense is recorded at the trigger's timestamp, like its
+    /// incident, so `/pardon` can find it.
+    async fn escalate(
+        &self,
+        guild_id: serenity::GuildId,
+        user_id: serenity::UserId,
+        triggered_at: serenity::Timestamp,
+        dry_run: bool,
+    ) -> Option<(MessageResponse, u32)> {
+        let policy = match self.datastore.get_escalation_policy(guild_id).await {
+            Ok(policy) => policy,
+            Err(Error::DatabaseEntryNotFound) => return None,
+            Err(why) => {
+                tracing::error!("Error retrieving escalation policy from database: {why:?}");
+                return None;
+            }
+        };
+
+        if !dry_run
+            && let Err(why) = self
+                .datastore
+                .insert_offense(guild_id, user_id, triggered_at)
+                .await
+        {
+            tracing::error!("Error saving offense to database: {why:?}");
+        }
+        let now = serenity::Timestamp::now();
+        let since = serenity::Timestamp::from_unix_timestamp(
+            now.unix_timestamp() - policy.decay_window.as_secs() as i64,
+        )
+        .unwrap_or(now);
+        match self
             .datastore
-            .get_message_response(guild_id, channel_id)
+            .count_offenses(guild_id, user_id, since)
             .await
         {
-            Ok(response) => response,
+            Ok(count) => {
+                let count = if dry_run { count + 1 } else { count };
+                policy
+                    .response_for_offense(count)
+                    .map(|response| (response, count))
+            }
+            Err(why) => {
+                tracing::error!("Error counting offenses: {why:?}");
+                None
+            }
+        }
+    }
+
+    /// Replaces all of a member's roles with the quarantine role, saving the removed roles so they
+    /// can be restored later with `/release`.
+    async fn quarantine_member(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        user_id: serenity::UserId,
+        quarantine_role_id: serenity::RoleId,
+        reason: &str,
+    ) -> Result<(), String> {
+        // Keep the roles saved from the first quarantine if the member somehow triggers the
+        // honeypot again, otherwise we'd only remember the quarantine role.
+        if self
+            .datastore
+            .get_quarantined_member(guild.id, user_id)
+            .await
+            .is_ok()
+        {
+            tracing::warn!(
+                "User `{user_id}` is already quarantined in guild `{}`",
+                guild.id
+            );
+            return Ok(());
+        }
+
+        let member = match guild.member(ctx, user_id).await {
+            Ok(member) => member,
+            Err(why) => return Err(action_error("fetching member to quarantine", why)),
+        };
+        // Managed roles (bot, booster and integration roles) can't be removed by the bot.
+        let removed_role_ids: Vec<serenity::RoleId> = member
+            .roles
+            .iter()
+            .filter(|role_id| guild.roles.get(role_id).is_some_and(|role| !role.managed))
+            .copied()
+            .collect();
+        let kept_role_ids = member
+            .roles
+            .iter()
+            .filter(|role_id| !removed_role_ids.contains(role_id))
+            .copied()
+            .chain(std::iter::once(quarantine_role_id))
+            .collect::<Vec<_>>();
+
+        // Save the removed roles before touching the member so they can't be lost.
+        let quarantined_member = QuarantinedMember {
+            guild_id: guild.id,
+            user_id,
+            quarantine_role_id,
+            removed_role_ids,
+        };
+        if let Err(why) = self
+            .datastore
+            .insert_quarantined_member(&quarantined_member)
+            .await
+        {
+            tracing::error!("Error saving quarantined member to database: {why:?}");
+            return Err(format!("Error saving quarantined member: {why:?}"));
+        }
+
+        let result = guild
+            .edit_member(
+                ctx,
+                user_id,
+                serenity::EditMember::new()
+                    .roles(kept_role_ids)
+                    .audit_log_reason(reason),
+            )
+            .await;
+        if let Err(why) = result {
+            let _ = self
+                .datastore
+                .delete_quarantined_member(guild.id, user_id)
+                .await;
+            return Err(action_error("quarantining", why));
+        }
+        Ok(())
+    }
+
+    /// Takes the channel's configured action on the user behind a trigger, if the channel counts
+    /// this kind of trigger.
+    async fn handle_trigger(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        trigger: Trigger,
+    ) {
+        let Some(channel_id) = trigger.channel_id else {
+            return;
+        };
+        let config = match self
+            .datastore
+            .get_message_response_config(guild.id, channel_id)
+            .await
+        {
+            Ok(config) => config,
             Err(why) => {
                 tracing::error!("Error retrieving configured response from database: {why:?}");
                 return;
             }
         };
+        if !config.trigger_kinds.contains(&trigger.kind) {
+            return;
+        }
+        let age_rule = config.response_for_ages(
+            age_since(trigger.user.id.created_at()),
+            trigger.joined_at.map(age_since),
+        );
+        self.respond(
+            ctx,
+            guild,
+            trigger,
+            HoneypotResponse::from(&config),
+            age_rule,
+        )
+        .await;
+    }
+
+    /// Acts on the poster if the message matches one of the guild's content rules, using the
+    /// oldest matching rule. Returns the message back if no rule matched. Messages from bots and
+    /// webhooks are never matched.
+    async fn apply_content_rules(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        message: serenity::Message,
+    ) -> Option<serenity::Message> {
+        if is_automated(&message) {
+            return Some(message);
+        }
+        let content_rules = match self.datastore.get_content_rules(guild.id).await {
+            Ok(content_rules) => content_rules,
+            Err(why) => {
+                tracing::error!("Error retrieving content rules from database: {why:?}");
+                return Some(message);
+            }
+        };
+        let Some(content_rule) = self
+            .content_rule_matcher
+            .first_match(&content_rules, &message.content)
+            .await
+        else {
+            return Some(message);
+        };
+        self.respond(
+            ctx,
+            guild,
+            Trigger::from_content_rule(guild, message),
+            HoneypotResponse::from(content_rule),
+            None,
+        )
+        .await;
+        None
+    }
+
+    /// Feeds the message to the burst detector if the guild uses burst detection, acting on the
+    /// user if it completed a burst. Returns the message back if it didn't.
+    async fn detect_burst(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        message: serenity::Message,
+    ) -> Option<serenity::Message> {
+        let config = match self.datastore.get_burst_detection_config(guild.id).await {
+            Ok(config) => config,
+            Err(Error::DatabaseEntryNotFound) => return Some(message),
+            Err(why) => {
+                tracing::error!("Error retrieving burst detection config from database: {why:?}");
+                return Some(message);
+            }
+        };
+        if is_automated(&message)
+            || !self
+                .burst_detector
+                .record(guild.id, &message, config.channel_count, config.window)
+                .await
+        {
+            return Some(message);
+        }
+        self.respond(
+            ctx,
+            guild,
+            Trigger::from_burst(guild, message),
+            HoneypotResponse::from(&config),
+            None,
+        )
+        .await;
+        None
+    }
+
+    /// Acts on the user behind a trigger, then records and logs it. `age_rule` is the response
+    /// picked by the channel's account and join age rules, if one matched.
+    async fn respond(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        trigger: Trigger,
+        mut config: HoneypotResponse,
+        age_rule: Option<(MessageResponse, AgeRule)>,
+    ) {
+        // The bot shouldn't ban, kick, or respond to itself (even if it would be hilarious)
+        if trigger.user.id == ctx.cache.current_user().id {
+            return;
+        }
+        let guild_id = guild.id;
+        let channel_id = trigger.channel_id;
+        let user_id = trigger.user.id;
+
+        let guild_settings = match self.datastore.get_guild_settings(guild_id).await {
+            Ok(guild_settings) => guild_settings,
+            Err(why) => {
+                tracing::error!("Error retrieving guild settings from database: {why:?}");
+                GuildSettings::new(guild_id)
+            }
+        };
+        config.dry_run |= guild_settings.dry_run;
 
-        // I feel like this is not the best way to get the guild...
-        let guild = (*new_message.guild(&ctx.cache).unwrap()).clone();
-        let user_id = new_message.author.id;
+        // Exempt users are either ignored or only get warned, no matter what the channel or
+        // escalation policy says.
+        let exemption_mode = self.exemption_mode(guild, &guild_settings, &trigger).await;
+        if exemption_mode == Some(ExemptionMode::Ignore) {
+            return;
+        }
 
-        match response {
+        // Repeat offenders get harsher responses if the guild has an escalation policy, otherwise
+        // the channel's account and join age rules can pick a different response.
+        let mut offense_count = None;
+        let mut matched_age_rule = None;
+        if config.response != MessageResponse::Nothing
+            && exemption_mode.is_none()
+            && let Some((response, count)) = self
+                .escalate(guild_id, user_id, trigger.timestamp, config.dry_run)
+                .await
+        {
+            config.response = response;
+            offense_count = Some(count);
+        } else if let Some((response, rule)) = age_rule {
+            config.response = response;
+            matched_age_rule = Some(rule);
+        }
+
+        if config.response != MessageResponse::Nothing && exemption_mode.is_some() {
+            config.response = MessageResponse::Respond;
+            config.delete_trigger = false;
+            config.purge_window = None;
+        }
+        // Role triggers use the role's name in place of the channel's.
+        let location_name = match channel_id {
+            Some(channel_id) => guild
+                .channels
+                .get(&channel_id)
+                .map(|channel| channel.name.as_str())
+                .unwrap_or_default(),
+            None => trigger.content.as_str(),
+        };
+        let reason = render_template(
+            config.reason.as_deref().unwrap_or(match trigger.kind {
+                TriggerKind::Role => DEFAULT_ROLE_REASON,
+                _ => DEFAULT_REASON,
+            }),
+            &[("user", &trigger.user.name), ("channel", location_name)],
+        );
+
+        // Dry runs only log and record what would have happened.
+        if config.dry_run {
+            if config.response == MessageResponse::Nothing
+                && !config.delete_trigger
+                && config.purge_window.is_none()
+            {
+                return;
+            }
+            let details = ActionDetails {
+                deleted_message_count: 0,
+                outcome: Some(dry_run_outcome(&config)),
+                error: None,
+                offense_count,
+                age_rule: matched_age_rule,
+                incident_id: None,
+            };
+            self.record_and_log(ctx, guild, &trigger, &config, details)
+                .await;
+            return;
+        }
+
+        // Clean up before taking action, since the bot may not be able to see the poster's
+        // messages once they've been kicked or banned.
+        let mut deleted_message_count = 0;
+        if config.delete_trigger {
+            match trigger.delete(ctx).await {
+                Ok(_) => deleted_message_count += 1,
+                Err(why) => tracing::error!("Error deleting honeypot trigger: {why:?}"),
+            }
+        }
+        if let Some(purge_window) = config.purge_window {
+            deleted_message_count += purge_user_messages(ctx, guild, user_id, purge_window).await;
+        }
+
+        let result = match config.response {
             MessageResponse::Respond => {
-                let result = new_message
-                    .reply(&ctx, "Are you lost? You shouldn't be in this channel...")
-                    .await;
-                if let Err(why) = result {
-                    tracing::error!("Error responding to user: {why:?}");
-                }
+                let template =
+                    resolve_template(&self.datastore, guild_id, channel_id, TemplateKind::Reply)
+                        .await;
+                trigger
+                    .reply(
+                        ctx,
+                        render_at(&template, user_id, &trigger.location(), config.response),
+                    )
+                    .await
+                    .map(|_| None)
+                    .map_err(|why| action_error("responding to", why))
             }
             MessageResponse::Kick => guild
-                .kick_with_reason(&ctx, user_id, "posted in a honeypot channel")
+                .kick_with_reason(ctx, user_id, &reason)
                 .await
-                .unwrap_or_else(|err| {
-                    tracing::error!("Error kicking user: {err:?}");
-                }),
+                .map(|_| None)
+                .map_err(|why| action_error("kicking", why)),
             MessageResponse::Ban => guild
-                .ban_with_reason(&ctx, user_id, 7, "posted in a honeypot channel")
+                .ban_with_reason(ctx, user_id, config.delete_message_days, &reason)
                 .await
-                .unwrap_or_else(|err| {
-                    tracing::error!("Error banning user: {err:?}");
-                }),
-            MessageResponse::Nothing => return,
+                .map(|_| None)
+                .map_err(|why| action_error("banning", why)),
+            MessageResponse::SoftBan => {
+                soft_ban_member(ctx, guild, user_id, config.delete_message_days, &reason)
+                    .await
+                    .map(Some)
+            }
+            MessageResponse::Timeout => {
+                let duration = config.timeout_duration.unwrap_or(DEFAULT_TIMEOUT_DURATION);
+                let until = serenity::Timestamp::from_unix_timestamp(
+                    serenity::Timestamp::now().unix_timestamp() + duration.as_secs() as i64,
+                )
+                .unwrap();
+                guild
+                    .edit_member(
+                        ctx,
+                        user_id,
+                        serenity::EditMember::new()
+                            .disable_communication_until_datetime(until)
+                            .audit_log_reason(&reason),
+                    )
+                    .await
+                    .map(|_| None)
+                    .map_err(|why| action_error("timing out", why))
+            }
+            MessageResponse::Quarantine => match config.quarantine_role_id {
+                Some(quarantine_role_id) => self
+                    .quarantine_member(ctx, guild, user_id, quarantine_role_id, &reason)
+                    .await
+                    .map(|_| None),
+                None => {
+                    tracing::error!(
+                        "No quarantine role configured for honeypot {}",
+                        trigger.location()
+                    );
+                    Err("No quarantine role is configured to quarantine the user with".to_string())
+                }
+            },
+            MessageResponse::Nothing if deleted_message_count == 0 => return,
+            MessageResponse::Nothing => Ok(None),
         };
+        let (outcome, error) = match result {
+            Ok(outcome) => (outcome, None),
+            Err(error) => {
+                let diagnosis = self
+                    .diagnose_failure(ctx, guild, channel_id, user_id, &config)
+                    .await;
+                match diagnosis {
+                    Some(diagnosis) => (None, Some(format!("{error} ({diagnosis})"))),
+                    None => (None, Some(error)),
+                }
+            }
+        };
+        let details = ActionDetails {
+            deleted_message_count,
+            outcome,
+            error,
+            offense_count,
+            age_rule: matched_age_rule,
+            incident_id: None,
+        };
+        self.record_and_log(ctx, guild, &trigger, &config, details)
+            .await;
+    }
+
+    /// Works out which permission or role position stopped a response, so the logging channel
+    /// says what to fix rather than just Discord's error.
+    async fn diagnose_failure(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        channel_id: Option<serenity::ChannelId>,
+        user_id: serenity::UserId,
+        config: &HoneypotResponse,
+    ) -> Option<String> {
+        let bot_member = permissions::bot_member(ctx, guild).await?;
+        let target = guild.member(ctx, user_id).await.ok();
+        permissions::diagnose_failure(
+            guild,
+            &bot_member,
+            channel_id,
+            target.as_deref(),
+            config.response,
+            config.quarantine_role_id,
+        )
+    }
+
+    /// Saves an incident for the action taken on the user behind a trigger, and posts it in the
+    /// guild's logging channel.
+    async fn record_and_log(
+        &self,
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        trigger: &Trigger,
+        config: &HoneypotResponse,
+        mut details: ActionDetails,
+    ) {
+        let guild_id = guild.id;
+        let channel_id = trigger.channel_id;
+        let incident = Incident {
+            id: 0,
+            guild_id,
+            channel_id,
+            user_id: trigger.user.id,
+            action: config.response,
+            trigger_kind: trigger.kind,
+            content: trigger
+                .content
+                .chars()
+                .take(INCIDENT_CONTENT_LENGTH)
+                .collect(),
+            created_at: trigger.timestamp,
+            outcome: details.outcome.clone(),
+            error: details.error.clone(),
+            false_positive: false,
+            dry_run: config.dry_run,
+        };
+        match self.datastore.insert_incident(&incident).await {
+            Ok(incident_id) => details.incident_id = Some(incident_id),
+            Err(why) => tracing::error!("Error saving incident to database: {why:?}"),
+        }
 
         if let Ok(logging_channel_id) = self.datastore.get_logging_channel(guild_id).await {
             let logging_channel = guild.channels.get(&logging_channel_id);
@@ -70,38 +588,363 @@ impl EventHandler for HoneybotEventHandler {
                 tracing::error!("Logging channel `{logging_channel_id}` not found!");
                 return;
             }
-            log_action_in_channel(&ctx, response, user_id, logging_channel.unwrap()).await;
+            let log_template =
+                resolve_template(&self.datastore, guild_id, channel_id, TemplateKind::Log).await;
+            log_action_in_channel(
+                ctx,
+                &log_template,
+                config,
+                trigger,
+                details,
+                logging_channel.unwrap(),
+            )
+            .await;
         } else {
             tracing::warn!("Logging channel not found for guild `{guild_id}`");
         }
     }
 }
 
-async fn log_action_in_channel(
+#[async_trait]
+impl EventHandler for HoneybotEventHandler {
+    async fn interaction_create(&self, ctx: serenity::Context, interaction: serenity::Interaction) {
+        if let serenity::Interaction::Component(component) = interaction {
+            handle_action_button(&ctx, &self.datastore, &component).await;
+        }
+    }
+
+    async fn message(&self, ctx: serenity::Context, new_message: serenity::Message) {
+        let Some(guild) = new_message.guild(&ctx.cache).map(|guild| guild.clone()) else {
+            return;
+        };
+        // Content rules and bursts are acted on instead of the channel's response, the user is
+        // already dealt with.
+        let Some(new_message) = self.apply_content_rules(&ctx, &guild, new_message).await else {
+            return;
+        };
+        let Some(new_message) = self.detect_burst(&ctx, &guild, new_message).await else {
+            return;
+        };
+        self.handle_trigger(&ctx, &guild, Trigger::from_message(&guild, new_message))
+            .await;
+    }
+
+    async fn reaction_add(&self, ctx: serenity::Context, add_reaction: serenity::Reaction) {
+        let Some(guild) = add_reaction
+            .guild_id
+            .and_then(|guild_id| ctx.cache.guild(guild_id).map(|guild| guild.clone()))
+        else {
+            return;
+        };
+        if let Some(trigger) = Trigger::from_reaction(&ctx, &guild, add_reaction).await {
+            self.handle_trigger(&ctx, &guild, trigger).await;
+        }
+    }
+
+    async fn guild_member_update(
+        &self,
+        ctx: serenity::Context,
+        old_if_available: Option<serenity::Member>,
+        new: Option<serenity::Member>,
+        event: serenity::GuildMemberUpdateEvent,
+    ) {
+        let Some(guild) = ctx.cache.guild(event.guild_id).map(|guild| guild.clone()) else {
+            return;
+        };
+        let role_response_configs = match self
+            .datastore
+            .get_role_response_configs(event.guild_id)
+            .await
+        {
+            Ok(role_response_configs) => role_response_configs,
+            Err(why) => {
+                tracing::error!("Error retrieving honeypot roles from database: {why:?}");
+                return;
+            }
+        };
+        // If the member wasn't cached, every honeypot role they have is treated as new.
+        let old_role_ids = old_if_available
+            .map(|member| member.roles)
+            .unwrap_or_default();
+        let Some(config) = role_response_configs.iter().find(|config| {
+            event.roles.contains(&config.role_id) && !old_role_ids.contains(&config.role_id)
+        }) else {
+            return;
+        };
+
+        let member = match new {
+            Some(member) => member,
+            None => match guild.member(&ctx, event.user.id).await {
+                Ok(member) => member.into_owned(),
+                Err(why) => {
+                    tracing::error!("Error fetching member that took a honeypot role: {why:?}");
+                    return;
+                }
+            },
+        };
+        let trigger = Trigger::from_role(&guild, &member, config.role_id);
+        self.respond(&ctx, &guild, trigger, HoneypotResponse::from(config), None)
+            .await;
+    }
+
+    async fn voice_state_update(
+        &self,
+        ctx: serenity::Context,
+        old: Option<serenity::VoiceState>,
+        new: serenity::VoiceState,
+    ) {
+        let Some(guild) = new
+            .guild_id
+            .and_then(|guild_id| ctx.cache.guild(guild_id).map(|guild| guild.clone()))
+        else {
+            return;
+        };
+        if let Some(trigger) = Trigger::from_voice_state(&ctx, &guild, old.as_ref(), &new).await {
+            self.handle_trigger(&ctx, &guild, trigger).await;
+        }
+    }
+
+    async fn guild_create(
+        &self,
+        ctx: serenity::Context,
+        guild: serenity::Guild,
+        _is_new: Option<bool>,
+    ) {
+        cleanup::remove_channels_deleted_while_offline(&ctx, &self.datastore, &guild).await;
+    }
+
+    async fn guild_delete(
+        &self,
+        _ctx: serenity::Context,
+        incomplete: serenity::UnavailableGuild,
+        _full: Option<serenity::Guild>,
+    ) {
+        // Unavailable guilds are having an outage, the bot is only gone if it was removed.
+        if incomplete.unavailable {
+            return;
+        }
+        tracing::info!(
+            "Removed from guild `{}`, deleting its configs",
+            incomplete.id
+        );
+        if let Err(why) = self.datastore.delete_guild_configs(incomplete.id).await {
+            tracing::error!(
+                "Error deleting configs of guild `{}`: {why:?}",
+                incomplete.id
+            );
+        }
+    }
+
+    async fn channel_delete(
+        &self,
+        ctx: serenity::Context,
+        channel: serenity::GuildChannel,
+        _messages: Option<Vec<serenity::Message>>,
+    ) {
+        cleanup::remove_deleted_channel(
+            &ctx,
+            &self.datastore,
+            channel.guild_id,
+            channel.id,
+            Some(&channel.name),
+        )
+        .await;
+    }
+
+    async fn thread_delete(
+        &self,
+        ctx: serenity::Context,
+        thread: serenity::PartialGuildChannel,
+        full_thread_data: Option<serenity::GuildChannel>,
+    ) {
+        let name = full_thread_data.as_ref().map(|thread| thread.name.as_str());
+        cleanup::remove_deleted_channel(&ctx, &self.datastore, thread.guild_id, thread.id, name)
+            .await;
+    }
+
+    // Forum posts are threads in forum channels, so they also arrive here.
+    async fn thread_create(&self, ctx: serenity::Context, thread: serenity::GuildChannel) {
+        let Some(guild) = ctx.cache.guild(thread.guild_id).map(|guild| guild.clone()) else {
+            return;
+        };
+        if let Some(trigger) = Trigger::from_thread(&ctx, &guild, thread).await {
+            self.handle_trigger(&ctx, &guild, trigger).await;
+        }
+    }
+}
+
+/// Whether the message was posted by a bot or webhook, which content rules and burst detection
+/// can't act on.
+fn is_automated(message: &serenity::Message) -> bool {
+    message.author.bot || message.webhook_id.is_some()
+}
+
+/// How long ago `timestamp` was, or zero if it's in the future.
+fn age_since(timestamp: serenity::Timestamp) -> Duration {
+    let secs = serenity::Timestamp::now().unix_timestamp() - timestamp.unix_timestamp();
+    Duration::from_secs(secs.max(0) as u64)
+}
+
+/// Logs an error from taking action on a user and describes it for the incident and logging
+/// channel.
+fn action_error(action: &str, why: serenity::Error) -> String {
+    tracing::error!("Error {action} user: {why:?}");
+    format!("Error {action} user: {why}")
+}
+
+/// Describes what a dry run would have done to the user.
+fn dry_run_outcome(config: &HoneypotResponse) -> String {
+    let mut outcome = format!(
+        "DRY RUN: the user would have been {}",
+        config.response.past_tense()
+    );
+    if config.delete_trigger || config.purge_window.is_some() {
+        outcome += " and had their messages deleted";
+    }
+    outcome
+}
+
+/// Deletes the user's messages sent within `window` from every text channel and active thread in
+/// the guild, returning the number of messages deleted.
+async fn purge_user_messages(
     ctx: &serenity::Context,
-    action: MessageResponse,
+    guild: &serenity::Guild,
     user_id: serenity::UserId,
-    logging_channel: &serenity::GuildChannel,
-) {
-    let action_str = match action {
-        MessageResponse::Ban => "Banned",
-        MessageResponse::Kick => "Kicked",
-        MessageResponse::Nothing => "Nothing done to",
-        MessageResponse::Respond => "Warned",
-    };
-    let result = logging_channel
-        .say(
-            ctx,
-            format!("{action_str} user <@{user_id}> for posting in the honeypot channel.",),
+    window: Duration,
+) -> usize {
+    let cutoff = serenity::Timestamp::now().unix_timestamp() - window.as_secs() as i64;
+    let channels = guild.channels.values().filter(|channel| {
+        matches!(
+            channel.kind,
+            serenity::ChannelType::Text | serenity::ChannelType::News
         )
-        .await;
-    match result {
-        Ok(_) => (),
-        Err(why) => {
-            tracing::warn!(
-                "Error logging action in channel `{}`: {why:?}",
-                logging_channel.id
-            );
+    });
+    let mut deleted_message_count = 0;
+    for channel in channels.chain(&guild.threads) {
+        deleted_message_count += purge_channel_messages(ctx, channel, user_id, cutoff).await;
+    }
+    deleted_message_count
+}
+
+/// Deletes the user's messages sent since `cutoff` from the channel, paging back through its
+/// history until `cutoff` or `MAX_PURGE_PAGES` pages of messages.
+async fn purge_channel_messages(
+    ctx: &serenity::Context,
+    channel: &serenity::GuildChannel,
+    user_id: serenity::UserId,
+    cutoff: i64,
+) -> usize {
+    let mut deleted_message_count = 0;
+    let mut before = None;
+    for _ in 0..MAX_PURGE_PAGES {
+        let mut builder = serenity::GetMessages::new().limit(100);
+        if let Some(before) = before {
+            builder = builder.before(before);
         }
+        let messages = match channel.messages(ctx, builder).await {
+            Ok(messages) => messages,
+            Err(why) => {
+                tracing::warn!(
+                    "Error reading messages in channel `{}`: {why:?}",
+                    channel.id
+                );
+                break;
+            }
+        };
+        let message_ids: Vec<serenity::MessageId> = messages
+            .iter()
+            .filter(|message| {
+                message.author.id == user_id && message.timestamp.unix_timestamp() >= cutoff
+            })
+            .map(|message| message.id)
+            .collect();
+        if !message_ids.is_empty() {
+            match channel.delete_messages(ctx, &message_ids).await {
+                Ok(_) => deleted_message_count += message_ids.len(),
+                Err(why) => {
+                    tracing::warn!(
+                        "Error deleting messages in channel `{}`: {why:?}",
+                        channel.id
+                    );
+                }
+            }
+        }
+
+        // Messages are returned newest first.
+        match messages.last() {
+            Some(oldest)
+                if messages.len() == 100 && oldest.timestamp.unix_timestamp() >= cutoff =>
+            {
+                before = Some(oldest.id);
+            }
+            _ => break,
+        }
+    }
+    deleted_message_count
+}
+
+/// Bans the user to delete their recent messages, then unbans them so they're able to rejoin.
+/// Returns a description of how the soft ban went for the logging channel.
+async fn soft_ban_member(
+    ctx: &serenity::Context,
+    guild: &serenity::Guild,
+    user_id: serenity::UserId,
+    delete_message_days: u8,
+    reason: &str,
+) -> Result<String, String> {
+    if let Err(why) = guild
+        .ban_with_reason(ctx, user_id, delete_message_days, reason)
+        .await
+    {
+        return Err(action_error("soft banning", why));
+    }
+
+    for attempt in 1..=SOFT_BAN_UNBAN_ATTEMPTS {
+        match guild.unban(ctx, user_id).await {
+            Ok(_) => {
+                return Ok(format!(
+                    "Purged {delete_message_days} day(s) of messages and unbanned the user."
+                ));
+            }
+            Err(why) => {
+                tracing::warn!("Error unbanning soft banned user (attempt {attempt}): {why:?}");
+                tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+            }
+        }
+    }
+    tracing::error!("Giving up on unbanning soft banned user `{user_id}`");
+    Ok(format!(
+        "Purged {delete_message_days} day(s) of messages, but **the unban failed** after {SOFT_BAN_UNBAN_ATTEMPTS} attempts; the user is still banned."
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn age_since() {
+        let now = serenity::Timestamp::now().unix_timestamp();
+        let day_ago = serenity::Timestamp::from_unix_timestamp(now - 24 * 60 * 60).unwrap();
+        let age = super::age_since(day_ago);
+        assert!(age >= Duration::from_secs(24 * 60 * 60));
+        // Leaves room for the clock ticking over during the test:
+        assert!(age <= Duration::from_secs(24 * 60 * 60 + 5));
+
+        let tomorrow = serenity::Timestamp::from_unix_timestamp(now + 24 * 60 * 60).unwrap();
+        assert_eq!(super::age_since(tomorrow), Duration::ZERO);
+    }
+
+    #[test]
+    fn is_automated() {
+        let mut message = serenity::Message::default();
+        assert!(!super::is_automated(&message));
+
+        message.author.bot = true;
+        assert!(super::is_automated(&message));
+
+        message.author.bot = false;
+        message.webhook_id = Some(serenity::WebhookId::new(1));
+        assert!(super::is_automated(&message));
     }
 }
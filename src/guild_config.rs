@@ -0,0 +1,1040 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Duration,
+};
+
+use poise::{
+    ChoiceParameter,
+    serenity_prelude::{self as serenity},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    burst_detector::{MAX_CHANNEL_COUNT, MAX_WINDOW},
+    content_rules::CompiledPattern,
+    datastore::{
+        errors::Error,
+        models::{
+            BurstDetectionConfig, ContentRule, ContentRuleKind, DEFAULT_DELETE_MESSAGE_DAYS,
+            EscalationPolicy, Exemption, ExemptionMode, GuildSettings, MessageResponse,
+            MessageResponseConfig, MessageTemplate, RoleResponseConfig, TemplateKind, TriggerKind,
+        },
+        traits::DatastoreReader,
+    },
+};
+
+/// Version of the document written by `export`. Bump it when a change would make older versions
+/// of the bot misread a document, and teach `GuildConfig::parse` to upgrade older ones.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Discord caps timeouts at 28 days.
+const MAX_TIMEOUT: Duration = Duration::from_secs(28 * 24 * 60 * 60);
+
+/// Discord can only bulk delete messages younger than 14 days.
+const MAX_PURGE_WINDOW: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Every setting of a guild, as a JSON document that can be kept in git and imported into other
+/// guilds. Durations are in seconds, and IDs are strings like in Discord's API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GuildConfig {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging_channel_id: Option<serenity::ChannelId>,
+    #[serde(default)]
+    pub settings: Settings,
+    #[serde(default)]
+    pub honeypot_channels: Vec<HoneypotChannel>,
+    #[serde(default)]
+    pub honeypot_roles: Vec<HoneypotRole>,
+    #[serde(default)]
+    pub message_templates: Vec<Template>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_policy: Option<Escalation>,
+    #[serde(default)]
+    pub exemptions: Exemptions,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst_detection: Option<BurstDetection>,
+    #[serde(default)]
+    pub content_rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    pub exemption_mode: ExemptionMode,
+    #[serde(default)]
+    pub exempt_moderators: bool,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoneypotChannel {
+    pub channel_id: serenity::ChannelId,
+    pub response: MessageResponse,
+    #[serde(default = "default_triggers")]
+    pub triggers: Vec<TriggerKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    #[serde(default)]
+    pub delete_message: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_secs: Option<u64>,
+    #[serde(default = "default_delete_message_days")]
+    pub delete_message_days: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_account_age_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_account_response: Option<MessageResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub established_member_age_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub established_member_response: Option<MessageResponse>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HoneypotRole {
+    pub role_id: serenity::RoleId,
+    pub response: MessageResponse,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purge_secs: Option<u64>,
+    #[serde(default = "default_delete_message_days")]
+    pub delete_message_days: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// A message template, for the whole guild when `channel_id` isn't set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Template {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel_id: Option<serenity::ChannelId>,
+    pub kind: TemplateKind,
+    pub template: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Escalation {
+    pub ladder: Vec<MessageResponse>,
+    pub decay_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Exemptions {
+    #[serde(default)]
+    pub users: Vec<serenity::UserId>,
+    #[serde(default)]
+    pub roles: Vec<serenity::RoleId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BurstDetection {
+    pub channel_count: u32,
+    pub window_secs: u64,
+    pub response: MessageResponse,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    #[serde(default)]
+    pub delete_messages: bool,
+}
+
+/// A content rule. Rules don't keep their IDs, they get new ones when imported.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub kind: ContentRuleKind,
+    #[serde(default)]
+    pub pattern: String,
+    pub response: MessageResponse,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quarantine_role_id: Option<serenity::RoleId>,
+    #[serde(default)]
+    pub delete_message: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings::from(&GuildSettings::new(serenity::GuildId::new(1)))
+    }
+}
+
+fn default_triggers() -> Vec<TriggerKind> {
+    vec![TriggerKind::Message]
+}
+
+fn default_delete_message_days() -> u8 {
+    DEFAULT_DELETE_MESSAGE_DAYS
+}
+
+fn secs(duration: Option<Duration>) -> Option<u64> {
+    duration.map(|duration| duration.as_secs())
+}
+
+fn duration(secs: Option<u64>) -> Option<Duration> {
+    secs.map(Duration::from_secs)
+}
+
+impl From<&GuildSettings> for Settings {
+    fn from(guild_settings: &GuildSettings) -> Self {
+        Self {
+            exemption_mode: guild_settings.exemption_mode,
+            exempt_moderators: guild_settings.exempt_moderators,
+            dry_run: guild_settings.dry_run,
+        }
+    }
+}
+
+impl From<&MessageResponseConfig> for HoneypotChannel {
+    fn from(config: &MessageResponseConfig) -> Self {
+        Self {
+            channel_id: config.channel_id,
+            response: config.response,
+            triggers: config.trigger_kinds.clone(),
+            timeout_secs: secs(config.timeout_duration),
+            quarantine_role_id: config.quarantine_role_id,
+            delete_message: config.delete_message,
+            purge_secs: secs(config.purge_window),
+            delete_message_days: config.delete_message_days,
+            reason: config.reason.clone(),
+            new_account_age_secs: secs(config.new_account_age),
+            new_account_response: config.new_account_response,
+            established_member_age_secs: secs(config.established_member_age),
+            established_member_response: config.established_member_response,
+            dry_run: config.dry_run,
+        }
+    }
+}
+
+impl From<&RoleResponseConfig> for HoneypotRole {
+    fn from(config: &RoleResponseConfig) -> Self {
+        Self {
+            role_id: config.role_id,
+            response: config.response,
+            timeout_secs: secs(config.timeout_duration),
+            quarantine_role_id: config.quarantine_role_id,
+            purge_secs: secs(config.purge_window),
+            delete_message_days: config.delete_message_days,
+            reason: config.reason.clone(),
+        }
+    }
+}
+
+impl From<&MessageTemplate> for Template {
+    fn from(message_template: &MessageTemplate) -> Self {
+        Self {
+            channel_id: message_template.channel_id,
+            kind: message_template.kind,
+            template: message_template.template.clone(),
+        }
+    }
+}
+
+impl From<&EscalationPolicy> for Escalation {
+    fn from(escalation_policy: &EscalationPolicy) -> Self {
+        Self {
+            ladder: escalation_policy.ladder.clone(),
+            decay_secs: escalation_policy.decay_window.as_secs(),
+        }
+    }
+}
+
+impl From<&BurstDetectionConfig> for BurstDetection {
+    fn from(config: &BurstDetectionConfig) -> Self {
+        Self {
+            channel_count: config.channel_count,
+            window_secs: config.window.as_secs(),
+            response: config.response,
+            timeout_secs: secs(config.timeout_duration),
+            quarantine_role_id: config.quarantine_role_id,
+            delete_messages: config.delete_messages,
+        }
+    }
+}
+
+impl From<&ContentRule> for Rule {
+    fn from(content_rule: &ContentRule) -> Self {
+        Self {
+            kind: content_rule.kind,
+            pattern: content_rule.pattern.clone(),
+            response: content_rule.response,
+            timeout_secs: secs(content_rule.timeout_duration),
+            quarantine_role_id: content_rule.quarantine_role_id,
+            delete_message: content_rule.delete_message,
+            reason: content_rule.reason.clone(),
+        }
+    }
+}
+
+/// Reads every setting of a guild into a document.
+pub async fn export(
+    datastore: &impl DatastoreReader,
+    guild_id: serenity::GuildId,
+) -> Result<GuildConfig, Error> {
+    let logging_channel_id = optional(datastore.get_logging_channel(guild_id).await)?;
+    let escalation_policy = optional(datastore.get_escalation_policy(guild_id).await)?;
+    let burst_detection = optional(datastore.get_burst_detection_config(guild_id).await)?;
+    // Guilds that never changed their settings have none stored.
+    let guild_settings = optional(datastore.get_guild_settings(guild_id).await)?
+        .unwrap_or_else(|| GuildSettings::new(guild_id));
+    let mut honeypot_roles: Vec<HoneypotRole> = datastore
+        .get_role_response_configs(guild_id)
+        .await?
+        .iter()
+        .map(HoneypotRole::from)
+        .collect();
+    honeypot_roles.sort_by_key(|honeypot_role| honeypot_role.role_id);
+    let mut exemptions = Exemptions::default();
+    for exemption in datastore.get_exemptions(guild_id).await? {
+        match exemption {
+            Exemption::User(user_id) => exemptions.users.push(user_id),
+            Exemption::Role(role_id) => exemptions.roles.push(role_id),
+        }
+    }
+    exemptions.users.sort();
+    exemptions.roles.sort();
+
+    Ok(GuildConfig {
+        version: SCHEMA_VERSION,
+        logging_channel_id,
+        settings: Settings::from(&guild_settings),
+        honeypot_channels: datastore
+            .list_message_response_configs(guild_id)
+            .await?
+            .iter()
+            .map(HoneypotChannel::from)
+            .collect(),
+        honeypot_roles,
+        message_templates: datastore
+            .list_message_templates(guild_id)
+            .await?
+            .iter()
+            .map(Template::from)
+            .collect(),
+        escalation_policy: escalation_policy.as_ref().map(Escalation::from),
+        exemptions,
+        burst_detection: burst_detection.as_ref().map(BurstDetection::from),
+        content_rules: datastore
+            .get_content_rules(guild_id)
+            .await?
+            .iter()
+            .map(Rule::from)
+            .collect(),
+    })
+}
+
+/// A document's settings as the datastore's models, for writing them to a guild.
+pub struct GuildConfigModels {
+    pub logging_channel_id: Option<serenity::ChannelId>,
+    pub guild_settings: GuildSettings,
+    pub message_response_configs: Vec<MessageResponseConfig>,
+    pub role_response_configs: Vec<RoleResponseConfig>,
+    pub message_templates: Vec<MessageTemplate>,
+    pub escalation_policy: Option<EscalationPolicy>,
+    pub exemptions: Vec<Exemption>,
+    pub burst_detection_config: Option<BurstDetectionConfig>,
+    /// Their IDs are ignored, the database assigns new ones.
+    pub content_rules: Vec<ContentRule>,
+}
+
+/// Turns a missing entry into `None`.
+fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::DatabaseEntryNotFound) => Ok(None),
+        Err(why) => Err(why),
+    }
+}
+
+impl GuildConfig {
+    /// Converts the document into the guild's models. The document should be validated first.
+    pub fn models(&self, guild_id: serenity::GuildId) -> GuildConfigModels {
+        let settings = &self.settings;
+        let exemptions = &self.exemptions;
+        let user_exemptions = exemptions.users.iter().copied().map(Exemption::User);
+        let role_exemptions = exemptions.roles.iter().copied().map(Exemption::Role);
+        GuildConfigModels {
+            logging_channel_id: self.logging_channel_id,
+            guild_settings: GuildSettings {
+                guild_id,
+                exemption_mode: settings.exemption_mode,
+                exempt_moderators: settings.exempt_moderators,
+                dry_run: settings.dry_run,
+            },
+            message_response_configs: self
+                .honeypot_channels
+                .iter()
+                .map(|honeypot_channel| MessageResponseConfig {
+                    guild_id,
+                    channel_id: honeypot_channel.channel_id,
+                    response: honeypot_channel.response,
+                    trigger_kinds: honeypot_channel.triggers.clone(),
+                    timeout_duration: duration(honeypot_channel.timeout_secs),
+                    quarantine_role_id: honeypot_channel.quarantine_role_id,
+                    delete_message: honeypot_channel.delete_message,
+                    purge_window: duration(honeypot_channel.purge_secs),
+                    delete_message_days: honeypot_channel.delete_message_days,
+                    reason: honeypot_channel.reason.clone(),
+                    new_account_age: duration(honeypot_channel.new_account_age_secs),
+                    new_account_response: honeypot_channel.new_account_response,
+                    established_member_age: duration(honeypot_channel.established_member_age_secs),
+                    established_member_response: honeypot_channel.established_member_response,
+                    dry_run: honeypot_channel.dry_run,
+                })
+                .collect(),
+            role_response_configs: self
+                .honeypot_roles
+                .iter()
+                .map(|honeypot_role| RoleResponseConfig {
+                    guild_id,
+                    role_id: honeypot_role.role_id,
+                    response: honeypot_role.response,
+                    timeout_duration: duration(honeypot_role.timeout_secs),
+                    quarantine_role_id: honeypot_role.quarantine_role_id,
+                    purge_window: duration(honeypot_role.purge_secs),
+                    delete_message_days: honeypot_role.delete_message_days,
+                    reason: honeypot_role.reason.clone(),
+                })
+                .collect(),
+            message_templates: self
+                .message_templates
+                .iter()
+                .map(|template| MessageTemplate {
+                    guild_id,
+                    channel_id: template.channel_id,
+                    kind: template.kind,
+                    template: template.template.clone(),
+                })
+                .collect(),
+            escalation_policy: self
+                .escalation_policy
+                .as_ref()
+                .map(|escalation| EscalationPolicy {
+                    guild_id,
+                    ladder: escalation.ladder.clone(),
+                    decay_window: Duration::from_secs(escalation.decay_secs),
+                }),
+            exemptions: user_exemptions.chain(role_exemptions).collect(),
+            burst_detection_config: self.burst_detection.as_ref().map(|burst_detection| {
+                BurstDetectionConfig {
+                    guild_id,
+                    channel_count: burst_detection.channel_count,
+                    window: Duration::from_secs(burst_detection.window_secs),
+                    response: burst_detection.response,
+                    timeout_duration: duration(burst_detection.timeout_secs),
+                    quarantine_role_id: burst_detection.quarantine_role_id,
+                    delete_messages: burst_detection.delete_messages,
+                }
+            }),
+            content_rules: self
+                .content_rules
+                .iter()
+                .map(|rule| ContentRule {
+                    id: 0,
+                    guild_id,
+                    kind: rule.kind,
+                    pattern: rule.pattern.clone(),
+                    response: rule.response,
+                    timeout_duration: duration(rule.timeout_secs),
+                    quarantine_role_id: rule.quarantine_role_id,
+                    delete_message: rule.delete_message,
+                    reason: rule.reason.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Parses a document, rejecting ones written by newer versions of the bot.
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value =
+            serde_json::from_str(json).map_err(|why| format!("Invalid JSON: {why}"))?;
+        match value.get("version").and_then(serde_json::Value::as_u64) {
+            Some(version) if version == SCHEMA_VERSION as u64 => (),
+            Some(version) => {
+                return Err(format!(
+                    "Version {version} documents aren't supported, this version of the bot reads \
+                     version {SCHEMA_VERSION}"
+                ));
+            }
+            None => return Err("The document is missing its `version`".to_string()),
+        }
+        serde_json::from_value(value).map_err(|why| format!("Invalid document: {why}"))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("guild configs should always serialize")
+    }
+
+    /// Checks the document for settings the bot's commands wouldn't accept, returning a
+    /// description of each problem.
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let out_of_purge_range = |purge_secs: Option<u64>| {
+            purge_secs.is_some_and(|secs| secs == 0 || secs > MAX_PURGE_WINDOW.as_secs())
+        };
+        let mut check_response =
+            |location: &str,
+             response: MessageResponse,
+             timeout_secs: Option<u64>,
+             quarantine_role_id: Option<serenity::RoleId>| {
+                if response == MessageResponse::Quarantine && quarantine_role_id.is_none() {
+                    problems.push(format!("{location} needs a `quarantine_role_id`"));
+                }
+                if timeout_secs.is_some_and(|secs| secs == 0 || secs > MAX_TIMEOUT.as_secs()) {
+                    problems.push(format!(
+                        "{location} has a `timeout_secs` outside of 1 second to 28 days"
+                    ));
+                }
+            };
+
+        for honeypot_channel in &self.honeypot_channels {
+            let location = format!("Honeypot channel `{}`", honeypot_channel.channel_id);
+            let responses = [
+                Some(honeypot_channel.response),
+                honeypot_channel.new_account_response,
+                honeypot_channel.established_member_response,
+            ];
+            for response in responses.into_iter().flatten() {
+                check_response(
+                    &location,
+                    response,
+                    honeypot_channel.timeout_secs,
+                    honeypot_channel.quarantine_role_id,
+                );
+            }
+        }
+        for honeypot_role in &self.honeypot_roles {
+            check_response(
+                &format!("Honeypot role `{}`", honeypot_role.role_id),
+                honeypot_role.response,
+                honeypot_role.timeout_secs,
+                honeypot_role.quarantine_role_id,
+            );
+        }
+        if let Some(burst_detection) = &self.burst_detection {
+            check_response(
+                "Burst detection",
+                burst_detection.response,
+                burst_detection.timeout_secs,
+                burst_detection.quarantine_role_id,
+            );
+        }
+        for (index, rule) in self.content_rules.iter().enumerate() {
+            check_response(
+                &format!("Content rule {}", index + 1),
+                rule.response,
+                rule.timeout_secs,
+                rule.quarantine_role_id,
+            );
+        }
+
+        let mut channel_ids = HashSet::new();
+        for honeypot_channel in &self.honeypot_channels {
+            let location = format!("Honeypot channel `{}`", honeypot_channel.channel_id);
+            if !channel_ids.insert(honeypot_channel.channel_id) {
+                problems.push(format!("{location} is listed more than once"));
+            }
+            if honeypot_channel.triggers.is_empty()
+                || honeypot_channel
+                    .triggers
+                    .iter()
+                    .any(|trigger_kind| !TriggerKind::CHANNEL.contains(trigger_kind))
+            {
+                problems.push(format!(
+                    "{location} needs `triggers` from `message`, `reaction`, `thread` and `voice`"
+                ));
+            }
+            if honeypot_channel.delete_message_days > 7 {
+                problems.push(format!("{location} has a `delete_message_days` over 7"));
+            }
+            if out_of_purge_range(honeypot_channel.purge_secs) {
+                problems.push(format!(
+                    "{location} has a `purge_secs` outside of 1 second to 14 days"
+                ));
+            }
+            let age_rules = [
+                (
+                    honeypot_channel.new_account_age_secs.is_some(),
+                    honeypot_channel.new_account_response.is_some(),
+                    "new_account",
+                ),
+                (
+                    honeypot_channel.established_member_age_secs.is_some(),
+                    honeypot_channel.established_member_response.is_some(),
+                    "established_member",
+                ),
+            ];
+            for (has_age, has_response, name) in age_rules {
+                if has_age != has_response {
+                    problems.push(format!(
+                        "{location} needs both or neither of `{name}_age_secs` and \
+                         `{name}_response`"
+                    ));
+                }
+            }
+        }
+        let mut role_ids = HashSet::new();
+        for honeypot_role in &self.honeypot_roles {
+            let location = format!("Honeypot role `{}`", honeypot_role.role_id);
+            if !role_ids.insert(honeypot_role.role_id) {
+                problems.push(format!("{location} is listed more than once"));
+            }
+            if honeypot_role.delete_message_days > 7 {
+                problems.push(format!("{location} has a `delete_message_days` over 7"));
+            }
+            if out_of_purge_range(honeypot_role.purge_secs) {
+                problems.push(format!(
+                    "{location} has a `purge_secs` outside of 1 second to 14 days"
+                ));
+            }
+        }
+        let mut templates = HashSet::new();
+        for template in &self.message_templates {
+            if !templates.insert((template.channel_id, template.kind as i64)) {
+                problems.push(format!(
+                    "The `{}` template{} is listed more than once",
+                    template.kind.name(),
+                    template
+                        .channel_id
+                        .map(|channel_id| format!(" of channel `{channel_id}`"))
+                        .unwrap_or_default()
+                ));
+            }
+        }
+        if let Some(escalation) = &self.escalation_policy {
+            if escalation.ladder.is_empty() {
+                problems.push("The escalation policy's `ladder` is empty".to_string());
+            }
+            if escalation.ladder.contains(&MessageResponse::Quarantine) {
+                problems.push(
+                    "The escalation policy's `ladder` can't use `quarantine`, since escalation \
+                    policies don't have a quarantine role"
+                        .to_string(),
+                );
+            }
+        }
+        if let Some(escalation) = &self.escalation_policy
+            && escalation.decay_secs == 0
+        {
+            problems.push("The escalation policy needs a `decay_secs` of at least 1".to_string());
+        }
+        if let Some(burst_detection) = &self.burst_detection {
+            if !(2..=MAX_CHANNEL_COUNT).contains(&burst_detection.channel_count) {
+                problems.push(format!(
+                    "Burst detection has a `channel_count` outside of 2 to {MAX_CHANNEL_COUNT}"
+                ));
+            }
+            if !(1..=MAX_WINDOW.as_secs()).contains(&burst_detection.window_secs) {
+                problems.push(format!(
+                    "Burst detection has a `window_secs` outside of 1 to {}",
+                    MAX_WINDOW.as_secs()
+                ));
+            }
+        }
+        for (index, rule) in self.content_rules.iter().enumerate() {
+            if rule.kind != ContentRuleKind::Invite
+                && let Err(why) = CompiledPattern::new(rule.kind, &rule.pattern)
+            {
+                problems.push(format!("Content rule {}: {why}", index + 1));
+            }
+        }
+        problems
+    }
+
+    /// Checks that the channels and roles in the document exist in the guild it's imported into.
+    /// Exempt users aren't checked, since they may have left or not joined yet.
+    pub fn missing_ids(
+        &self,
+        guild_channel_ids: &HashSet<serenity::ChannelId>,
+        guild_role_ids: &HashSet<serenity::RoleId>,
+    ) -> Vec<String> {
+        let mut channel_ids: Vec<(serenity::ChannelId, &str)> = vec![];
+        if let Some(logging_channel_id) = self.logging_channel_id {
+            channel_ids.push((logging_channel_id, "Logging channel"));
+        }
+        for honeypot_channel in &self.honeypot_channels {
+            channel_ids.push((honeypot_channel.channel_id, "Honeypot channel"));
+        }
+        for template in &self.message_templates {
+            if let Some(channel_id) = template.channel_id {
+                channel_ids.push((channel_id, "Template channel"));
+            }
+        }
+
+        let mut role_ids: Vec<(serenity::RoleId, &str)> = vec![];
+        for honeypot_role in &self.honeypot_roles {
+            role_ids.push((honeypot_role.role_id, "Honeypot role"));
+        }
+        for role_id in &self.exemptions.roles {
+            role_ids.push((*role_id, "Exempt role"));
+        }
+        let quarantine_role_ids = self
+            .honeypot_channels
+            .iter()
+            .map(|honeypot_channel| honeypot_channel.quarantine_role_id)
+            .chain(
+                self.honeypot_roles
+                    .iter()
+                    .map(|honeypot_role| honeypot_role.quarantine_role_id),
+            )
+            .chain(
+                self.burst_detection
+                    .iter()
+                    .map(|burst_detection| burst_detection.quarantine_role_id),
+            )
+            .chain(
+                self.content_rules
+                    .iter()
+                    .map(|rule| rule.quarantine_role_id),
+            );
+        for role_id in quarantine_role_ids.flatten() {
+            role_ids.push((role_id, "Quarantine role"));
+        }
+
+        let missing_channels = channel_ids
+            .into_iter()
+            .filter(|(channel_id, _)| !guild_channel_ids.contains(channel_id))
+            .map(|(channel_id, name)| format!("{name} `{channel_id}` isn't in this server"));
+        let missing_roles = role_ids
+            .into_iter()
+            .filter(|(role_id, _)| !guild_role_ids.contains(role_id))
+            .map(|(role_id, name)| format!("{name} `{role_id}` isn't in this server"));
+        // IDs used in several places are reported once per name, in the order they're first used.
+        let mut reported = HashSet::new();
+        missing_channels
+            .chain(missing_roles)
+            .filter(|problem| reported.insert(problem.clone()))
+            .collect()
+    }
+
+    /// Flattens the document into one line per setting, keyed so that the same setting in two
+    /// documents has the same key.
+    fn entries(&self) -> BTreeMap<String, String> {
+        let mut entries = BTreeMap::new();
+        if let Some(logging_channel_id) = self.logging_channel_id {
+            entries.insert(
+                "logging channel".to_string(),
+                logging_channel_id.to_string(),
+            );
+        }
+        entries.insert("settings".to_string(), to_json(&self.settings));
+        for honeypot_channel in &self.honeypot_channels {
+            entries.insert(
+                format!("honeypot channel {}", honeypot_channel.channel_id),
+                to_json(honeypot_channel),
+            );
+        }
+        for honeypot_role in &self.honeypot_roles {
+            entries.insert(
+                format!("honeypot role {}", honeypot_role.role_id),
+                to_json(honeypot_role),
+            );
+        }
+        for template in &self.message_templates {
+            let scope = match template.channel_id {
+                Some(channel_id) => format!("channel {channel_id}"),
+                None => "server".to_string(),
+            };
+            entries.insert(
+                format!("{} template for {scope}", template.kind.name()),
+                to_json(&template.template),
+            );
+        }
+        if let Some(escalation) = &self.escalation_policy {
+            entries.insert("escalation policy".to_string(), to_json(escalation));
+        }
+        for user_id in &self.exemptions.users {
+            entries.insert(format!("exempt user {user_id}"), String::new());
+        }
+        for role_id in &self.exemptions.roles {
+            entries.insert(format!("exempt role {role_id}"), String::new());
+        }
+        if let Some(burst_detection) = &self.burst_detection {
+            entries.insert("burst detection".to_string(), to_json(burst_detection));
+        }
+        // Rules have no stable key, so an edited rule shows up as removed and added.
+        for rule in &self.content_rules {
+            entries.insert(format!("content rule {}", to_json(rule)), String::new());
+        }
+        entries
+    }
+
+    /// Describes every setting, one line each.
+    pub fn summary(&self) -> Vec<String> {
+        self.entries()
+            .iter()
+            .map(|(key, value)| entry_line(key, value))
+            .collect()
+    }
+
+    /// Describes what importing `self` over `current` would change, one line per added (`+`)
+    /// or removed (`-`) setting. Changed settings are shown as removed then added.
+    pub fn diff(&self, current: &GuildConfig) -> Vec<String> {
+        let old = current.entries();
+        let new = self.entries();
+        let line =
+            |sign: char, key: &str, value: &str| format!("{sign} {}", entry_line(key, value));
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        let mut lines = vec![];
+        for key in keys {
+            match (old.get(key), new.get(key)) {
+                (Some(old_value), Some(new_value)) if old_value == new_value => (),
+                (old_value, new_value) => {
+                    if let Some(old_value) = old_value {
+                        lines.push(line('-', key, old_value));
+                    }
+                    if let Some(new_value) = new_value {
+                        lines.push(line('+', key, new_value));
+                    }
+                }
+            }
+        }
+        lines
+    }
+}
+
+fn entry_line(key: &str, value: &str) -> String {
+    match value {
+        "" => key.to_string(),
+        value => format!("{key}: {value}"),
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).expect("guild config sections should always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use serial_test::serial;
+
+    use crate::datastore::{test_utils::test_each_backend, traits::DatastoreWriter};
+
+    use super::*;
+
+    /// Uses every section, in the order `export` lists them.
+    const FULL_CONFIG: &str = r#"{
+        "version": 1,
+        "logging_channel_id": "100",
+        "settings": {"exemption_mode": "ignore", "exempt_moderators": true, "dry_run": false},
+        "honeypot_channels": [
+            {
+                "channel_id": "101",
+                "response": "timeout",
+                "triggers": ["message", "reaction"],
+                "timeout_secs": 3600,
+                "delete_message": true,
+                "purge_secs": 600,
+                "delete_message_days": 1,
+                "reason": "Posted in {channel}",
+                "new_account_age_secs": 86400,
+                "new_account_response": "ban",
+                "dry_run": true
+            },
+            {"channel_id": "102", "response": "quarantine", "quarantine_role_id": "200"}
+        ],
+        "honeypot_roles": [{"role_id": "201", "response": "kick", "purge_secs": 60}],
+        "message_templates": [
+            {"kind": "log", "template": "{user} was caught"},
+            {"channel_id": "101", "kind": "warning", "template": "Don't post here"}
+        ],
+        "escalation_policy": {"ladder": ["timeout", "kick", "ban"], "decay_secs": 604800},
+        "exemptions": {"users": ["300"], "roles": ["202"]},
+        "burst_detection": {
+            "channel_count": 3,
+            "window_secs": 30,
+            "response": "ban",
+            "delete_messages": true
+        },
+        "content_rules": [
+            {"kind": "keyword", "pattern": "free nitro", "response": "ban", "reason": "Scam"}
+        ]
+    }"#;
+
+    fn guild_config(json: &str) -> GuildConfig {
+        GuildConfig::parse(json).unwrap()
+    }
+
+    #[test]
+    fn parse_checks_the_version() {
+        assert!(GuildConfig::parse(FULL_CONFIG).is_ok());
+        assert_eq!(
+            GuildConfig::parse(r#"{"version": 2}"#),
+            Err(
+                "Version 2 documents aren't supported, this version of the bot reads version 1"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            GuildConfig::parse(r#"{"honeypot_channels": []}"#),
+            Err("The document is missing its `version`".to_string())
+        );
+        assert_eq!(
+            GuildConfig::parse(r#"{"version": "1"}"#),
+            Err("The document is missing its `version`".to_string())
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert_eq!(guild_config(FULL_CONFIG).validate(), Vec::<String>::new());
+
+        let mut guild_config = guild_config(FULL_CONFIG);
+        guild_config.escalation_policy.as_mut().unwrap().ladder = vec![];
+        guild_config.honeypot_channels[1].quarantine_role_id = None;
+        assert_eq!(
+            guild_config.validate(),
+            vec![
+                "Honeypot channel `102` needs a `quarantine_role_id`",
+                "The escalation policy's `ladder` is empty",
+            ]
+        );
+
+        let mut quarantine_ladder = self::guild_config(FULL_CONFIG);
+        quarantine_ladder.escalation_policy.as_mut().unwrap().ladder =
+            vec![MessageResponse::Timeout, MessageResponse::Quarantine];
+        assert_eq!(
+            quarantine_ladder.validate(),
+            vec![
+                "The escalation policy's `ladder` can't use `quarantine`, since escalation \
+                policies don't have a quarantine role"
+            ]
+        );
+
+        // The bounds of the commands' options are checked too
+        let mut out_of_bounds = self::guild_config(FULL_CONFIG);
+        out_of_bounds.honeypot_channels[0].purge_secs = Some(15 * 24 * 60 * 60);
+        out_of_bounds.honeypot_roles[0].purge_secs = Some(0);
+        out_of_bounds.escalation_policy.as_mut().unwrap().decay_secs = 0;
+        let burst_detection = out_of_bounds.burst_detection.as_mut().unwrap();
+        burst_detection.channel_count = 51;
+        burst_detection.window_secs = 3600;
+        assert_eq!(
+            out_of_bounds.validate(),
+            vec![
+                "Honeypot channel `101` has a `purge_secs` outside of 1 second to 14 days",
+                "Honeypot role `201` has a `purge_secs` outside of 1 second to 14 days",
+                "The escalation policy needs a `decay_secs` of at least 1",
+                "Burst detection has a `channel_count` outside of 2 to 50",
+                "Burst detection has a `window_secs` outside of 1 to 600",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_ids() {
+        let guild_config = guild_config(FULL_CONFIG);
+        let channel_ids = HashSet::from([100, 101, 102].map(serenity::ChannelId::new));
+        let role_ids = HashSet::from([200, 201, 202].map(serenity::RoleId::new));
+        assert_eq!(
+            guild_config.missing_ids(&channel_ids, &role_ids),
+            Vec::<String>::new()
+        );
+
+        // Exempt users aren't checked
+        let channel_ids = HashSet::from([100, 102].map(serenity::ChannelId::new));
+        let role_ids = HashSet::from([200, 201].map(serenity::RoleId::new));
+        assert_eq!(
+            guild_config.missing_ids(&channel_ids, &role_ids),
+            vec![
+                "Honeypot channel `101` isn't in this server",
+                "Template channel `101` isn't in this server",
+                "Exempt role `202` isn't in this server",
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_ids_are_reported_once() {
+        // The same quarantine role is used before and after another one
+        let guild_config = guild_config(
+            r#"{
+                "version": 1,
+                "honeypot_channels": [
+                    {"channel_id": "1", "response": "quarantine", "quarantine_role_id": "10"},
+                    {"channel_id": "2", "response": "quarantine", "quarantine_role_id": "20"}
+                ],
+                "honeypot_roles": [
+                    {"role_id": "30", "response": "quarantine", "quarantine_role_id": "10"}
+                ]
+            }"#,
+        );
+        let channel_ids = HashSet::from([serenity::ChannelId::new(1), serenity::ChannelId::new(2)]);
+        let role_ids = HashSet::from([serenity::RoleId::new(30)]);
+        assert_eq!(
+            guild_config.missing_ids(&channel_ids, &role_ids),
+            vec![
+                "Quarantine role `10` isn't in this server",
+                "Quarantine role `20` isn't in this server",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let guild_config = guild_config(FULL_CONFIG);
+        assert_eq!(
+            GuildConfig::parse(&guild_config.to_json()),
+            Ok(guild_config)
+        );
+    }
+
+    #[test]
+    fn diff() {
+        let current = guild_config(FULL_CONFIG);
+        assert_eq!(current.diff(&current), Vec::<String>::new());
+
+        let mut new = current.clone();
+        new.settings.dry_run = true;
+        new.honeypot_roles.clear();
+        new.exemptions.users.push(serenity::UserId::new(301));
+        assert_eq!(
+            new.diff(&current),
+            vec![
+                "+ exempt user 301",
+                r#"- honeypot role 201: {"role_id":"201","response":"kick","purge_secs":60,"delete_message_days":7}"#,
+                r#"- settings: {"exemption_mode":"ignore","exempt_moderators":true,"dry_run":false}"#,
+                r#"+ settings: {"exemption_mode":"ignore","exempt_moderators":true,"dry_run":true}"#,
+            ]
+        );
+    }
+
+    test_each_backend! {
+        async fn export_after_import_round_trips(db) {
+            let guild_id =
+                serenity::GuildId::new(serenity::Timestamp::now().timestamp_millis() as u64);
+            let guild_config = guild_config(FULL_CONFIG);
+            db.replace_guild_configs(guild_id, &guild_config)
+                .await
+                .unwrap();
+            assert_eq!(export(&db, guild_id).await, Ok(guild_config));
+
+            // Clean up rows:
+            db.delete_guild_configs(guild_id).await.unwrap();
+        }
+    }
+}
@@ -1,7 +1,17 @@
+mod action_log;
+mod burst_detector;
+mod cleanup;
+mod cli;
 mod commands;
+mod content_rules;
 mod context_data;
 mod datastore;
 mod event_handler;
+mod guild_config;
+mod permissions;
+mod templates;
+mod trigger;
+mod utils;
 
 use clap::Parser;
 use std::sync::Arc;
@@ -11,20 +21,35 @@ use poise::serenity_prelude::{self as serenity, Error};
 
 use crate::{
     context_data::ContextData,
-    datastore::{Datastore, DatastoreOptions, database::DatabaseOptions},
+    datastore::{
+        Datastore, DatastoreOptions,
+        backend::{Backend, BackendOptions},
+    },
     event_handler::HoneybotEventHandler,
 };
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    /// Path to sqlite db file
+    /// Path to sqlite db file, or a `postgres://` URL. Takes precedence over `DATABASE_URL`
     #[arg(short, long)]
     db_path: Option<String>,
 
-    /// Path to migrations directory
+    /// Directory to read migrations from instead of the ones built into the binary
     #[arg(short, long)]
     migrations_path: Option<String>,
+
+    /// Don't apply pending migrations, and fail if there are any
+    #[arg(long, conflicts_with = "migrate_only")]
+    no_migrate: bool,
+
+    /// Apply pending migrations and exit without starting the bot
+    #[arg(long)]
+    migrate_only: bool,
+
+    /// Run an admin command instead of starting the bot
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[tokio::main]
@@ -35,26 +60,66 @@ async fn main() {
     dotenv().ok();
     let args = Args::parse();
 
+    let backend_options = BackendOptions {
+        url: args
+            .db_path
+            .or(std::env::var("DATABASE_URL").ok())
+            .unwrap_or("honeybot.db".to_string()),
+        migrations_path: args.migrations_path,
+        migrate: !args.no_migrate,
+    };
+
+    // Admin commands work on the database alone, so they run even when Discord can't be reached.
+    let command = match args.migrate_only {
+        true => Some(cli::Command::Migrate),
+        false => args.command,
+    };
+    if let Some(command) = command {
+        let database = Backend::new(&backend_options)
+            .await
+            .unwrap_or_else(|why| exit_with_error(why));
+        if let Err(why) = cli::run(command, &database).await {
+            exit_with_error(why);
+        }
+        return;
+    }
+
     let datastore = Arc::new(
         Datastore::new_with_options(&DatastoreOptions {
-            database_options: DatabaseOptions {
-                filename: args.db_path.unwrap_or("honeybot.db".to_string()),
-                migrations_path: args.migrations_path.unwrap_or("./migrations".to_string()),
-            },
+            backend_options,
             cache_options: Default::default(),
         })
-        .await,
+        .await
+        .unwrap_or_else(|why| exit_with_error(why)),
     );
 
     // Poise boilerplate to configure bot:
     let token = std::env::var("DISCORD_TOKEN").expect("missing DISCORD_TOKEN");
-    let intents = serenity::GatewayIntents::non_privileged();
+    // Message content is needed to show the offending message in the logging channel, and guild
+    // members to see members getting honeypot roles.
+    let intents = serenity::GatewayIntents::non_privileged()
+        | serenity::GatewayIntents::MESSAGE_CONTENT
+        | serenity::GatewayIntents::GUILD_MEMBERS;
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands: vec![
                 commands::listen(),
                 commands::unlisten(),
+                commands::listen_role(),
+                commands::unlisten_role(),
                 commands::logging_channel(),
+                commands::release(),
+                commands::message_template(),
+                commands::escalation(),
+                commands::incidents(),
+                commands::pardon(),
+                commands::exempt(),
+                commands::burst_detection(),
+                commands::rule(),
+                commands::dry_run(),
+                commands::honeypots(),
+                commands::honeybot(),
+                commands::config(),
             ],
             event_handler: |ctx, event, framework, data| {
                 Box::pin(event_handler(ctx, event, framework, data))
@@ -89,6 +154,11 @@ async fn main() {
     client.unwrap().start().await.unwrap();
 }
 
+fn exit_with_error(why: impl std::fmt::Display) -> ! {
+    eprintln!("{why}");
+    std::process::exit(1);
+}
+
 async fn event_handler(
     ctx: &serenity::Context,
     event: &serenity::FullEvent,
@@ -99,7 +169,11 @@ async fn event_handler(
         .clone()
         .dispatch(
             ctx.clone(),
-            &HoneybotEventHandler::new(data.datastore.clone()),
+            &HoneybotEventHandler::new(
+                data.datastore.clone(),
+                data.burst_detector.clone(),
+                data.content_rule_matcher.clone(),
+            ),
         )
         .await;
     Ok(())
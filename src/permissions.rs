@@ -0,0 +1,326 @@
+use std::fmt;
+
+use poise::serenity_prelude::{self as serenity};
+
+use crate::datastore::models::{MessageResponse, MessageResponseConfig, TriggerKind};
+
+/// What the bot needs to post action logs in the logging channel.
+pub const LOGGING_CHANNEL_PERMISSIONS: serenity::Permissions = serenity::Permissions::VIEW_CHANNEL
+    .union(serenity::Permissions::SEND_MESSAGES)
+    .union(serenity::Permissions::EMBED_LINKS);
+
+/// How many roles above the bot are named before the rest are summarized.
+const MAX_NAMED_ROLES: usize = 5;
+
+/// Something that will stop the bot from doing its job, and how to fix it.
+pub struct Problem {
+    pub description: String,
+    pub fix: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "⚠️ {}. {}", self.description, self.fix)
+    }
+}
+
+/// What the bot needs to take a response.
+pub fn response_permissions(response: MessageResponse) -> serenity::Permissions {
+    match response {
+        MessageResponse::Ban | MessageResponse::SoftBan => serenity::Permissions::BAN_MEMBERS,
+        MessageResponse::Kick => serenity::Permissions::KICK_MEMBERS,
+        MessageResponse::Timeout => serenity::Permissions::MODERATE_MEMBERS,
+        MessageResponse::Quarantine => serenity::Permissions::MANAGE_ROLES,
+        MessageResponse::Respond => serenity::Permissions::SEND_MESSAGES,
+        MessageResponse::Nothing => serenity::Permissions::empty(),
+    }
+}
+
+/// Every response a honeypot channel can take, including those picked by age rules.
+fn channel_responses(config: &MessageResponseConfig) -> Vec<MessageResponse> {
+    [
+        Some(config.response),
+        config.new_account_response,
+        config.established_member_response,
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// What the bot needs in a honeypot channel to see triggers, clean up after them, and take any
+/// of the channel's responses.
+pub fn channel_permissions(config: &MessageResponseConfig) -> serenity::Permissions {
+    let mut permissions = serenity::Permissions::VIEW_CHANNEL;
+    for response in channel_responses(config) {
+        permissions |= response_permissions(response);
+    }
+    if config.delete_message {
+        for trigger_kind in &config.trigger_kinds {
+            permissions |= match trigger_kind {
+                TriggerKind::Thread => serenity::Permissions::MANAGE_THREADS,
+                TriggerKind::Voice => serenity::Permissions::MOVE_MEMBERS,
+                _ => serenity::Permissions::MANAGE_MESSAGES,
+            };
+        }
+    }
+    if config.purge_window.is_some() {
+        permissions |=
+            serenity::Permissions::MANAGE_MESSAGES | serenity::Permissions::READ_MESSAGE_HISTORY;
+    }
+    permissions
+}
+
+/// Looks up the bot's own member in the guild.
+pub async fn bot_member(
+    ctx: &serenity::Context,
+    guild: &serenity::Guild,
+) -> Option<serenity::Member> {
+    let bot_id = ctx.cache.current_user().id;
+    match guild.member(ctx, bot_id).await {
+        Ok(member) => Some(member.into_owned()),
+        Err(why) => {
+            tracing::warn!(
+                "Error fetching the bot's member in guild `{}`: {why:?}",
+                guild.id
+            );
+            None
+        }
+    }
+}
+
+/// Checks that a honeypot channel still exists, that the bot can take the channel's responses
+/// in it, and that the bot's role is high enough to act on members.
+pub fn check_honeypot_channel(
+    guild: &serenity::Guild,
+    bot_member: &serenity::Member,
+    config: &MessageResponseConfig,
+) -> Vec<Problem> {
+    let channel_id = config.channel_id;
+    let Some(channel) = guild.channels.get(&channel_id) else {
+        return vec![Problem {
+            description: format!("Honeypot channel <#{channel_id}> no longer exists"),
+            fix: "Remove it with `/unlisten`".to_string(),
+        }];
+    };
+    let mut required = channel_permissions(config);
+    // The warning banner is posted when the channel is listened to.
+    if matches!(
+        channel.kind,
+        serenity::ChannelType::Text | serenity::ChannelType::News | serenity::ChannelType::Voice
+    ) {
+        required |= serenity::Permissions::SEND_MESSAGES;
+    }
+    let mut problems = vec![];
+    let missing = required - guild.user_permissions_in(channel, bot_member);
+    if !missing.is_empty() {
+        problems.push(Problem {
+            description: format!(
+                "The bot is missing {} in <#{channel_id}>",
+                describe_permissions(missing)
+            ),
+            fix: "Grant them to the bot's role in the server's role settings or the channel's \
+                  permission overrides"
+                .to_string(),
+        });
+    }
+    problems.extend(check_hierarchy(
+        guild,
+        bot_member,
+        &channel_responses(config),
+        config.quarantine_role_id,
+    ));
+    problems
+}
+
+/// Checks that the bot has the server-wide permissions for responses that don't happen in a
+/// single channel, like honeypot roles, content rules and burst detection.
+pub fn check_responses(
+    guild: &serenity::Guild,
+    bot_member: &serenity::Member,
+    responses: &[MessageResponse],
+    quarantine_role_id: Option<serenity::RoleId>,
+    delete_messages: bool,
+) -> Vec<Problem> {
+    let mut required = responses
+        .iter()
+        .fold(serenity::Permissions::empty(), |required, response| {
+            required | response_permissions(*response)
+        })
+        // Replies are posted in whichever channel the trigger happened in.
+        - serenity::Permissions::SEND_MESSAGES;
+    if delete_messages {
+        required |= serenity::Permissions::MANAGE_MESSAGES;
+    }
+    let mut problems = vec![];
+    let missing = required - guild.member_permissions(bot_member);
+    if !missing.is_empty() {
+        problems.push(Problem {
+            description: format!("The bot is missing {}", describe_permissions(missing)),
+            fix: "Grant them to the bot's role in the server's role settings".to_string(),
+        });
+    }
+    problems.extend(check_hierarchy(
+        guild,
+        bot_member,
+        responses,
+        quarantine_role_id,
+    ));
+    problems
+}
+
+/// Checks that the logging channel still exists and that the bot can post embeds in it.
+pub fn check_logging_channel(
+    guild: &serenity::Guild,
+    bot_member: &serenity::Member,
+    logging_channel_id: serenity::ChannelId,
+) -> Vec<Problem> {
+    let Some(channel) = guild.channels.get(&logging_channel_id) else {
+        return vec![Problem {
+            description: format!("Logging channel <#{logging_channel_id}> no longer exists"),
+            fix: "Pick a new one with `/logging_channel`".to_string(),
+        }];
+    };
+    let missing = LOGGING_CHANNEL_PERMISSIONS - guild.user_permissions_in(channel, bot_member);
+    if missing.is_empty() {
+        return vec![];
+    }
+    vec![Problem {
+        description: format!(
+            "The bot is missing {} in logging channel <#{logging_channel_id}>",
+            describe_permissions(missing)
+        ),
+        fix: "Grant them to the bot's role in the channel's permission overrides".to_string(),
+    }]
+}
+
+/// Checks that the bot's highest role is above the quarantine role, and above the roles regular
+/// members have, since Discord doesn't let bots act on members with a role as high as theirs.
+fn check_hierarchy(
+    guild: &serenity::Guild,
+    bot_member: &serenity::Member,
+    responses: &[MessageResponse],
+    quarantine_role_id: Option<serenity::RoleId>,
+) -> Vec<Problem> {
+    let acts_on_members = responses.iter().any(|response| {
+        !matches!(
+            response,
+            MessageResponse::Respond | MessageResponse::Nothing
+        )
+    });
+    if !acts_on_members {
+        return vec![];
+    }
+    let bot_position = guild
+        .member_highest_role(bot_member)
+        .map_or(0, |role| role.position);
+    let mut problems = vec![];
+
+    if responses.contains(&MessageResponse::Quarantine)
+        && let Some(quarantine_role) =
+            quarantine_role_id.and_then(|role_id| guild.roles.get(&role_id))
+        && quarantine_role.position >= bot_position
+    {
+        problems.push(Problem {
+            description: format!(
+                "Quarantine role <@&{}> is not below the bot's highest role",
+                quarantine_role.id
+            ),
+            fix: "Drag the bot's role above it in the server's role settings".to_string(),
+        });
+    }
+
+    // Moderator roles are expected to be above the bot, they're not who honeypots are for.
+    let mut roles_above: Vec<&serenity::Role> = guild
+        .roles
+        .values()
+        .filter(|role| {
+            role.position >= bot_position
+                && role.id != guild.id.everyone_role()
+                && !role.managed
+                && !bot_member.roles.contains(&role.id)
+                && !role.permissions.administrator()
+                && !role.permissions.ban_members()
+                && !role.permissions.kick_members()
+                && !role.permissions.moderate_members()
+                && !role.permissions.manage_messages()
+        })
+        .collect();
+    if !roles_above.is_empty() {
+        roles_above.sort_by_key(|role| std::cmp::Reverse(role.position));
+        let mut names = roles_above
+            .iter()
+            .take(MAX_NAMED_ROLES)
+            .map(|role| format!("<@&{}>", role.id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if roles_above.len() > MAX_NAMED_ROLES {
+            names += &format!(" and {} more", roles_above.len() - MAX_NAMED_ROLES);
+        }
+        problems.push(Problem {
+            description: format!(
+                "Members with {names} can't be acted on, the roles aren't below the bot's \
+                 highest role"
+            ),
+            fix: "Drag the bot's role above every role regular members can get".to_string(),
+        });
+    }
+    problems
+}
+
+/// Explains why the bot couldn't take a response on a user, for the logging channel. Returns
+/// `None` if the bot has the permission and is above the user, so the cause is something else.
+pub fn diagnose_failure(
+    guild: &serenity::Guild,
+    bot_member: &serenity::Member,
+    channel_id: Option<serenity::ChannelId>,
+    target: Option<&serenity::Member>,
+    response: MessageResponse,
+    quarantine_role_id: Option<serenity::RoleId>,
+) -> Option<String> {
+    let permissions = match channel_id.and_then(|channel_id| guild.channels.get(&channel_id)) {
+        Some(channel) => guild.user_permissions_in(channel, bot_member),
+        None => guild.member_permissions(bot_member),
+    };
+    let missing = response_permissions(response) - permissions;
+    if !missing.is_empty() {
+        return Some(format!(
+            "the bot is missing {}",
+            describe_permissions(missing)
+        ));
+    }
+    let bot_position = guild
+        .member_highest_role(bot_member)
+        .map_or(0, |role| role.position);
+    if response == MessageResponse::Quarantine
+        && let Some(quarantine_role) =
+            quarantine_role_id.and_then(|role_id| guild.roles.get(&role_id))
+        && quarantine_role.position >= bot_position
+    {
+        return Some(format!(
+            "quarantine role <@&{}> is not below the bot's highest role",
+            quarantine_role.id
+        ));
+    }
+    let target = target?;
+    if target.user.id == guild.owner_id {
+        return Some("the user owns the server".to_string());
+    }
+    match guild.member_highest_role(target) {
+        Some(role) if role.position >= bot_position => Some(format!(
+            "the user's highest role <@&{}> is not below the bot's highest role",
+            role.id
+        )),
+        _ => None,
+    }
+}
+
+/// Names the permissions, e.g. "the Ban Members and Kick Members permissions".
+fn describe_permissions(permissions: serenity::Permissions) -> String {
+    let names = permissions.get_permission_names();
+    match names.as_slice() {
+        [name] => format!("the {name} permission"),
+        [rest @ .., last] => format!("the {} and {last} permissions", rest.join(", ")),
+        [] => "no permissions".to_string(),
+    }
+}
@@ -0,0 +1,117 @@
+use poise::serenity_prelude::{self as serenity};
+
+use crate::{
+    datastore::{
+        Datastore,
+        errors::Error,
+        models::{MessageResponse, TemplateKind},
+        traits::DatastoreReader,
+    },
+    utils::render_template,
+};
+
+pub const PLACEHOLDERS_HELP: &str = "`{user}` (user mention), `{channel}` (channel mention) and \
+    `{action}` (e.g. \"banned\")";
+
+pub fn default_template(kind: TemplateKind) -> &'static str {
+    match kind {
+        TemplateKind::Reply => "Are you lost? You shouldn't be in this channel...",
+        TemplateKind::Warning => "**Do not** post in this channel unless you want to be {action}",
+        TemplateKind::Log => {
+            "User {user} was {action} for posting in the honeypot channel {channel}."
+        }
+    }
+}
+
+/// Returns the template set for the channel, falling back to the guild-wide template and then the
+/// default template.
+pub async fn resolve_template(
+    datastore: &Datastore,
+    guild_id: serenity::GuildId,
+    channel_id: Option<serenity::ChannelId>,
+    kind: TemplateKind,
+) -> String {
+    let mut scopes = vec![None];
+    if channel_id.is_some() {
+        scopes.insert(0, channel_id);
+    }
+    for channel_id in scopes {
+        match datastore
+            .get_message_template(guild_id, channel_id, kind)
+            .await
+        {
+            Ok(template) => return template,
+            Err(Error::DatabaseEntryNotFound) => (),
+            Err(why) => {
+                tracing::error!("Error retrieving {kind:?} template from database: {why:?}");
+                break;
+            }
+        }
+    }
+    default_template(kind).to_string()
+}
+
+pub fn render(
+    template: &str,
+    user_id: serenity::UserId,
+    channel_id: serenity::ChannelId,
+    response: MessageResponse,
+) -> String {
+    render_at(template, user_id, &format!("<#{channel_id}>"), response)
+}
+
+/// What `{user}` is replaced with in the warning banner, which isn't aimed at anyone in
+/// particular.
+const WARNING_USER: &str = "everyone";
+
+/// Renders the warning banner posted by `/listen`. There's no poster yet, so `{user}` doesn't
+/// mention anyone.
+pub fn render_warning(
+    template: &str,
+    channel_id: serenity::ChannelId,
+    response: MessageResponse,
+) -> String {
+    render_template(
+        template,
+        &[
+            ("user", WARNING_USER),
+            ("channel", &format!("<#{channel_id}>")),
+            ("action", response.past_tense()),
+        ],
+    )
+}
+
+/// Renders a template where `{channel}` is replaced with `location`, which is the honeypot role
+/// for role triggers.
+pub fn render_at(
+    template: &str,
+    user_id: serenity::UserId,
+    location: &str,
+    response: MessageResponse,
+) -> String {
+    render_template(
+        template,
+        &[
+            ("user", &format!("<@{user_id}>")),
+            ("channel", location),
+            ("action", response.past_tense()),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_warning() {
+        assert_eq!(
+            super::render_warning(
+                "{user}: don't post in {channel} or you'll be {action}",
+                serenity::ChannelId::new(1),
+                MessageResponse::Ban
+            ),
+            "everyone: don't post in <#1> or you'll be banned"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use poise::serenity_prelude::{self as serenity};
+
+use crate::datastore::models::TriggerKind;
+
+/// Threads older than this when the bot hears about them weren't just created, e.g. the bot was
+/// added to an existing private thread.
+const NEW_THREAD_MAX_AGE_SECS: i64 = 60;
+
+/// Something a user did in a honeypot channel or with a honeypot role, or a message burst or
+/// content rule match, with everything the response pipeline needs to
+/// know about it.
+pub struct Trigger {
+    pub kind: TriggerKind,
+    /// The honeypot channel, which is the parent channel for threads and forum posts. `None` for
+    /// role triggers.
+    pub channel_id: Option<serenity::ChannelId>,
+    /// The honeypot role, for role triggers
+    pub role_id: Option<serenity::RoleId>,
+    pub user: serenity::User,
+    pub role_ids: Vec<serenity::RoleId>,
+    pub joined_at: Option<serenity::Timestamp>,
+    pub permissions: Option<serenity::Permissions>,
+    /// The message content, thread name, emoji or role name
+    pub content: String,
+    pub attachments: Vec<serenity::Attachment>,
+    pub timestamp: serenity::Timestamp,
+    source: TriggerSource,
+}
+
+// Boxed since the serenity models are large and differ a lot in size.
+enum TriggerSource {
+    Message(Box<serenity::Message>),
+    Reaction(Box<serenity::Reaction>),
+    Thread(Box<serenity::GuildChannel>),
+    Role(serenity::GuildId),
+    Voice(serenity::GuildId),
+}
+
+impl Trigger {
+    pub fn from_message(guild: &serenity::Guild, message: serenity::Message) -> Self {
+        Self {
+            kind: TriggerKind::Message,
+            channel_id: Some(message.channel_id),
+            role_id: None,
+            user: message.author.clone(),
+            role_ids: message
+                .member
+                .as_ref()
+                .map(|member| member.roles.clone())
+                .unwrap_or_default(),
+            joined_at: message.member.as_ref().and_then(|member| member.joined_at),
+            permissions: message
+                .member
+                .as_ref()
+                .map(|member| guild.partial_member_permissions(message.author.id, member)),
+            content: message.content.clone(),
+            attachments: message.attachments.clone(),
+            timestamp: message.timestamp,
+            source: TriggerSource::Message(Box::new(message)),
+        }
+    }
+
+    /// The message that completed a burst of the same message across several channels.
+    pub fn from_burst(guild: &serenity::Guild, message: serenity::Message) -> Self {
+        let mut trigger = Self::from_message(guild, message);
+        trigger.kind = TriggerKind::Burst;
+        trigger
+    }
+
+    /// A message matching one of the guild's content rules.
+    pub fn from_content_rule(guild: &serenity::Guild, message: serenity::Message) -> Self {
+        let mut trigger = Self::from_message(guild, message);
+        trigger.kind = TriggerKind::ContentRule;
+        trigger
+    }
+
+    /// Returns `None` if the user that reacted can't be found.
+    pub async fn from_reaction(
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        reaction: serenity::Reaction,
+    ) -> Option<Self> {
+        let member = match &reaction.member {
+            Some(member) => member.clone(),
+            None => guild
+                .member(ctx, reaction.user_id?)
+                .await
+                .ok()?
+                .into_owned(),
+        };
+        Some(Self::from_member(
+            TriggerKind::Reaction,
+            guild,
+            Some(reaction.channel_id),
+            &member,
+            reaction.emoji.to_string(),
+            serenity::Timestamp::now(),
+            TriggerSource::Reaction(Box::new(reaction)),
+        ))
+    }
+
+    /// Returns `None` if the thread wasn't just created or its creator can't be found.
+    pub async fn from_thread(
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        thread: serenity::GuildChannel,
+    ) -> Option<Self> {
+        let now = serenity::Timestamp::now();
+        let created_at = thread
+            .thread_metadata
+            .and_then(|metadata| metadata.create_timestamp)
+            .unwrap_or(now);
+        if now.unix_timestamp() - created_at.unix_timestamp() > NEW_THREAD_MAX_AGE_SECS {
+            return None;
+        }
+        let member = guild.member(ctx, thread.owner_id?).await.ok()?.into_owned();
+        Some(Self::from_member(
+            TriggerKind::Thread,
+            guild,
+            Some(thread.parent_id?),
+            &member,
+            thread.name.clone(),
+            created_at,
+            TriggerSource::Thread(Box::new(thread)),
+        ))
+    }
+
+    /// Returns `None` unless the voice state is for the user joining a channel, or if the user
+    /// can't be found.
+    pub async fn from_voice_state(
+        ctx: &serenity::Context,
+        guild: &serenity::Guild,
+        old: Option<&serenity::VoiceState>,
+        new: &serenity::VoiceState,
+    ) -> Option<Self> {
+        let channel_id = new.channel_id?;
+        if old.and_then(|old| old.channel_id) == Some(channel_id) {
+            return None;
+        }
+        let member = match &new.member {
+            Some(member) => member.clone(),
+            None => guild.member(ctx, new.user_id).await.ok()?.into_owned(),
+        };
+        let channel_name = guild
+            .channels
+            .get(&channel_id)
+            .map(|channel| channel.name.clone())
+            .unwrap_or_default();
+        Some(Self::from_member(
+            TriggerKind::Voice,
+            guild,
+            Some(channel_id),
+            &member,
+            channel_name,
+            serenity::Timestamp::now(),
+            TriggerSource::Voice(guild.id),
+        ))
+    }
+
+    pub fn from_role(
+        guild: &serenity::Guild,
+        member: &serenity::Member,
+        role_id: serenity::RoleId,
+    ) -> Self {
+        let role_name = guild
+            .roles
+            .get(&role_id)
+            .map(|role| role.name.clone())
+            .unwrap_or_default();
+        let mut trigger = Self::from_member(
+            TriggerKind::Role,
+            guild,
+            None,
+            member,
+            role_name,
+            serenity::Timestamp::now(),
+            TriggerSource::Role(guild.id),
+        );
+        trigger.role_id = Some(role_id);
+        trigger
+    }
+
+    fn from_member(
+        kind: TriggerKind,
+        guild: &serenity::Guild,
+        channel_id: Option<serenity::ChannelId>,
+        member: &serenity::Member,
+        content: String,
+        timestamp: serenity::Timestamp,
+        source: TriggerSource,
+    ) -> Self {
+        Self {
+            kind,
+            channel_id,
+            role_id: None,
+            user: member.user.clone(),
+            role_ids: member.roles.clone(),
+            joined_at: member.joined_at,
+            permissions: Some(guild.member_permissions(member)),
+            content,
+            attachments: vec![],
+            timestamp,
+            source,
+        }
+    }
+
+    /// Mentions the honeypot channel or role.
+    pub fn location(&self) -> String {
+        match (self.channel_id, self.role_id) {
+            (Some(channel_id), _) => format!("<#{channel_id}>"),
+            (None, Some(role_id)) => format!("<@&{role_id}>"),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Deletes the message, reaction or thread that triggered the honeypot, takes back the
+    /// honeypot role, or disconnects the user from the voice channel.
+    pub async fn delete(&self, ctx: &serenity::Context) -> Result<(), serenity::Error> {
+        match &self.source {
+            TriggerSource::Message(message) => message.delete(ctx).await,
+            TriggerSource::Reaction(reaction) => reaction.delete(ctx).await,
+            TriggerSource::Thread(thread) => thread.delete(ctx).await.map(|_| ()),
+            TriggerSource::Role(guild_id) => match self.role_id {
+                Some(role_id) => {
+                    ctx.http
+                        .remove_member_role(*guild_id, self.user.id, role_id, None)
+                        .await
+                }
+                None => Ok(()),
+            },
+            TriggerSource::Voice(guild_id) => guild_id
+                .disconnect_member(ctx, self.user.id)
+                .await
+                .map(|_| ()),
+        }
+    }
+
+    /// Replies to the message, or posts in the channel, thread or voice channel's text chat for
+    /// other triggers. Role triggers don't happen in a channel, so the user is sent a DM instead.
+    pub async fn reply(
+        &self,
+        ctx: &serenity::Context,
+        content: String,
+    ) -> Result<(), serenity::Error> {
+        match &self.source {
+            TriggerSource::Message(message) => message.reply(ctx, content).await.map(|_| ()),
+            TriggerSource::Reaction(_) | TriggerSource::Voice(_) => match self.channel_id {
+                Some(channel_id) => channel_id.say(ctx, content).await.map(|_| ()),
+                None => Ok(()),
+            },
+            TriggerSource::Thread(thread) => thread.say(ctx, content).await.map(|_| ()),
+            TriggerSource::Role(_) => self
+                .user
+                .direct_message(ctx, serenity::CreateMessage::new().content(content))
+                .await
+                .map(|_| ()),
+        }
+    }
+}
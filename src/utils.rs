@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+/// Embed descriptions are limited to 4096 characters by Discord.
+const MAX_DESCRIPTION_LENGTH: usize = 4000;
+
+/// Formats a duration as a short human readable string, e.g. `1d 2h 30m`.
+pub fn format_duration(duration: Duration) -> String {
+    let total_secs = duration.as_secs();
+    let units = [
+        (total_secs / 86_400, "d"),
+        (total_secs % 86_400 / 3_600, "h"),
+        (total_secs % 3_600 / 60, "m"),
+        (total_secs % 60, "s"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(amount, _)| *amount > 0)
+        .map(|(amount, unit)| format!("{amount}{unit}"))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Replaces each `{name}` placeholder in `template` with its value.
+pub fn render_template(template: &str, placeholders: &[(&str, &str)]) -> String {
+    placeholders
+        .iter()
+        .fold(template.to_string(), |rendered, (name, value)| {
+            rendered.replace(&format!("{{{name}}}"), value)
+        })
+}
+
+/// Cuts a description down to what fits in an embed, marking where it was cut.
+pub fn truncate_description(mut description: String) -> String {
+    if description.len() > MAX_DESCRIPTION_LENGTH {
+        let mut end = MAX_DESCRIPTION_LENGTH;
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+        description.truncate(end);
+        description += "…";
+    }
+    description
+}